@@ -8,7 +8,9 @@
 //! semantics, the caching layer does *not* merely wrap [`IoProvider`]
 //! implementations. Instead, a cacheable bundle must implement the
 //! [`CacheBackend`] trait defined in this module. An example of such a bundle
-//! is the [`crate::itar::IndexedTarBackend`] for bundles served over HTTP.
+//! is the [`crate::itar::IndexedTarBackend`] for bundles served over HTTP, or
+//! the [`crate::itar::LocalIndexedTarBackend`] for the same format accessed
+//! through the local filesystem.
 //!
 //! In order to access a cacheable bundle, you need a handle to a local
 //! [`Cache`], probably obtained with [`Cache::get_user_default()`], and a URL,
@@ -18,12 +20,13 @@
 
 use fs2::FileExt;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     env,
     fs::{self, File},
     io::{BufRead, BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    time::{Duration, SystemTime},
 };
 use tectonic_errors::prelude::*;
 use tectonic_io_base::{
@@ -33,12 +36,17 @@ use tectonic_io_base::{
 };
 use tectonic_status_base::{tt_warning, StatusBackend};
 
-use crate::Bundle;
+use crate::{export::compute_bundle_digest, signature::SignaturePolicy, Bundle};
+
+/// The maximum number of files to request from a backend in one
+/// [`CacheBackend::get_files`] call.
+const FETCH_CHUNK_SIZE: usize = 256;
 
 /// A cache of data from one or more bundles using the local filesystem.
 #[derive(Debug)]
 pub struct Cache {
     root: PathBuf,
+    signature_policy: SignaturePolicy,
 }
 
 impl Cache {
@@ -63,12 +71,27 @@ impl Cache {
             None => app_dirs::ensure_user_cache_dir("")?,
         };
 
-        Ok(Cache { root: cache_path })
+        Ok(Cache {
+            root: cache_path,
+            signature_policy: SignaturePolicy::default(),
+        })
     }
 
     /// Get a handle to a bundle cache, using a custom cache directory.
     pub fn get_for_custom_directory<P: Into<PathBuf>>(root: P) -> Self {
-        Cache { root: root.into() }
+        Cache {
+            root: root.into(),
+            signature_policy: SignaturePolicy::default(),
+        }
+    }
+
+    /// Set the policy for checking the signatures of bundles opened through
+    /// this cache.
+    ///
+    /// By default, signatures are not checked.
+    pub fn set_signature_policy(&mut self, policy: SignaturePolicy) -> &mut Self {
+        self.signature_policy = policy;
+        self
     }
 
     /// Get the root directory of this cache.
@@ -89,10 +112,137 @@ impl Cache {
         only_cached: bool,
         status: &mut dyn StatusBackend,
     ) -> Result<CachingBundle<CB>> {
-        CachingBundle::new(url, only_cached, status, &self.root)
+        CachingBundle::new(
+            url,
+            only_cached,
+            self.signature_policy.clone(),
+            status,
+            &self.root,
+        )
+    }
+
+    /// Remove cached data that haven't been used recently.
+    ///
+    /// Every time that a bundle is opened through the cache, the digest of its
+    /// contents is marked as used. This method drops the cached information
+    /// about any bundle digests that haven't been used within *max_age*, as
+    /// well as any URLs that resolve to them. Cached file contents are stored
+    /// by their own digests and may be shared between several bundles. Rather
+    /// than keeping reference counts, we mark every file listed in the
+    /// manifest of a kept bundle, and delete the rest.
+    ///
+    /// This should not be run while other processes are using the cache.
+    pub fn gc(&self, max_age: Duration, status: &mut dyn StatusBackend) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        let now = SystemTime::now();
+
+        let index_base = ensure_cache_dir(&self.root, "indexes")?;
+        let manifest_base = ensure_cache_dir(&self.root, "manifests")?;
+        let resolved_base = ensure_cache_dir(&self.root, "redirects")?;
+        let signatures_base = ensure_cache_dir(&self.root, "signatures")?;
+        let urls_base = ensure_cache_dir(&self.root, "urls")?;
+        let data_base = ensure_cache_dir(&self.root, "files")?;
+
+        // Figure out which bundle digests are still live. A digest without an
+        // index can't be used without re-pulling everything, so it isn't live
+        // either.
+
+        let mut live_digests = HashSet::new();
+
+        for (digest_text, path) in list_txt_files(&index_base)? {
+            let last_used = fs::metadata(&path)?.modified()?;
+            let age = now.duration_since(last_used).unwrap_or_default();
+
+            if age <= max_age {
+                live_digests.insert(digest_text);
+            }
+        }
+
+        // Remove the data associated with non-live digests.
+
+        for base in &[
+            &index_base,
+            &manifest_base,
+            &resolved_base,
+            &signatures_base,
+        ] {
+            for (digest_text, path) in list_txt_files(base)? {
+                if live_digests.contains(&digest_text) {
+                    continue;
+                }
+
+                if *base == &index_base {
+                    report.n_digests += 1;
+                }
+
+                atry!(
+                    fs::remove_file(&path);
+                    ["failed to delete cache file `{}`", path.display()]
+                );
+            }
+        }
+
+        for entry in fs::read_dir(&urls_base)? {
+            let path = entry?.path();
+            let digest_text = fs::read_to_string(&path)?;
+
+            if !live_digests.contains(digest_text.trim()) {
+                atry!(
+                    fs::remove_file(&path);
+                    ["failed to delete cache file `{}`", path.display()]
+                );
+            }
+        }
+
+        // Now, all of the files that the live digests refer to.
+
+        let mut live_files = HashSet::new();
+
+        for digest_text in &live_digests {
+            let manifest_path = make_txt_path(&manifest_base, digest_text);
+
+            for info in load_manifest(&manifest_path, status)?.values() {
+                live_files.insert(info.digest.create_two_part_path(&data_base)?);
+            }
+        }
+
+        for entry in fs::read_dir(&data_base)? {
+            let subdir = entry?.path();
+
+            if !subdir.is_dir() {
+                continue;
+            }
+
+            for entry in fs::read_dir(&subdir)? {
+                let path = entry?.path();
+
+                if !live_files.contains(&path) {
+                    report.n_bytes += remove_data_file(&path)?;
+                    report.n_files += 1;
+                }
+            }
+
+            // This will fail if the directory isn't empty, which is fine.
+            let _ = fs::remove_dir(&subdir);
+        }
+
+        Ok(report)
     }
 }
 
+/// The results of cleaning up a cache with [`Cache::gc`].
+#[derive(Clone, Debug, Default)]
+pub struct GcReport {
+    /// The number of bundle digests whose cached data were dropped.
+    pub n_digests: usize,
+
+    /// The number of cached files that were deleted.
+    pub n_files: usize,
+
+    /// The total size of the deleted files, in bytes.
+    pub n_bytes: u64,
+}
+
 /// Information describing a cache backend.
 ///
 /// This type is returned by a [`CacheBackend`] on a "pull", a first-time
@@ -181,15 +331,52 @@ pub trait CacheBackend: Sized {
         info: &Self::FileInfo,
         status: &mut dyn StatusBackend,
     ) -> Result<Vec<u8>>;
+
+    /// Get the digest of a file’s contents from its index information, if
+    /// known.
+    ///
+    /// If the digest is known, and the cache already contains a file with that
+    /// digest, perhaps obtained for a different bundle, the cache will use it
+    /// rather than fetching the file from the backend again. The default
+    /// implementation returns `None`.
+    fn file_digest(_info: &Self::FileInfo) -> Option<DigestData> {
+        None
+    }
+
+    /// Obtain the signature of the bundle.
+    ///
+    /// This should return the contents of the bundle’s signature file, as
+    /// described in [`crate::signature`]. It is only called if the cache has
+    /// been configured to check signatures. The default implementation returns
+    /// an error, since not all backends can provide signatures.
+    fn get_signature(&mut self, _status: &mut dyn StatusBackend) -> Result<String> {
+        bail!("this kind of bundle does not support signatures");
+    }
+
+    /// Obtain several files from the backend at once.
+    ///
+    /// The return value contains the result for each requested file, in the
+    /// same order as *files*. Backends that can retrieve files more
+    /// efficiently in bulk, for instance by combining or parallelizing network
+    /// requests, should override this method. The default implementation just
+    /// calls [`Self::get_file`] for each file in turn.
+    fn get_files(
+        &mut self,
+        files: &[(String, Self::FileInfo)],
+        status: &mut dyn StatusBackend,
+    ) -> Vec<Result<Vec<u8>>> {
+        files
+            .iter()
+            .map(|(name, info)| self.get_file(name, info, status))
+            .collect()
+    }
 }
 
 /// Information about a cached file.
 #[derive(Clone, Copy, Debug)]
 struct CachedFileInfo {
     /// The length of the file in bytes.
-    ///
-    /// This field isn't currently used, but seems handy to keep around.
-    _length: u64,
+    length: u64,
 
     /// The digest of the file contents.
     ///
@@ -287,6 +474,20 @@ pub struct CachingBundle<CB: CacheBackend> {
     /// This directory contains the actual cached file contents, in a directory
     /// structured based on the digest of each file’s content.
     data_base: PathBuf,
+
+    /// The path to a file containing a verified signature of the backend’s
+    /// content digest.
+    ///
+    /// Saving the signature allows us to check it again without connecting to
+    /// the backend. The actual file path is based on the content digest.
+    signature_path: PathBuf,
+
+    /// Which signatures we trust for the backend’s content digest.
+    signature_policy: SignaturePolicy,
+
+    /// Whether the backend’s content digest has been found to carry a valid
+    /// signature.
+    signature_verified: bool,
 }
 
 /// A locally-cached analogue of [`BackendPullData`].
@@ -309,6 +510,7 @@ impl<CB: CacheBackend> CachingBundle<CB> {
     fn new(
         start_url: &str,
         only_cached: bool,
+        signature_policy: SignaturePolicy,
         status: &mut dyn StatusBackend,
         cache_root: &Path,
     ) -> Result<Self> {
@@ -319,6 +521,7 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         let index_base = ensure_cache_dir(cache_root, "indexes")?;
         let manifest_base = ensure_cache_dir(cache_root, "manifests")?;
         let data_base = ensure_cache_dir(cache_root, "files")?;
+        let signatures_base = ensure_cache_dir(cache_root, "signatures")?;
 
         // The whole point of this cache is to avoid connecting to the backend
         // if at all possible. So we first see if we have cached the "pull data"
@@ -361,69 +564,17 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // Now that we have the backend content digest, we know which manifest
         // to use. Read it in, if it exists.
 
-        let manifest_path = make_txt_path(&manifest_base, &cached_digest.to_string());
-        let mut contents = HashMap::new();
+        let digest_text = cached_digest.to_string();
+        let manifest_path = make_txt_path(&manifest_base, &digest_text);
+        let contents = load_manifest(&manifest_path, status)?;
 
-        match try_open_file(&manifest_path) {
-            OpenResult::NotAvailable => {}
-            OpenResult::Err(e) => {
-                return Err(e);
-            }
-            OpenResult::Ok(mfile) => {
-                // Note that the lock is released when the file is closed,
-                // which is good since BufReader::new() and BufReader::lines()
-                // consume their objects.
-                if let Err(e) = mfile.lock_shared() {
-                    tt_warning!(status, "failed to lock manifest file \"{}\" for reading; this might be fine",
-                                manifest_path.display(); e.into());
-                }
+        // Record that this digest is in use, so that garbage collection of the
+        // cache will leave its data alone. This isn't essential, so we don't
+        // complain if it fails (e.g., because the cache is read-only).
 
-                let f = BufReader::new(mfile);
-
-                for res in f.lines() {
-                    let line = res?;
-                    let mut bits = line.rsplitn(3, ' ');
-
-                    let (original_name, length, digest) =
-                        match (bits.next(), bits.next(), bits.next(), bits.next()) {
-                            (Some(s), Some(t), Some(r), None) => (r, t, s),
-                            _ => continue,
-                        };
-
-                    let name = original_name.to_owned();
-
-                    let length = match length.parse::<u64>() {
-                        Ok(l) => l,
-                        Err(_) => continue,
-                    };
-
-                    let digest = if digest == "-" {
-                        continue;
-                    } else {
-                        match DigestData::from_str(digest) {
-                            Ok(d) => d,
-                            Err(e) => {
-                                tt_warning!(status, "ignoring bad digest data \"{}\" for \"{}\" in \"{}\"",
-                                            &digest, original_name, manifest_path.display() ; e);
-                                continue;
-                            }
-                        }
-                    };
-
-                    contents.insert(
-                        name,
-                        CachedFileInfo {
-                            _length: length,
-                            digest,
-                        },
-                    );
-                }
-            }
-        }
-
-        // All set.
+        let _ = mark_used(&make_txt_path(&index_base, &digest_text));
 
-        Ok(CachingBundle {
+        let mut bundle = CachingBundle {
             start_url: start_url.to_owned(),
             resolved_url: cached_pull_data.resolved_url,
             digest_path,
@@ -435,7 +586,111 @@ impl<CB: CacheBackend> CachingBundle<CB> {
             only_cached,
             backend,
             index: cached_pull_data.index,
-        })
+            signature_path: make_txt_path(&signatures_base, &digest_text),
+            signature_policy,
+            signature_verified: false,
+        };
+
+        // If we need to check the bundle's signature, do so before anything
+        // gets used. If we verified it previously, we don't need to go to the
+        // backend.
+
+        if bundle.signature_policy.is_enabled() && !bundle.load_saved_signature() {
+            if only_cached {
+                bail!(
+                    "the signature of bundle `{}` has not been verified yet, which \
+                    can't be done in cache-only mode",
+                    start_url
+                );
+            }
+
+            bundle.ensure_backend_validity(status)?;
+        }
+
+        if bundle.signature_policy.is_enabled() {
+            atry!(
+                bundle.verify_index();
+                ["refusing to use untrusted bundle `{}`", start_url]
+            );
+        }
+
+        // All set.
+
+        Ok(bundle)
+    }
+
+    /// Check the bundle signature saved in the cache, if any. Returns true if
+    /// it exists and is valid.
+    fn load_saved_signature(&mut self) -> bool {
+        if let Ok(text) = fs::read_to_string(&self.signature_path) {
+            if self
+                .signature_policy
+                .verify(&self.cached_digest, &text)
+                .is_ok()
+            {
+                self.signature_verified = true;
+            }
+        }
+
+        self.signature_verified
+    }
+
+    /// Obtain the bundle signature from the backend, if our policy requires
+    /// it, and make sure that it is valid.
+    ///
+    /// After this function returns successfully, the bundle contents can be
+    /// trusted. The backend must be connected.
+    fn verify_backend_signature(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
+        if self.signature_verified || !self.signature_policy.is_enabled() {
+            return Ok(());
+        }
+
+        let text = atry!(
+            self.backend.as_mut().unwrap().get_signature(status);
+            ["failed to obtain the signature of bundle `{}`", self.start_url]
+        );
+
+        atry!(
+            self.signature_policy.verify(&self.cached_digest, &text);
+            ["refusing to use untrusted bundle `{}`", self.start_url]
+        );
+
+        // Saving the signature is just an optimization, so failure is OK.
+        let _ = file_create_write(&self.signature_path, |f| f.write_all(text.as_bytes()));
+        self.signature_verified = true;
+        Ok(())
+    }
+
+    /// Check that the file digests given in the backend index are the ones
+    /// that the bundle's content digest was computed from.
+    ///
+    /// Once the content digest's signature has been verified, this makes the
+    /// index trustworthy too, and [`Self::store_file`] checks every file
+    /// obtained from the backend against it. So, signed bundles must have
+    /// indexes that give the digests of all of their files.
+    fn verify_index(&self) -> Result<()> {
+        let mut digests = Vec::with_capacity(self.index.len());
+
+        for (name, info) in &self.index {
+            if name == digest::DIGEST_NAME {
+                continue;
+            }
+
+            let d = a_ok_or!(
+                CB::file_digest(info);
+                ["the bundle index doesn't give the digest of `{}`, so it can't be verified", name]
+            );
+            digests.push((name.as_str(), d));
+        }
+
+        if compute_bundle_digest(digests.iter().map(|(n, d)| (*n, d))) != self.cached_digest {
+            bail!(
+                "the file digests in the bundle index don't match the bundle's \
+                signed digest; the bundle may have been tampered with"
+            );
+        }
+
+        Ok(())
     }
 
     /// Save data about a file to our local cache manifest.
@@ -464,32 +719,37 @@ impl<CB: CacheBackend> CachingBundle<CB> {
             writeln!(man, "{name} {length} {digest_text}")?;
         }
 
-        self.contents.insert(
-            name.to_owned(),
-            CachedFileInfo {
-                _length: length,
-                digest,
-            },
-        );
+        self.contents
+            .insert(name.to_owned(), CachedFileInfo { length, digest });
 
         Ok(())
     }
 
     /// Ensure that the backend is connected and valid.
     ///
-    /// Here we do a "quick check" to see if the backend's digest is what we
-    /// expect. If not, we do a lame thing where we error out but set things up
-    /// so that things should succeed if the program is re-run. Exactly the lame
-    /// TeX user experience that I've been trying to avoid!
+    /// If our signature policy requires it, this also checks that the
+    /// backend's content digest carries a valid signature.
     ///
     /// After this function has been called, you can assume that `self.backend`
     /// is Some.
     fn ensure_backend_validity(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
         // If backend is Some, we already have a validated connection to it.
-        if self.backend.is_some() {
-            return Ok(());
+        if self.backend.is_none() {
+            self.connect_backend(status)?;
         }
 
+        // Before anything else happens, make sure that the bundle is
+        // authentic, if we've been asked to.
+        self.verify_backend_signature(status)
+    }
+
+    /// Connect to the backend.
+    ///
+    /// Here we do a "quick check" to see if the backend's digest is what we
+    /// expect. If not, we do a lame thing where we error out but set things up
+    /// so that things should succeed if the program is re-run. Exactly the lame
+    /// TeX user experience that I've been trying to avoid!
+    fn connect_backend(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
         // Do the quick check. If anything goes wrong, eat the error and try a
         // fresh pull.
         if let Some(info) = self.index.get(digest::DIGEST_NAME) {
@@ -552,17 +812,24 @@ impl<CB: CacheBackend> CachingBundle<CB> {
             };
         }
 
-        // No, it's not. Are we in cache-only mode?
-        if self.only_cached {
-            return OpenResult::NotAvailable;
-        }
-
-        // Is the file in the backend at all?
+        // No, it's not. Is the file in the backend at all?
         let info = match self.index.get(name).cloned() {
             Some(info) => info,
             None => return OpenResult::NotAvailable,
         };
 
+        // It is. Maybe we've already got its contents from another bundle?
+        match self.adopt_shared_file(name, &info) {
+            Ok(Some(p)) => return OpenResult::Ok(p),
+            Ok(None) => {}
+            Err(e) => return OpenResult::Err(e),
+        }
+
+        // No luck. Are we in cache-only mode?
+        if self.only_cached {
+            return OpenResult::NotAvailable;
+        }
+
         // Yes, it is. Time to fetch it! In order to do that, we need to ensure
         // that we have a valid backend connection.
         if let Err(e) = self.ensure_backend_validity(status) {
@@ -582,16 +849,64 @@ impl<CB: CacheBackend> CachingBundle<CB> {
             Err(e) => return OpenResult::Err(e),
         };
 
-        let length = content.len();
+        match self.store_file(name, &content, CB::file_digest(&info)) {
+            Ok(p) => OpenResult::Ok(p),
+            Err(e) => OpenResult::Err(e),
+        }
+    }
+
+    /// Try to add a file to this bundle's cache without contacting the backend.
+    ///
+    /// Cached file contents are stored by their digests, and shared between
+    /// all of the bundles in the cache. So if the backend index tells us the
+    /// digest of a file, and the cache already has data with that digest, we
+    /// can just record the file in our manifest. If so, the return value is the
+    /// file's path.
+    fn adopt_shared_file(&mut self, name: &str, info: &CB::FileInfo) -> Result<Option<PathBuf>> {
+        let digest = match CB::file_digest(info) {
+            Some(d) => d,
+            None => return Ok(None),
+        };
+
+        let path = digest.create_two_part_path(&self.data_base)?;
+
+        let length = match fs::metadata(&path) {
+            Ok(md) => md.len(),
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        self.save_to_manifest(name, length, digest)?;
+        Ok(Some(path))
+    }
 
+    /// Save a file obtained from the backend into the cache, and return its
+    /// filesystem path.
+    ///
+    /// If the backend index gives the file's digest as *expected_digest*, the
+    /// contents must match it; otherwise they are rejected.
+    fn store_file(
+        &mut self,
+        name: &str,
+        content: &[u8],
+        expected_digest: Option<DigestData>,
+    ) -> Result<PathBuf> {
         let mut digest_builder = digest::create();
-        digest_builder.update(&content);
+        digest_builder.update(content);
         let digest = DigestData::from(digest_builder);
 
-        let final_path = match digest.create_two_part_path(&self.data_base) {
-            Ok(p) => p,
-            Err(e) => return OpenResult::Err(e),
-        };
+        if let Some(expected) = expected_digest {
+            if digest != expected {
+                bail!(
+                    "the contents of `{}` obtained from bundle `{}` don't match the digest \
+                    in its index; the bundle may have been tampered with",
+                    name,
+                    self.start_url
+                );
+            }
+        }
+
+        let final_path = digest.create_two_part_path(&self.data_base)?;
 
         // Perform a racy check for the destination existing, because this
         // matters on Windows: if the destination is already there, we'll get
@@ -600,36 +915,218 @@ impl<CB: CacheBackend> CachingBundle<CB> {
         // subject to the race once.
 
         if !final_path.exists() {
-            if let Err(e) = file_create_write(&final_path, |f| f.write_all(&content)) {
-                return OpenResult::Err(e);
-            }
+            file_create_write(&final_path, |f| f.write_all(content))?;
 
             // Now we can make the file readonly. It would be nice to set the
             // permissions using the already-open file handle owned by the
             // tempfile, but mkstemp doesn't give us access.
-            let mut perms = match fs::metadata(&final_path) {
-                Ok(p) => p,
-                Err(e) => {
-                    return OpenResult::Err(e.into());
-                }
-            }
-            .permissions();
+            let mut perms = fs::metadata(&final_path)?.permissions();
             perms.set_readonly(true);
-
-            if let Err(e) = fs::set_permissions(&final_path, perms) {
-                return OpenResult::Err(e.into());
-            }
+            fs::set_permissions(&final_path, perms)?;
         }
 
         // And finally add a record of this file to our manifest. Note that
         // we're opening and closing the manifest every time we cache a new
         // file; not so efficient, but whatever.
 
-        if let Err(e) = self.save_to_manifest(name, length as u64, digest) {
-            return OpenResult::Err(e);
+        self.save_to_manifest(name, content.len() as u64, digest)?;
+        Ok(final_path)
+    }
+
+    /// Fetch a group of files from the backend into the cache.
+    ///
+    /// Names that are already cached, or aren't in the bundle at all, are
+    /// ignored. The rest are requested from the backend in batches using
+    /// [`CacheBackend::get_files`], which is much faster than fetching them
+    /// one at a time for backends that support it. The return value is the
+    /// number of files that were newly cached. If any file can't be fetched,
+    /// the others are still saved, and the first error is returned.
+    fn fetch_files<I>(&mut self, names: I, status: &mut dyn StatusBackend) -> Result<usize>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        if self.only_cached {
+            bail!("cannot fetch bundle files in cache-only mode");
         }
 
-        OpenResult::Ok(final_path)
+        let mut candidates: Vec<_> = names
+            .into_iter()
+            .filter(|n| !self.contents.contains_key(n))
+            .filter_map(|n| self.index.get(&n).cloned().map(|i| (n, i)))
+            .collect();
+        candidates.sort_by(|a, b| a.0.cmp(&b.0));
+        candidates.dedup_by(|a, b| a.0 == b.0);
+
+        let mut n_fetched = 0;
+        let mut requests = Vec::with_capacity(candidates.len());
+
+        for (name, info) in candidates {
+            if self.adopt_shared_file(&name, &info)?.is_some() {
+                n_fetched += 1;
+            } else {
+                requests.push((name, info));
+            }
+        }
+
+        if requests.is_empty() {
+            return Ok(n_fetched);
+        }
+
+        self.ensure_backend_validity(status)?;
+
+        let mut first_error = None;
+
+        // Limit how much data we hold in memory at once.
+        for chunk in requests.chunks(FETCH_CHUNK_SIZE) {
+            let results = self.backend.as_mut().unwrap().get_files(chunk, status);
+
+            for ((name, info), result) in chunk.iter().zip(results) {
+                let expected_digest = CB::file_digest(info);
+
+                match result.and_then(|content| self.store_file(name, &content, expected_digest)) {
+                    Ok(_) => n_fetched += 1,
+                    Err(e) => {
+                        if first_error.is_none() {
+                            first_error = Some(e.context(format!("failed to fetch \"{name}\"")));
+                        }
+                    }
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(n_fetched),
+        }
+    }
+
+    /// Rewrite the local cache manifest to match [`Self::contents`].
+    fn rewrite_manifest(&mut self) -> Result<()> {
+        let mut man = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .open(&self.manifest_path)?;
+
+        atry!(
+            man.lock_exclusive();
+            ["failed to lock manifest file \"{}\" for writing", self.manifest_path.display()]
+        );
+
+        // Only truncate once we hold the lock.
+        man.set_len(0)?;
+
+        let mut names: Vec<_> = self.contents.keys().collect();
+        names.sort();
+
+        for name in names {
+            let info = &self.contents[name];
+            writeln!(man, "{} {} {}", name, info.length, info.digest)?;
+        }
+
+        Ok(())
+    }
+
+    /// Download every file in the bundle into the local cache.
+    ///
+    /// This allows the bundle to be used offline later on, with no worries
+    /// about whether a document might need a file that hasn't been cached yet.
+    /// The return value is the number of files that were newly downloaded. For
+    /// large bundles this can take a long time and consume gigabytes of disk
+    /// space.
+    pub fn prefetch_all(&mut self, status: &mut dyn StatusBackend) -> Result<usize> {
+        if self.only_cached {
+            bail!("cannot prefetch bundle files in cache-only mode");
+        }
+
+        let names: Vec<_> = self.index.keys().cloned().collect();
+        self.fetch_files(names, status)
+    }
+
+    /// Check the locally cached files against the digests recorded in the
+    /// cache manifest.
+    ///
+    /// Every cached file is read back and re-hashed. If *repair* is true, any
+    /// files that are missing or corrupt are removed from the cache and its
+    /// manifest so that they will be downloaded afresh the next time that
+    /// they're needed.
+    pub fn verify(&mut self, repair: bool, status: &mut dyn StatusBackend) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        let mut names: Vec<_> = self.contents.keys().cloned().collect();
+        names.sort();
+
+        for name in names {
+            let info = self.contents[&name];
+            let path = info.digest.create_two_part_path(&self.data_base)?;
+            report.n_checked += 1;
+
+            let mut f = match File::open(&path) {
+                Ok(f) => f,
+                Err(ref e) if e.kind() == IoErrorKind::NotFound => {
+                    tt_warning!(status, "cached data for \"{}\" are missing", name);
+                    report.missing.push(name);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(e.into());
+                }
+            };
+
+            let mut digest_builder = digest::create();
+            atry!(
+                std::io::copy(&mut f, &mut digest_builder);
+                ["failed to read `{}`", path.display()]
+            );
+
+            if DigestData::from(digest_builder) != info.digest {
+                tt_warning!(status, "cached data for \"{}\" are corrupt", name);
+                report.corrupt.push(name);
+            }
+        }
+
+        if repair && !report.is_ok() {
+            for name in &report.corrupt {
+                let path = self.contents[name]
+                    .digest
+                    .create_two_part_path(&self.data_base)?;
+
+                // Several names may share the same data file, so it may have
+                // already been removed.
+                if path.exists() {
+                    remove_data_file(&path)?;
+                }
+            }
+
+            for name in report.missing.iter().chain(report.corrupt.iter()) {
+                self.contents.remove(name);
+            }
+
+            self.rewrite_manifest()?;
+        }
+
+        Ok(report)
+    }
+}
+
+/// The results of checking a cache with [`CachingBundle::verify`].
+#[derive(Clone, Debug, Default)]
+pub struct VerifyReport {
+    /// The number of cached files that were checked.
+    pub n_checked: usize,
+
+    /// The names of files whose cached data have gone missing.
+    pub missing: Vec<String>,
+
+    /// The names of files whose cached data don't match their recorded
+    /// digests.
+    pub corrupt: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Returns true if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
     }
 }
 
@@ -669,6 +1166,47 @@ impl<CB: CacheBackend> Bundle for CachingBundle<CB> {
         }
         Ok(self.index.keys().cloned().collect())
     }
+
+    fn file_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<DigestData>> {
+        // If the file is cached, or the index records its digest, we don't
+        // need to fetch it.
+        if let Some(info) = self.contents.get(name) {
+            return Ok(Some(info.digest));
+        }
+
+        if let Some(d) = self.index.get(name).and_then(CB::file_digest) {
+            return Ok(Some(d));
+        }
+
+        match self.ensure_file_availability(name, status) {
+            OpenResult::Ok(_) => Ok(self.contents.get(name).map(|info| info.digest)),
+            OpenResult::NotAvailable => Ok(None),
+            OpenResult::Err(e) => Err(e),
+        }
+    }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        // In cache-only mode, there's nothing we can do.
+        if !self.only_cached {
+            self.fetch_files(names.iter().cloned(), status)?;
+        }
+        Ok(())
+    }
+
+    fn prefetch_digests(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        // Files whose digests are recorded in the index don't need to be
+        // fetched.
+        let needed: Vec<_> = names
+            .iter()
+            .filter(|n| self.index.get(*n).and_then(CB::file_digest).is_none())
+            .cloned()
+            .collect();
+        self.prefetch(&needed, status)
+    }
 }
 
 /// Load the cached "pull" data for a backend.
@@ -729,6 +1267,96 @@ fn load_cached_pull_data<CB: CacheBackend>(
     }
 }
 
+/// Load a cache manifest file.
+///
+/// Each line of the manifest gives a file name, its length, and the digest of
+/// its contents. Malformed lines are skipped. If the manifest doesn't exist,
+/// the cache is empty.
+fn load_manifest(
+    manifest_path: &Path,
+    status: &mut dyn StatusBackend,
+) -> Result<HashMap<String, CachedFileInfo>> {
+    let mut contents = HashMap::new();
+
+    let mfile = match try_open_file(manifest_path) {
+        OpenResult::NotAvailable => return Ok(contents),
+        OpenResult::Err(e) => return Err(e),
+        OpenResult::Ok(f) => f,
+    };
+
+    // Note that the lock is released when the file is closed, which is good
+    // since BufReader::new() and BufReader::lines() consume their objects.
+    if let Err(e) = mfile.lock_shared() {
+        tt_warning!(status, "failed to lock manifest file \"{}\" for reading; this might be fine",
+                    manifest_path.display(); e.into());
+    }
+
+    let f = BufReader::new(mfile);
+
+    for res in f.lines() {
+        let line = res?;
+        let mut bits = line.rsplitn(3, ' ');
+
+        let (original_name, length, digest) =
+            match (bits.next(), bits.next(), bits.next(), bits.next()) {
+                (Some(s), Some(t), Some(r), None) => (r, t, s),
+                _ => continue,
+            };
+
+        let name = original_name.to_owned();
+
+        let length = match length.parse::<u64>() {
+            Ok(l) => l,
+            Err(_) => continue,
+        };
+
+        let digest = if digest == "-" {
+            continue;
+        } else {
+            match DigestData::from_str(digest) {
+                Ok(d) => d,
+                Err(e) => {
+                    tt_warning!(status, "ignoring bad digest data \"{}\" for \"{}\" in \"{}\"",
+                                &digest, original_name, manifest_path.display() ; e);
+                    continue;
+                }
+            }
+        };
+
+        contents.insert(name, CachedFileInfo { length, digest });
+    }
+
+    Ok(contents)
+}
+
+/// Update the modification time of a file to mark that it has been used.
+fn mark_used(path: &Path) -> Result<()> {
+    let f = fs::OpenOptions::new().append(true).open(path)?;
+    f.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Remove a file from the cache data directory.
+///
+/// Cached data files are made read-only when they're created, which prevents
+/// their deletion on Windows, so we have to undo that first.
+fn remove_data_file(path: &Path) -> Result<u64> {
+    let meta = fs::metadata(path)?;
+    let mut perms = meta.permissions();
+
+    if perms.readonly() {
+        #[allow(clippy::permissions_set_readonly_false)]
+        perms.set_readonly(false);
+        fs::set_permissions(path, perms)?;
+    }
+
+    atry!(
+        fs::remove_file(path);
+        ["failed to delete cached file `{}`", path.display()]
+    );
+    Ok(meta.len())
+}
+
 /// A convenience method to provide a better error message when writing to a created file.
 fn file_create_write<P, F, E>(path: P, write_fn: F) -> Result<()>
 where
@@ -758,7 +1386,432 @@ fn ensure_cache_dir(root: &Path, path: &str) -> Result<PathBuf> {
     Ok(full_path)
 }
 
+/// List the `.txt` files in a cache directory, along with their stems.
+fn list_txt_files(base: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(base)? {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|e| e == "txt") {
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                files.push((stem.to_owned(), path.clone()));
+            }
+        }
+    }
+
+    Ok(files)
+}
+
 /// Convenience to generate a text filename
 fn make_txt_path(base: &Path, name: &str) -> PathBuf {
     base.join(name).with_extension("txt")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{export::compute_bundle_digest, itar::LocalIndexedTarBackend};
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::{Cursor, Write};
+    use tectonic_status_base::NoopStatusBackend;
+
+    /// Write an indexed tar bundle containing the given files, returning its
+    /// `file:` URL. If *with_digests* is true, the index includes the digests
+    /// of the files.
+    fn make_indexed_tar(dir: &Path, files: &[(&str, &[u8])], with_digests: bool) -> String {
+        let digests: Vec<_> = files
+            .iter()
+            .map(|(n, d)| {
+                let mut dc = digest::create();
+                dc.update(d);
+                (*n, DigestData::from(dc))
+            })
+            .collect();
+        let bundle_digest = compute_bundle_digest(digests.iter().map(|(n, d)| (*n, d)));
+        let digest_text = bundle_digest.to_string();
+
+        let mut builder = ::tar::Builder::new(Vec::new());
+
+        for (name, data) in files.iter().copied().chain(std::iter::once((
+            digest::DIGEST_NAME,
+            digest_text.as_bytes(),
+        ))) {
+            let mut header = ::tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+
+        let data = builder.into_inner().unwrap();
+        let mut archive = ::tar::Archive::new(Cursor::new(&data));
+        let mut index = GzEncoder::new(Vec::new(), Compression::default());
+
+        for entry in archive.entries_with_seek().unwrap() {
+            let mut entry = entry.unwrap();
+            write!(
+                index,
+                "{} {} {}",
+                entry.path().unwrap().display(),
+                entry.raw_file_position(),
+                entry.size()
+            )
+            .unwrap();
+
+            if with_digests {
+                let mut contents = Vec::new();
+                entry.read_to_end(&mut contents).unwrap();
+                let mut dc = digest::create();
+                dc.update(&contents);
+                write!(index, " {}", DigestData::from(dc)).unwrap();
+            }
+
+            writeln!(index).unwrap();
+        }
+
+        let tar_path = dir.join("bundle.tar");
+        fs::write(&tar_path, &data).unwrap();
+        fs::write(dir.join("bundle.tar.index.gz"), index.finish().unwrap()).unwrap();
+        url::Url::from_file_path(&tar_path).unwrap().to_string()
+    }
+
+    fn read_file(bundle: &mut dyn Bundle, name: &str) -> Option<String> {
+        let mut status = NoopStatusBackend::default();
+
+        match bundle.input_open_name(name, &mut status) {
+            OpenResult::Ok(mut ih) => {
+                let mut s = String::new();
+                ih.read_to_string(&mut s).unwrap();
+                Some(s)
+            }
+            OpenResult::NotAvailable => None,
+            OpenResult::Err(e) => panic!("failed to open `{}`: {}", name, e),
+        }
+    }
+
+    #[test]
+    fn local_backend() {
+        let bundle_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = make_indexed_tar(
+            bundle_dir.path(),
+            &[("a.tex", b"alpha"), ("b.sty", b"beta!")],
+            false,
+        );
+
+        let mut status = NoopStatusBackend::default();
+        let mut cache = Cache::get_for_custom_directory(cache_dir.path());
+
+        let tar_path = bundle_dir.path().join("bundle.tar");
+        assert!(LocalIndexedTarBackend::can_open(&tar_path));
+        assert!(!LocalIndexedTarBackend::can_open(bundle_dir.path()));
+
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+        assert_eq!(read_file(&mut bundle, "c.cls"), None);
+
+        // Once the backing file is gone, cached files are still available in
+        // cache-only mode, but uncached ones aren't.
+        fs::remove_file(&tar_path).unwrap();
+        assert!(!LocalIndexedTarBackend::can_open(&tar_path));
+
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url, true, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+        assert_eq!(read_file(&mut bundle, "a.tex"), None);
+    }
+
+    #[test]
+    fn shared_files() {
+        let dir_1 = tempfile::tempdir().unwrap();
+        let dir_2 = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url_1 = make_indexed_tar(
+            dir_1.path(),
+            &[("a.tex", b"alpha"), ("b.sty", b"beta!")],
+            true,
+        );
+        let url_2 = make_indexed_tar(
+            dir_2.path(),
+            &[("b.sty", b"beta!"), ("c.cls", b"gamma")],
+            true,
+        );
+
+        let mut status = NoopStatusBackend::default();
+        let mut cache = Cache::get_for_custom_directory(cache_dir.path());
+
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url_1, false, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+
+        // Connect to the second bundle so that its index is cached, then make
+        // its data unavailable. Its copy of `b.sty` can still be obtained,
+        // since the cache already has the same contents from the first bundle.
+        cache
+            .open::<LocalIndexedTarBackend>(&url_2, false, &mut status)
+            .unwrap();
+        fs::remove_file(dir_2.path().join("bundle.tar")).unwrap();
+
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url_2, true, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+        assert_eq!(read_file(&mut bundle, "c.cls"), None);
+    }
+
+    #[test]
+    fn signatures() {
+        use crate::signature::SIGNATURE_SUFFIX;
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let bundle_dir = tempfile::tempdir().unwrap();
+        let url = make_indexed_tar(bundle_dir.path(), &[("a.tex", b"alpha")], true);
+        let mut status = NoopStatusBackend::default();
+
+        let mut unchecked_cache = Cache::get_for_custom_directory(bundle_dir.path().join("c0"));
+        let digest = unchecked_cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .unwrap()
+            .get_digest(&mut status)
+            .unwrap();
+
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let mut policy = SignaturePolicy::default();
+        policy
+            .add_trusted_key(&digest::bytes_to_hex(key.verifying_key().as_bytes()))
+            .unwrap();
+
+        // Unsigned bundles are rejected.
+        let mut cache = Cache::get_for_custom_directory(bundle_dir.path().join("c1"));
+        cache.set_signature_policy(policy.clone());
+        assert!(cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .is_err());
+
+        // So are ones signed with the wrong key.
+        let sig_path = bundle_dir
+            .path()
+            .join(format!("bundle.tar{SIGNATURE_SUFFIX}"));
+        let other_key = SigningKey::from_bytes(&[2; 32]);
+        let sig = other_key.sign(digest.as_bytes()).to_bytes();
+        fs::write(&sig_path, digest::bytes_to_hex(&sig)).unwrap();
+
+        let mut cache = Cache::get_for_custom_directory(bundle_dir.path().join("c2"));
+        cache.set_signature_policy(policy.clone());
+        assert!(cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .is_err());
+
+        // But correctly signed ones are fine, and the signature is remembered.
+        let sig = key.sign(digest.as_bytes()).to_bytes();
+        fs::write(&sig_path, digest::bytes_to_hex(&sig)).unwrap();
+
+        let mut cache = Cache::get_for_custom_directory(bundle_dir.path().join("c3"));
+        cache.set_signature_policy(policy.clone());
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "a.tex").as_deref(), Some("alpha"));
+
+        fs::remove_file(&sig_path).unwrap();
+        cache
+            .open::<LocalIndexedTarBackend>(&url, true, &mut status)
+            .unwrap();
+
+        // A signed bundle whose index doesn't give the file digests is
+        // rejected, since its files can't be verified.
+        let url = make_indexed_tar(bundle_dir.path(), &[("a.tex", b"alpha")], false);
+        fs::write(&sig_path, digest::bytes_to_hex(&sig)).unwrap();
+
+        let mut cache = Cache::get_for_custom_directory(bundle_dir.path().join("c4"));
+        cache.set_signature_policy(policy);
+        let err = cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .err()
+            .unwrap();
+        assert!(format!("{err:?}").contains("doesn't give the digest of `a.tex`"));
+    }
+
+    #[test]
+    fn tampered_files() {
+        let bundle_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = make_indexed_tar(
+            bundle_dir.path(),
+            &[("a.tex", b"alpha"), ("b.sty", b"beta!")],
+            true,
+        );
+
+        let mut status = NoopStatusBackend::default();
+        let mut cache = Cache::get_for_custom_directory(cache_dir.path());
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .unwrap();
+
+        // Change the file contents after the index has been obtained.
+        let tar_path = bundle_dir.path().join("bundle.tar");
+        let mut data = fs::read(&tar_path).unwrap();
+
+        for (orig, tampered) in [(b"alpha", b"ALPHA"), (b"beta!", b"BETA!")] {
+            let pos = data.windows(5).position(|w| w == orig).unwrap();
+            data[pos..pos + 5].copy_from_slice(tampered);
+        }
+
+        fs::write(&tar_path, &data).unwrap();
+
+        // The tampered files are rejected whether they're fetched one by one
+        // or in bulk, and nothing is cached.
+        assert!(matches!(
+            bundle.input_open_name("a.tex", &mut status),
+            OpenResult::Err(_)
+        ));
+        assert!(bundle.prefetch(&["b.sty".to_owned()], &mut status).is_err());
+        assert!(bundle.contents.is_empty());
+    }
+
+    #[test]
+    fn gc() {
+        let dir_1 = tempfile::tempdir().unwrap();
+        let dir_2 = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url_1 = make_indexed_tar(
+            dir_1.path(),
+            &[("a.tex", b"alpha"), ("b.sty", b"beta!")],
+            true,
+        );
+        let url_2 = make_indexed_tar(
+            dir_2.path(),
+            &[("b.sty", b"beta!"), ("c.cls", b"gamma")],
+            true,
+        );
+
+        let mut status = NoopStatusBackend::default();
+        let mut cache = Cache::get_for_custom_directory(cache_dir.path());
+        let mut digests = Vec::new();
+
+        for (url, names) in [(&url_1, ["a.tex", "b.sty"]), (&url_2, ["b.sty", "c.cls"])] {
+            let mut bundle = cache
+                .open::<LocalIndexedTarBackend>(url, false, &mut status)
+                .unwrap();
+
+            for name in names {
+                assert!(read_file(&mut bundle, name).is_some());
+            }
+
+            digests.push(bundle.get_digest(&mut status).unwrap().to_string());
+        }
+
+        // Nothing is old enough to be removed yet.
+        let max_age = Duration::from_secs(3600);
+        let report = cache.gc(max_age, &mut status).unwrap();
+        assert_eq!(
+            (report.n_digests, report.n_files, report.n_bytes),
+            (0, 0, 0)
+        );
+
+        // Make the first bundle look like it was last used two hours ago.
+        let index_path = make_txt_path(&cache_dir.path().join("indexes"), &digests[0]);
+        File::options()
+            .append(true)
+            .open(&index_path)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * max_age)
+            .unwrap();
+
+        // Only `a.tex` goes: `b.sty` is still used by the second bundle.
+        let report = cache.gc(max_age, &mut status).unwrap();
+        assert_eq!(
+            (report.n_digests, report.n_files, report.n_bytes),
+            (1, 1, 5)
+        );
+        assert!(!index_path.exists());
+
+        let indexes = list_txt_files(&cache_dir.path().join("indexes")).unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].0, digests[1]);
+        assert_eq!(
+            fs::read_dir(cache_dir.path().join("urls")).unwrap().count(),
+            1
+        );
+
+        // The second bundle still works offline.
+        fs::remove_file(dir_2.path().join("bundle.tar")).unwrap();
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url_2, true, &mut status)
+            .unwrap();
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+        assert_eq!(read_file(&mut bundle, "c.cls").as_deref(), Some("gamma"));
+    }
+
+    #[test]
+    fn verify() {
+        let bundle_dir = tempfile::tempdir().unwrap();
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = make_indexed_tar(
+            bundle_dir.path(),
+            &[
+                ("a.tex", b"alpha"),
+                ("b.sty", b"beta!"),
+                ("c.cls", b"gamma"),
+            ],
+            true,
+        );
+
+        let mut status = NoopStatusBackend::default();
+        let mut cache = Cache::get_for_custom_directory(cache_dir.path());
+        let mut bundle = cache
+            .open::<LocalIndexedTarBackend>(&url, false, &mut status)
+            .unwrap();
+        // The bundle's SHA256SUM file is fetched too.
+        assert_eq!(bundle.prefetch_all(&mut status).unwrap(), 4);
+        assert_eq!(bundle.prefetch_all(&mut status).unwrap(), 0);
+
+        let report = bundle.verify(false, &mut status).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.n_checked, 4);
+
+        // Corrupt one cached file and delete another.
+        let data_path = |bundle: &CachingBundle<LocalIndexedTarBackend>, name: &str| {
+            bundle.contents[name]
+                .digest
+                .create_two_part_path(&bundle.data_base)
+                .unwrap()
+        };
+
+        let a_path = data_path(&bundle, "a.tex");
+        remove_data_file(&a_path).unwrap();
+        fs::write(&a_path, b"ALPHA").unwrap();
+        remove_data_file(&data_path(&bundle, "c.cls")).unwrap();
+
+        // Without repairs, the problems are only reported.
+        for _ in 0..2 {
+            let report = bundle.verify(false, &mut status).unwrap();
+            assert_eq!(report.n_checked, 4);
+            assert_eq!(report.corrupt, vec!["a.tex"]);
+            assert_eq!(report.missing, vec!["c.cls"]);
+            assert_eq!(fs::read(&a_path).unwrap(), b"ALPHA");
+        }
+
+        // With them, the affected files are dropped from the cache, and
+        // fetched afresh when they're needed again.
+        let report = bundle.verify(true, &mut status).unwrap();
+        assert_eq!(report.corrupt, vec!["a.tex"]);
+        assert_eq!(report.missing, vec!["c.cls"]);
+        assert!(!a_path.exists());
+
+        let report = bundle.verify(false, &mut status).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.n_checked, 2);
+
+        assert_eq!(read_file(&mut bundle, "a.tex").as_deref(), Some("alpha"));
+        assert_eq!(bundle.prefetch_all(&mut status).unwrap(), 1);
+
+        let report = bundle.verify(false, &mut status).unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.n_checked, 4);
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Comparing the contents of two bundles.
+//!
+//! This is mainly useful when upgrading from one version of a bundle to
+//! another, to review which TeX support files have changed. Files are compared
+//! by their digests, obtained with [`Bundle::file_digest`], so that bundles
+//! that record digests, such as a [`crate::cache::CachingBundle`] with a
+//! well-populated cache, can be compared without reading every file. The files
+//! whose digests are needed are requested with [`Bundle::prefetch_digests`]
+//! first, so that bundles that need to download them can do so in bulk.
+
+use std::collections::BTreeSet;
+use tectonic_errors::prelude::*;
+use tectonic_io_base::digest;
+use tectonic_status_base::{tt_warning, StatusBackend};
+
+use crate::Bundle;
+
+/// The differences between two bundles, as computed by [`diff_bundles`].
+///
+/// Each list of filenames is sorted. The `SHA256SUM` file is never included,
+/// since it changes whenever anything else does.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BundleDiff {
+    /// Files that are in the new bundle but not the old one.
+    pub added: Vec<String>,
+
+    /// Files that are in the old bundle but not the new one.
+    pub removed: Vec<String>,
+
+    /// Files that are in both bundles, but with different contents.
+    pub changed: Vec<String>,
+
+    /// Files that are in both bundles, but whose contents couldn't be
+    /// compared because one of the bundles couldn't provide their digest,
+    /// such as a caching bundle in cache-only mode.
+    pub unknown: Vec<String>,
+}
+
+impl BundleDiff {
+    /// Returns true if the two bundles are known to contain the same files.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.unknown.is_empty()
+    }
+}
+
+/// Compare the contents of two bundles.
+///
+/// The file lists of the two bundles are compared, and then the digests of
+/// every file that appears in both. If the overall digests of the two bundles
+/// are the same, their contents must be the same too, so the per-file
+/// comparison is skipped.
+pub fn diff_bundles<A, B>(
+    old: &mut A,
+    new: &mut B,
+    status: &mut dyn StatusBackend,
+) -> Result<BundleDiff>
+where
+    A: Bundle + ?Sized,
+    B: Bundle + ?Sized,
+{
+    let old_files = bundle_file_set(old, status)?;
+    let new_files = bundle_file_set(new, status)?;
+
+    let mut diff = BundleDiff {
+        added: new_files.difference(&old_files).cloned().collect(),
+        removed: old_files.difference(&new_files).cloned().collect(),
+        changed: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    // Bundles without a `SHA256SUM` file don't have a digest, which is fine.
+    if let (Ok(d1), Ok(d2)) = (old.get_digest(status), new.get_digest(status)) {
+        if d1 == d2 {
+            return Ok(diff);
+        }
+    }
+
+    let common: Vec<String> = old_files.intersection(&new_files).cloned().collect();
+
+    // Failures here aren't fatal, since each digest is requested again below.
+    if let Err(e) = old.prefetch_digests(&common, status) {
+        tt_warning!(status, "failed to prefetch files from the old bundle"; e);
+    }
+
+    if let Err(e) = new.prefetch_digests(&common, status) {
+        tt_warning!(status, "failed to prefetch files from the new bundle"; e);
+    }
+
+    for name in common {
+        match (
+            old.file_digest(&name, status)?,
+            new.file_digest(&name, status)?,
+        ) {
+            (Some(d1), Some(d2)) if d1 == d2 => {}
+            (Some(_), Some(_)) => diff.changed.push(name),
+            _ => diff.unknown.push(name),
+        }
+    }
+
+    Ok(diff)
+}
+
+fn bundle_file_set<B: Bundle + ?Sized>(
+    bundle: &mut B,
+    status: &mut dyn StatusBackend,
+) -> Result<BTreeSet<String>> {
+    Ok(bundle
+        .all_files(status)?
+        .into_iter()
+        .filter(|n| n != digest::DIGEST_NAME)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dir::DirBundle;
+    use std::fs;
+    use tectonic_io_base::{digest::DigestData, InputHandle, IoProvider, OpenResult};
+    use tectonic_status_base::NoopStatusBackend;
+
+    /// A bundle that can't provide the digest of one of its files, and
+    /// records the names whose digests are prefetched.
+    struct Partial(DirBundle, &'static str, Vec<String>);
+
+    impl IoProvider for Partial {
+        fn input_open_name(
+            &mut self,
+            name: &str,
+            status: &mut dyn StatusBackend,
+        ) -> OpenResult<InputHandle> {
+            self.0.input_open_name(name, status)
+        }
+    }
+
+    impl Bundle for Partial {
+        fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+            self.0.all_files(status)
+        }
+
+        fn file_digest(
+            &mut self,
+            name: &str,
+            status: &mut dyn StatusBackend,
+        ) -> Result<Option<DigestData>> {
+            if name == self.1 {
+                Ok(None)
+            } else {
+                self.0.file_digest(name, status)
+            }
+        }
+
+        fn prefetch_digests(
+            &mut self,
+            names: &[String],
+            _status: &mut dyn StatusBackend,
+        ) -> Result<()> {
+            self.2.extend_from_slice(names);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn basic() {
+        let old_dir = tempfile::tempdir().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+
+        for (name, text) in [("a.tex", "a"), ("b.tex", "b"), ("c.tex", "c")] {
+            fs::write(old_dir.path().join(name), text).unwrap();
+        }
+
+        for (name, text) in [("b.tex", "b"), ("c.tex", "C"), ("d.tex", "d")] {
+            fs::write(new_dir.path().join(name), text).unwrap();
+        }
+
+        let mut old = DirBundle::new(old_dir.path());
+        let mut new = DirBundle::new(new_dir.path());
+        let mut status = NoopStatusBackend::default();
+        let diff = diff_bundles(&mut old, &mut new, &mut status).unwrap();
+
+        assert_eq!(diff.added, vec!["d.tex"]);
+        assert_eq!(diff.removed, vec!["a.tex"]);
+        assert_eq!(diff.changed, vec!["c.tex"]);
+
+        let mut same = DirBundle::new(old_dir.path());
+        let diff = diff_bundles(&mut old, &mut same, &mut status).unwrap();
+        assert!(diff.is_empty());
+    }
+
+    #[test]
+    fn unknown() {
+        let old_dir = tempfile::tempdir().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+
+        for (name, text) in [("a.tex", "a"), ("b.tex", "b"), ("c.tex", "c")] {
+            fs::write(old_dir.path().join(name), text).unwrap();
+            fs::write(new_dir.path().join(name), text.to_uppercase()).unwrap();
+        }
+
+        let mut old = DirBundle::new(old_dir.path());
+        let mut new = Partial(DirBundle::new(new_dir.path()), "b.tex", Vec::new());
+        let mut status = NoopStatusBackend::default();
+        let diff = diff_bundles(&mut old, &mut new, &mut status).unwrap();
+
+        assert_eq!(diff.changed, vec!["a.tex", "c.tex"]);
+        assert_eq!(diff.unknown, vec!["b.tex"]);
+        assert_eq!(new.2, vec!["a.tex", "b.tex", "c.tex"]);
+    }
+}
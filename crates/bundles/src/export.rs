@@ -0,0 +1,491 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Exporting bundles into standalone ZIP files, directories, or indexed tar
+//! files.
+//!
+//! Any [`Bundle`] can be exported, but the main use case is to take a bundle
+//! accessed through the network, such as a [`crate::cache::CachingBundle`],
+//! and turn it into a [`crate::zip::ZipBundle`] or [`crate::dir::DirBundle`]
+//! that can be used on a machine without network access. Bundles exported as
+//! indexed tar files can instead be served over HTTP or from a shared
+//! filesystem, and accessed through the cache with the backends in
+//! [`crate::itar`]. The exported bundle contains a `SHA256SUM` file computed
+//! the same way as the standard Tectonic bundle builder, so that it can be
+//! used interchangeably with the original.
+//!
+//! It is also possible to export only some of the files in a bundle, such as
+//! the ones needed to build a particular document.
+
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs,
+    io::{self, Read, Seek, Write},
+    path::Path,
+};
+use tar::{Builder, Header};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::{
+    digest::{self, Digest, DigestData},
+    OpenResult,
+};
+use tectonic_status_base::{tt_warning, StatusBackend};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::Bundle;
+
+/// Compute the overall digest of a bundle from the digests of its files.
+///
+/// This is the value that should be stored in a bundle’s `SHA256SUM` file. It
+/// is the SHA256 digest of the sorted names of the files, each followed by a
+/// NUL byte and the raw digest of the file contents. Any entry for the
+/// `SHA256SUM` file itself is ignored.
+pub fn compute_bundle_digest<'a, I>(files: I) -> DigestData
+where
+    I: IntoIterator<Item = (&'a str, &'a DigestData)>,
+{
+    let mut files: Vec<_> = files
+        .into_iter()
+        .filter(|(name, _)| *name != digest::DIGEST_NAME)
+        .collect();
+    files.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut dc = digest::create();
+
+    for (name, file_digest) in files {
+        dc.update(name.as_bytes());
+        dc.update(b"\0");
+        dc.update(file_digest.as_bytes());
+    }
+
+    DigestData::from(dc)
+}
+
+/// Export a bundle as a ZIP file.
+///
+/// Every file in the bundle is read and written into the ZIP stream *dest*,
+/// along with a freshly computed `SHA256SUM` file. The result can be used as a
+/// [`crate::zip::ZipBundle`]. The return value is the digest of the exported
+/// bundle.
+pub fn export_to_zip<B, W>(
+    bundle: &mut B,
+    dest: W,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write + Seek,
+{
+    zip_impl(bundle, None, dest, status)
+}
+
+/// Export some of the files in a bundle as a ZIP file.
+///
+/// This is like [`export_to_zip`], but only the files named in *names* are
+/// exported. The `SHA256SUM` file of the result reflects the exported files,
+/// so it will differ from that of the original bundle.
+pub fn export_subset_to_zip<B, W>(
+    bundle: &mut B,
+    names: &[String],
+    dest: W,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write + Seek,
+{
+    zip_impl(bundle, Some(names), dest, status)
+}
+
+fn zip_impl<B, W>(
+    bundle: &mut B,
+    names: Option<&[String]>,
+    dest: W,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write + Seek,
+{
+    let mut zip = ZipWriter::new(dest);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let digest = export_files(bundle, names, status, |name, data| {
+        zip.start_file(name, options)?;
+        zip.write_all(data)?;
+        Ok(())
+    })?;
+
+    zip.start_file(digest::DIGEST_NAME, options)?;
+    zip.write_all(digest.to_string().as_bytes())?;
+    zip.finish()?;
+    Ok(digest)
+}
+
+/// Export a bundle as a directory.
+///
+/// Every file in the bundle is written into the directory *dest*, along with a
+/// freshly computed `SHA256SUM` file. The result can be used as a
+/// [`crate::dir::DirBundle`]. The directory is created if needed; if it
+/// already exists, it must be empty, to avoid mixing the exported files with
+/// unrelated ones. The return value is the digest of the exported bundle.
+pub fn export_to_dir<B, P>(
+    bundle: &mut B,
+    dest: P,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    P: AsRef<Path>,
+{
+    dir_impl(bundle, None, dest.as_ref(), status)
+}
+
+/// Export some of the files in a bundle as a directory.
+///
+/// This is like [`export_to_dir`], but only the files named in *names* are
+/// exported. The `SHA256SUM` file of the result reflects the exported files,
+/// so it will differ from that of the original bundle.
+pub fn export_subset_to_dir<B, P>(
+    bundle: &mut B,
+    names: &[String],
+    dest: P,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    P: AsRef<Path>,
+{
+    dir_impl(bundle, Some(names), dest.as_ref(), status)
+}
+
+fn dir_impl<B>(
+    bundle: &mut B,
+    names: Option<&[String]>,
+    dest: &Path,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+{
+    atry!(
+        fs::create_dir_all(dest);
+        ["failed to create directory `{}`", dest.display()]
+    );
+
+    if fs::read_dir(dest)?.next().is_some() {
+        bail!(
+            "refusing to export bundle into non-empty directory `{}`",
+            dest.display()
+        );
+    }
+
+    let digest = export_files(bundle, names, status, |name, data| {
+        let path = dest.join(name);
+        atry!(
+            fs::write(&path, data);
+            ["failed to write `{}`", path.display()]
+        );
+        Ok(())
+    })?;
+
+    let path = dest.join(digest::DIGEST_NAME);
+    atry!(
+        fs::write(&path, digest.to_string());
+        ["failed to write `{}`", path.display()]
+    );
+    Ok(digest)
+}
+
+/// Export a bundle as an indexed tar file.
+///
+/// Every file in the bundle is written into the tar stream *dest*, along with
+/// a freshly computed `SHA256SUM` file, and the gzipped index of the tar file
+/// is written to *index*. Besides the offset and length of each file, the index
+/// gives the digest of its contents, which allows the cache to reuse files that
+/// it has already obtained for other bundles. The index should be stored next
+/// to the tar file, with an added `.index.gz` extension. The return value is
+/// the digest of the exported bundle.
+pub fn export_to_itar<B, W, I>(
+    bundle: &mut B,
+    dest: W,
+    index: I,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write,
+    I: Write,
+{
+    itar_impl(bundle, None, dest, index, status)
+}
+
+/// Export some of the files in a bundle as an indexed tar file.
+///
+/// This is like [`export_to_itar`], but only the files named in *names* are
+/// exported. The `SHA256SUM` file of the result reflects the exported files,
+/// so it will differ from that of the original bundle.
+pub fn export_subset_to_itar<B, W, I>(
+    bundle: &mut B,
+    names: &[String],
+    dest: W,
+    index: I,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write,
+    I: Write,
+{
+    itar_impl(bundle, Some(names), dest, index, status)
+}
+
+fn itar_impl<B, W, I>(
+    bundle: &mut B,
+    names: Option<&[String]>,
+    dest: W,
+    index: I,
+    status: &mut dyn StatusBackend,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    W: Write,
+    I: Write,
+{
+    let mut tar = Builder::new(CountingWriter {
+        inner: dest,
+        pos: 0,
+    });
+    let mut index = GzEncoder::new(index, Compression::default());
+
+    let mut append = |name: &str, data: &[u8]| -> Result<()> {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, name, data)?;
+
+        // The data are the last thing written for the entry, padded to a
+        // whole number of blocks. Long names may have added extra headers
+        // before them, so we work backwards.
+        let padded_len = (data.len() as u64).div_ceil(512) * 512;
+        let offset = tar.get_ref().pos - padded_len;
+
+        let mut dc = digest::create();
+        dc.update(data);
+        writeln!(
+            index,
+            "{} {} {} {}",
+            name,
+            offset,
+            data.len(),
+            DigestData::from(dc)
+        )?;
+        Ok(())
+    };
+
+    let digest = export_files(bundle, names, status, &mut append)?;
+    append(digest::DIGEST_NAME, digest.to_string().as_bytes())?;
+
+    tar.into_inner()?.inner.flush()?;
+    index.finish()?.flush()?;
+    Ok(digest)
+}
+
+/// A writer that keeps track of how many bytes have been written through it.
+struct CountingWriter<W> {
+    inner: W,
+    pos: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The shared implementation of the export functions.
+///
+/// Every file in *names*, or in the whole bundle if it's `None`, is read and
+/// passed to *write_file*, except for `SHA256SUM`. The digest of the exported
+/// files is returned. When exporting a whole bundle that has a recorded digest
+/// that doesn't match, something has gone wrong, most likely that some of its
+/// files couldn't be obtained, so we warn about it.
+fn export_files<B, F>(
+    bundle: &mut B,
+    names: Option<&[String]>,
+    status: &mut dyn StatusBackend,
+    mut write_file: F,
+) -> Result<DigestData>
+where
+    B: Bundle + ?Sized,
+    F: FnMut(&str, &[u8]) -> Result<()>,
+{
+    let is_subset = names.is_some();
+    let mut names = match names {
+        Some(n) => n.to_vec(),
+        None => bundle.all_files(status)?,
+    };
+    names.sort();
+    names.dedup();
+
+    let mut digests = Vec::with_capacity(names.len());
+    let mut n_unavailable = 0;
+
+    for name in &names {
+        if name == digest::DIGEST_NAME {
+            continue;
+        }
+
+        // The exported bundles are flat, so names that could escape the
+        // destination are out of the question.
+        if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+            bail!("cannot export bundle file with unsupported name `{}`", name);
+        }
+
+        // A file can be unavailable if, e.g., a caching bundle is in
+        // cache-only mode. In that case we export what we can.
+        let mut ih = match bundle.input_open_name(name, status) {
+            OpenResult::Ok(h) => h,
+            OpenResult::NotAvailable => {
+                n_unavailable += 1;
+                continue;
+            }
+            OpenResult::Err(e) => {
+                return Err(e.context(format!("failed to open bundle file `{name}`")));
+            }
+        };
+
+        let mut data = Vec::new();
+        atry!(
+            ih.read_to_end(&mut data);
+            ["failed to read bundle file `{}`", name]
+        );
+
+        let mut dc = digest::create();
+        dc.update(&data);
+        digests.push((name.as_str(), DigestData::from(dc)));
+
+        atry!(
+            write_file(name, &data);
+            ["failed to export bundle file `{}`", name]
+        );
+    }
+
+    if n_unavailable > 0 {
+        tt_warning!(
+            status,
+            "{} bundle file(s) could not be obtained and were left out of the export",
+            n_unavailable
+        );
+    }
+
+    let digest = compute_bundle_digest(digests.iter().map(|(n, d)| (*n, d)));
+
+    if !is_subset && names.iter().any(|n| n == digest::DIGEST_NAME) {
+        match bundle.get_digest(status) {
+            Ok(orig) if orig != digest => {
+                tt_warning!(
+                    status,
+                    "the exported bundle’s digest ({}) does not match that of the original ({})",
+                    digest,
+                    orig
+                );
+            }
+
+            Ok(_) => {}
+
+            Err(e) => {
+                tt_warning!(status, "couldn't get the digest of the original bundle"; e);
+            }
+        }
+    }
+
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest_ordering() {
+        let a = DigestData::zeros();
+        let b = DigestData::of_nothing();
+
+        let d1 = compute_bundle_digest(vec![("b.tex", &b), ("a.tex", &a)]);
+        let d2 = compute_bundle_digest(vec![
+            ("a.tex", &a),
+            (digest::DIGEST_NAME, &a),
+            ("b.tex", &b),
+        ]);
+        assert_eq!(d1, d2);
+
+        let d3 = compute_bundle_digest(vec![("a.tex", &b), ("b.tex", &a)]);
+        assert_ne!(d1, d3);
+    }
+
+    #[test]
+    fn itar_roundtrip() {
+        use crate::{cache::Cache, dir::DirBundle, itar::LocalIndexedTarBackend};
+        use flate2::read::GzDecoder;
+        use std::fs::File;
+        use tectonic_io_base::IoProvider;
+        use tectonic_status_base::NoopStatusBackend;
+
+        let src = tempfile::tempdir().unwrap();
+        let long_name = format!("{}.sty", "x".repeat(120));
+        fs::write(src.path().join("a.tex"), "hello").unwrap();
+        fs::write(src.path().join(&long_name), "long").unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        let tar_path = out.path().join("bundle.tar");
+        let index_path = out.path().join("bundle.tar.index.gz");
+        let mut status = NoopStatusBackend::default();
+
+        let digest = export_to_itar(
+            &mut DirBundle::new(src.path()),
+            File::create(&tar_path).unwrap(),
+            File::create(&index_path).unwrap(),
+            &mut status,
+        )
+        .unwrap();
+
+        let mut index = String::new();
+        GzDecoder::new(File::open(&index_path).unwrap())
+            .read_to_string(&mut index)
+            .unwrap();
+        let hello_digest = DigestData::from({
+            let mut dc = digest::create();
+            dc.update(b"hello");
+            dc
+        });
+        assert!(index
+            .lines()
+            .any(|l| l.starts_with("a.tex ") && l.ends_with(&hello_digest.to_string())));
+
+        assert!(LocalIndexedTarBackend::can_open(&tar_path));
+        let cache_dir = tempfile::tempdir().unwrap();
+        let url = url::Url::from_file_path(&tar_path).unwrap();
+        let mut bundle = Cache::get_for_custom_directory(cache_dir.path())
+            .open::<LocalIndexedTarBackend>(url.as_str(), false, &mut status)
+            .unwrap();
+
+        assert_eq!(bundle.get_digest(&mut status).unwrap(), digest);
+
+        for (name, contents) in [("a.tex", "hello"), (long_name.as_str(), "long")] {
+            let mut s = String::new();
+            bundle
+                .input_open_name(name, &mut status)
+                .unwrap()
+                .read_to_string(&mut s)
+                .unwrap();
+            assert_eq!(s, contents);
+        }
+    }
+}
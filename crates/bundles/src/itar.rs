@@ -13,23 +13,59 @@
 //! centered on HTTP byte-range requests. For each file contained in the backing
 //! resource, the index file merely contains a byte offset and length that are
 //! then used to construct an HTTP Range request to obtain the file as needed.
+//! Index lines may optionally also give the SHA256 digest of each file’s
+//! contents, which allows the cache to reuse files that it has already
+//! obtained for a different bundle. Indexes written by
+//! [`crate::export::export_to_itar`] do so; without them, files are fetched
+//! again for every new bundle version.
+//! When many files are needed at once, the ranges of files that are near each
+//! other in the tar file are combined, and several requests are made in
+//! parallel.
+//!
+//! The [`LocalIndexedTarBackend`] reads the same format from the local
+//! filesystem, given a `file:` URL.
 
 use flate2::read::GzDecoder;
-use std::{convert::TryInto, io::Read, str::FromStr};
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
 use tectonic_errors::prelude::*;
 use tectonic_geturl::{DefaultBackend, DefaultRangeReader, GetUrlBackend, RangeReader};
 use tectonic_io_base::digest::{self, DigestData};
 use tectonic_status_base::{tt_note, tt_warning, StatusBackend};
+use url::Url;
 
-use crate::cache::{BackendPullData, CacheBackend};
+use crate::{
+    cache::{BackendPullData, CacheBackend},
+    signature::SIGNATURE_SUFFIX,
+};
 
 const MAX_HTTP_ATTEMPTS: usize = 4;
 
+/// When fetching several files at once, the largest amount of unneeded data
+/// between two files that we'll download in order to get both of them in one
+/// request. Files in a tar archive are always separated by at least one
+/// 512-byte header.
+const MAX_BATCH_GAP: u64 = 64 * 1024;
+
+/// The largest amount of data that we'll request in one combined request.
+const MAX_BATCH_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The maximum number of requests that we'll run in parallel.
+const MAX_PARALLEL_REQUESTS: usize = 6;
+
 /// The internal file-information struct used by the [`IndexedTarBackend`].
 #[derive(Clone, Copy, Debug)]
 pub struct FileInfo {
-    offset: u64,
-    length: u64,
+    pub(crate) offset: u64,
+    pub(crate) length: u64,
+    pub(crate) digest: Option<DigestData>,
 }
 
 /// A simple web-based file backend based on HTTP Range requests.
@@ -39,6 +75,7 @@ pub struct FileInfo {
 /// framework.
 #[derive(Debug)]
 pub struct IndexedTarBackend {
+    url: String,
     reader: DefaultRangeReader,
 }
 
@@ -66,27 +103,11 @@ impl CacheBackend for IndexedTarBackend {
         // Step 3: get digest, setting up instance as we go
 
         let mut cache_backend = IndexedTarBackend {
+            url: resolved_url.clone(),
             reader: geturl_backend.open_range_reader(&resolved_url),
         };
 
-        let digest_info = {
-            let mut digest_info = None;
-
-            for line in index.lines() {
-                if let Ok((name, info)) = Self::parse_index_line(line) {
-                    if name == digest::DIGEST_NAME {
-                        digest_info = Some(info);
-                        break;
-                    }
-                }
-            }
-
-            atry!(
-                digest_info;
-                ["backend does not provide needed {} file", digest::DIGEST_NAME]
-            )
-        };
-
+        let digest_info = find_digest_info(&index)?;
         let digest_text =
             String::from_utf8(cache_backend.get_file(digest::DIGEST_NAME, &digest_info, status)?)
                 .map_err(|e| e.utf8_error())?;
@@ -109,6 +130,7 @@ impl CacheBackend for IndexedTarBackend {
         status: &mut dyn StatusBackend,
     ) -> Result<Option<(Self, DigestData)>> {
         let mut cache_backend = IndexedTarBackend {
+            url: resolved_url.to_owned(),
             reader: DefaultBackend::default().open_range_reader(resolved_url),
         };
 
@@ -124,20 +146,11 @@ impl CacheBackend for IndexedTarBackend {
     }
 
     fn parse_index_line(line: &str) -> Result<(String, Self::FileInfo)> {
-        let mut bits = line.split_whitespace();
-
-        if let (Some(name), Some(offset), Some(length)) = (bits.next(), bits.next(), bits.next()) {
-            Ok((
-                name.to_owned(),
-                FileInfo {
-                    offset: offset.parse::<u64>()?,
-                    length: length.parse::<u64>()?,
-                },
-            ))
-        } else {
-            // TODO: preserve the warning info or something!
-            bail!("malformed index line");
-        }
+        parse_index_line(line)
+    }
+
+    fn file_digest(info: &Self::FileInfo) -> Option<DigestData> {
+        info.digest
     }
 
     fn get_file(
@@ -197,4 +210,388 @@ impl CacheBackend for IndexedTarBackend {
 
         Ok(buf)
     }
+
+    fn get_signature(&mut self, status: &mut dyn StatusBackend) -> Result<String> {
+        let sig_url = format!("{}{}", &self.url, SIGNATURE_SUFFIX);
+        tt_note!(status, "downloading signature {}", sig_url);
+
+        let mut text = String::new();
+        DefaultBackend::default()
+            .get_url(&sig_url, status)?
+            .read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    fn get_files(
+        &mut self,
+        files: &[(String, Self::FileInfo)],
+        status: &mut dyn StatusBackend,
+    ) -> Vec<Result<Vec<u8>>> {
+        let batches = plan_batches(files);
+        let n_requests = batches.len();
+
+        if n_requests > 1 {
+            tt_note!(
+                status,
+                "downloading {} files in {} requests",
+                files.len(),
+                n_requests
+            );
+        }
+
+        // Fetch the batches in parallel. Each worker makes its own connection
+        // and claims batches until there are none left. There's no point in
+        // retrying here, since failed files are retried individually below.
+
+        let next_batch = AtomicUsize::new(0);
+        let url = &self.url;
+        let batches = &batches;
+
+        let mut fetched: Vec<Option<Vec<u8>>> = vec![None; n_requests];
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..MAX_PARALLEL_REQUESTS.min(n_requests))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut reader = DefaultBackend::default().open_range_reader(url);
+                        let mut results = Vec::new();
+
+                        loop {
+                            let i = next_batch.fetch_add(1, Ordering::SeqCst);
+
+                            let batch = match batches.get(i) {
+                                Some(b) => b,
+                                None => break,
+                            };
+
+                            let mut buf = Vec::with_capacity(batch.length as usize);
+                            let ok = reader
+                                .read_range(batch.offset, batch.length as usize)
+                                .and_then(|mut r| Ok(r.read_to_end(&mut buf)?))
+                                .is_ok();
+
+                            if ok && buf.len() as u64 == batch.length {
+                                results.push((i, buf));
+                            }
+                        }
+
+                        results
+                    })
+                })
+                .collect();
+
+            for worker in workers {
+                // If a worker panics, its batches will get retried.
+                if let Ok(results) = worker.join() {
+                    for (i, buf) in results {
+                        fetched[i] = Some(buf);
+                    }
+                }
+            }
+        });
+
+        // Now split the batches back up into individual files.
+
+        let mut results: Vec<Option<Result<Vec<u8>>>> = files.iter().map(|_| None).collect();
+
+        for (batch, data) in batches.iter().zip(fetched) {
+            if let Some(data) = data {
+                for &i in &batch.members {
+                    let info = &files[i].1;
+                    let start = (info.offset - batch.offset) as usize;
+                    results[i] = Some(Ok(data[start..start + info.length as usize].to_vec()));
+                }
+            }
+        }
+
+        // Anything that failed gets the full treatment with retries.
+
+        results
+            .into_iter()
+            .zip(files)
+            .map(|(r, (name, info))| match r {
+                Some(r) => r,
+                None => self.get_file(name, info, status),
+            })
+            .collect()
+    }
+}
+
+/// A group of files that can be fetched from an indexed tar file with a
+/// single range request.
+#[derive(Debug)]
+struct Batch {
+    offset: u64,
+    length: u64,
+    members: Vec<usize>,
+}
+
+/// Group files into batches of nearby files that can be fetched together.
+///
+/// The files are identified in the batches by their indices in *files*.
+/// Zero-length files are left out, since [`IndexedTarBackend::get_file`] can
+/// handle them without making any request.
+fn plan_batches(files: &[(String, FileInfo)]) -> Vec<Batch> {
+    let mut order: Vec<usize> = (0..files.len())
+        .filter(|&i| files[i].1.length > 0)
+        .collect();
+    order.sort_by_key(|&i| files[i].1.offset);
+
+    let mut batches: Vec<Batch> = Vec::new();
+
+    for i in order {
+        let info = &files[i].1;
+        let end = info.offset + info.length;
+
+        if let Some(batch) = batches.last_mut() {
+            let batch_end = batch.offset + batch.length;
+
+            if info.offset >= batch_end
+                && info.offset - batch_end <= MAX_BATCH_GAP
+                && end - batch.offset <= MAX_BATCH_SIZE
+            {
+                batch.length = end - batch.offset;
+                batch.members.push(i);
+                continue;
+            }
+        }
+
+        batches.push(Batch {
+            offset: info.offset,
+            length: info.length,
+            members: vec![i],
+        });
+    }
+
+    batches
+}
+
+/// A file backend that reads an indexed tar file from the local filesystem.
+///
+/// This type implements the [`CacheBackend`] trait just like
+/// [`IndexedTarBackend`], but for `file:` URLs. The index is read from the
+/// file with the same name plus `.index.gz`. It's useful for bundles stored
+/// on a shared network filesystem, which benefit from the same local caching
+/// and digest checks as web-based bundles.
+#[derive(Debug)]
+pub struct LocalIndexedTarBackend {
+    file: File,
+    path: PathBuf,
+}
+
+impl LocalIndexedTarBackend {
+    /// Check whether a local file can be accessed through this backend: it
+    /// must be an uncompressed tar file with an index next to it, named with an
+    /// added `.index.gz` extension. Bundles given as `file:` URLs that pass
+    /// this check should be opened through the cache with this backend, rather
+    /// than as plain [`crate::tar::TarBundle`]s.
+    pub fn can_open<P: AsRef<Path>>(path: P) -> bool {
+        let path = path.as_ref();
+        let mut index_path = path.as_os_str().to_owned();
+        index_path.push(".index.gz");
+
+        if !path.is_file() || !Path::new(&index_path).is_file() {
+            return false;
+        }
+
+        // The index offsets refer to the uncompressed data, so gzipped tar
+        // files can't be read this way.
+        let mut magic = [0u8; 2];
+        let is_gzipped = File::open(path)
+            .and_then(|mut f| f.read_exact(&mut magic))
+            .is_ok()
+            && magic == [0x1f, 0x8b];
+        !is_gzipped
+    }
+
+    fn open(url: &str) -> Result<Self> {
+        let path = Url::parse(url)
+            .ok()
+            .filter(|u| u.scheme() == "file")
+            .and_then(|u| u.to_file_path().ok());
+        let path = a_ok_or!(path; ["`{}` is not a valid local file URL", url]);
+
+        let file = atry!(
+            File::open(&path);
+            ["failed to open `{}`", path.display()]
+        );
+
+        Ok(LocalIndexedTarBackend { file, path })
+    }
+}
+
+impl CacheBackend for LocalIndexedTarBackend {
+    type FileInfo = FileInfo;
+
+    fn open_with_pull(
+        start_url: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<(Self, BackendPullData)> {
+        let mut cache_backend = Self::open(start_url)?;
+
+        let index = {
+            let mut index_path = cache_backend.path.clone().into_os_string();
+            index_path.push(".index.gz");
+            let index_file = atry!(
+                File::open(&index_path);
+                ["failed to open index file `{}`", index_path.to_string_lossy()]
+            );
+
+            let mut index = String::new();
+            GzDecoder::new(index_file).read_to_string(&mut index)?;
+            index
+        };
+
+        let digest_info = find_digest_info(&index)?;
+        let digest_text =
+            String::from_utf8(cache_backend.get_file(digest::DIGEST_NAME, &digest_info, status)?)
+                .map_err(|e| e.utf8_error())?;
+        let digest = DigestData::from_str(&digest_text)?;
+
+        Ok((
+            cache_backend,
+            BackendPullData {
+                resolved_url: start_url.to_owned(),
+                digest,
+                index,
+            },
+        ))
+    }
+
+    fn open_with_quick_check(
+        resolved_url: &str,
+        digest_file_info: &Self::FileInfo,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<(Self, DigestData)>> {
+        let mut cache_backend = match Self::open(resolved_url) {
+            Ok(b) => b,
+            Err(_) => return Ok(None),
+        };
+
+        if let Ok(d) = cache_backend.get_file(digest::DIGEST_NAME, digest_file_info, status) {
+            if let Ok(d) = String::from_utf8(d) {
+                if let Ok(d) = DigestData::from_str(&d) {
+                    return Ok(Some((cache_backend, d)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn parse_index_line(line: &str) -> Result<(String, Self::FileInfo)> {
+        parse_index_line(line)
+    }
+
+    fn file_digest(info: &Self::FileInfo) -> Option<DigestData> {
+        info.digest
+    }
+
+    fn get_file(
+        &mut self,
+        name: &str,
+        info: &Self::FileInfo,
+        _status: &mut dyn StatusBackend,
+    ) -> Result<Vec<u8>> {
+        let mut buf = vec![0; info.length.try_into().unwrap()];
+        self.file.seek(SeekFrom::Start(info.offset))?;
+        atry!(
+            self.file.read_exact(&mut buf);
+            ["failed to read \"{}\" from local bundle", name]
+        );
+        Ok(buf)
+    }
+
+    fn get_signature(&mut self, _status: &mut dyn StatusBackend) -> Result<String> {
+        let mut sig_path = self.path.clone().into_os_string();
+        sig_path.push(SIGNATURE_SUFFIX);
+        Ok(atry!(
+            std::fs::read_to_string(&sig_path);
+            ["failed to read bundle signature file `{}`", sig_path.to_string_lossy()]
+        ))
+    }
+}
+
+/// Parse a line of an indexed tar index: the file name, followed by the
+/// offset and length of its data within the tar file, and optionally the
+/// digest of its contents.
+fn parse_index_line(line: &str) -> Result<(String, FileInfo)> {
+    let mut bits = line.split_whitespace();
+
+    if let (Some(name), Some(offset), Some(length)) = (bits.next(), bits.next(), bits.next()) {
+        let digest = match bits.next() {
+            Some(d) => Some(DigestData::from_str(d)?),
+            None => None,
+        };
+
+        Ok((
+            name.to_owned(),
+            FileInfo {
+                offset: offset.parse::<u64>()?,
+                length: length.parse::<u64>()?,
+                digest,
+            },
+        ))
+    } else {
+        // TODO: preserve the warning info or something!
+        bail!("malformed index line");
+    }
+}
+
+/// Find the location of the digest file in an indexed tar index.
+fn find_digest_info(index: &str) -> Result<FileInfo> {
+    for line in index.lines() {
+        if let Ok((name, info)) = parse_index_line(line) {
+            if name == digest::DIGEST_NAME {
+                return Ok(info);
+            }
+        }
+    }
+
+    bail!(
+        "backend does not provide needed {} file",
+        digest::DIGEST_NAME
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(offset: u64, length: u64) -> (String, FileInfo) {
+        (
+            String::new(),
+            FileInfo {
+                offset,
+                length,
+                digest: None,
+            },
+        )
+    }
+
+    #[test]
+    fn batching() {
+        let files = vec![
+            file(MAX_BATCH_SIZE, 100),
+            file(512, 100),
+            file(1536, 0),
+            file(1536, 200),
+            file(4 * MAX_BATCH_SIZE, 10),
+        ];
+
+        let batches = plan_batches(&files);
+        let summary: Vec<_> = batches
+            .iter()
+            .map(|b| (b.offset, b.length, b.members.clone()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (512, 1224, vec![1, 3]),
+                (MAX_BATCH_SIZE, 100, vec![0]),
+                (4 * MAX_BATCH_SIZE, 10, vec![4]),
+            ]
+        );
+    }
 }
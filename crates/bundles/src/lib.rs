@@ -15,16 +15,31 @@
 //!   filesystem caching.
 //! - [`dir::DirBundle`] turns a directory full of files into a bundle; it is
 //!   useful for testing and lightweight usage.
+//! - [`tar::TarBundle`] for a local, possibly compressed, tar-format bundle.
 //! - [`zip::ZipBundle`] for a ZIP-format bundle.
+//! - [`overlay::OverlayBundle`] stacks several bundles on top of one another.
+//!
+//! The [`export`] module can turn any bundle into a standalone ZIP or
+//! directory bundle, and the [`diff`] module compares the contents of two
+//! bundles. The [`signature`] module allows bundles obtained through the
+//! cache to be checked for authenticity.
 
-use std::{io::Read, str::FromStr};
+use std::{cell::RefCell, io::Read, rc::Rc, str::FromStr};
 use tectonic_errors::{anyhow::bail, atry, Result};
-use tectonic_io_base::{digest, digest::DigestData, IoProvider, OpenResult};
+use tectonic_io_base::{
+    digest::{self, Digest, DigestData},
+    IoProvider, OpenResult,
+};
 use tectonic_status_base::StatusBackend;
 
 pub mod cache;
+pub mod diff;
 pub mod dir;
+pub mod export;
 pub mod itar;
+pub mod overlay;
+pub mod signature;
+pub mod tar;
 pub mod zip;
 
 /// A trait for bundles of Tectonic support files.
@@ -83,6 +98,53 @@ pub trait Bundle: IoProvider {
     /// might be fairly substantial (although we are talking megabytes, not
     /// gigabytes).
     fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>>;
+
+    /// Get the digest of the contents of one of the files in this bundle.
+    ///
+    /// The return value is `None` if the file isn't in the bundle. The default
+    /// implementation reads the file and computes its digest, but bundles that
+    /// keep track of their files’ digests may be able to provide them more
+    /// cheaply.
+    fn file_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<DigestData>> {
+        let mut ih = match self.input_open_name(name, status) {
+            OpenResult::Ok(h) => h,
+            OpenResult::NotAvailable => return Ok(None),
+            OpenResult::Err(e) => return Err(e),
+        };
+
+        let mut data = Vec::new();
+        ih.read_to_end(&mut data)?;
+
+        let mut dc = digest::create();
+        dc.update(&data);
+        Ok(Some(DigestData::from(dc)))
+    }
+
+    /// Hint that the named files are likely to be needed soon.
+    ///
+    /// Bundles that obtain their files over the network can use this hint to
+    /// fetch many files at once, which can be much faster than fetching them
+    /// one by one as they are opened. Names that aren't in the bundle should be
+    /// ignored. Errors are not fatal, in the sense that the files will simply
+    /// be fetched again when they are actually opened. The default
+    /// implementation does nothing.
+    fn prefetch(&mut self, _names: &[String], _status: &mut dyn StatusBackend) -> Result<()> {
+        Ok(())
+    }
+
+    /// Hint that the digests of the named files are likely to be requested
+    /// soon with [`Bundle::file_digest`].
+    ///
+    /// This is like [`Bundle::prefetch`], but bundles that can get the digests
+    /// of some files without their contents need not fetch those files. The
+    /// default implementation calls [`Bundle::prefetch`].
+    fn prefetch_digests(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        self.prefetch(names, status)
+    }
 }
 
 impl<B: Bundle + ?Sized> Bundle for Box<B> {
@@ -93,6 +155,48 @@ impl<B: Bundle + ?Sized> Bundle for Box<B> {
     fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
         (**self).all_files(status)
     }
+
+    fn file_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<DigestData>> {
+        (**self).file_digest(name, status)
+    }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        (**self).prefetch(names, status)
+    }
+
+    fn prefetch_digests(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        (**self).prefetch_digests(names, status)
+    }
+}
+
+impl<B: Bundle + ?Sized> Bundle for Rc<RefCell<B>> {
+    fn get_digest(&mut self, status: &mut dyn StatusBackend) -> Result<DigestData> {
+        self.borrow_mut().get_digest(status)
+    }
+
+    fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+        self.borrow_mut().all_files(status)
+    }
+
+    fn file_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<DigestData>> {
+        self.borrow_mut().file_digest(name, status)
+    }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        self.borrow_mut().prefetch(names, status)
+    }
+
+    fn prefetch_digests(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        self.borrow_mut().prefetch_digests(names, status)
+    }
 }
 
 /// Get the URL of the default bundle.
@@ -0,0 +1,365 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Stacking several bundles on top of one another.
+//!
+//! The main type offered by this module is [`OverlayBundle`], which combines a
+//! series of bundles into one. This makes it possible to supplement or override
+//! the contents of a large standard bundle with a handful of custom files,
+//! such as a house style, while still treating the combination as an ordinary
+//! bundle with a well-defined digest.
+
+use std::{collections::HashSet, path::PathBuf};
+use tectonic_errors::prelude::*;
+use tectonic_io_base::{
+    digest::{self, Digest, DigestData},
+    InputHandle, IoProvider, OpenResult,
+};
+use tectonic_status_base::StatusBackend;
+
+use crate::{export::compute_bundle_digest, Bundle};
+
+/// A bundle composed of several other bundles, or "layers".
+///
+/// When a file is requested, the layers are searched in order, and the first
+/// one that provides the file wins. So, files in earlier layers take
+/// precedence over files in later ones, and you will generally want to put a
+/// complete standard bundle last.
+pub struct OverlayBundle {
+    layers: Vec<Box<dyn Bundle>>,
+}
+
+impl OverlayBundle {
+    /// Create a new overlay bundle from a list of layers, highest-precedence
+    /// first.
+    pub fn new(layers: Vec<Box<dyn Bundle>>) -> Self {
+        OverlayBundle { layers }
+    }
+
+    /// Add a new layer to the bundle, with lower precedence than all of the
+    /// existing ones.
+    pub fn push(&mut self, layer: Box<dyn Bundle>) -> &mut Self {
+        self.layers.push(layer);
+        self
+    }
+
+    /// Pass each layer the names of the files that will be read from it, which
+    /// are those that it provides and earlier layers don't. If we can't list a
+    /// layer's files, we pass it all of the remaining names, which is harmless
+    /// since unknown names are ignored.
+    fn prefetch_by_layer<F>(
+        &mut self,
+        names: &[String],
+        status: &mut dyn StatusBackend,
+        mut prefetch: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut dyn Bundle, &[String], &mut dyn StatusBackend) -> Result<()>,
+    {
+        let mut remaining = names.to_vec();
+
+        for layer in &mut self.layers {
+            if remaining.is_empty() {
+                break;
+            }
+
+            match layer.all_files(status) {
+                Ok(files) => {
+                    let files: HashSet<String> = files.into_iter().collect();
+                    let (provided, rest): (Vec<_>, Vec<_>) =
+                        remaining.into_iter().partition(|n| files.contains(n));
+                    remaining = rest;
+
+                    if !provided.is_empty() {
+                        prefetch(&mut **layer, &provided, status)?;
+                    }
+                }
+
+                Err(_) => prefetch(&mut **layer, &remaining, status)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl IoProvider for OverlayBundle {
+    fn input_open_name(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        for layer in &mut self.layers {
+            match layer.input_open_name(name, status) {
+                OpenResult::NotAvailable => continue,
+                r => return r,
+            }
+        }
+
+        OpenResult::NotAvailable
+    }
+
+    fn input_open_name_with_abspath(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
+        for layer in &mut self.layers {
+            match layer.input_open_name_with_abspath(name, status) {
+                OpenResult::NotAvailable => continue,
+                r => return r,
+            }
+        }
+
+        OpenResult::NotAvailable
+    }
+}
+
+impl Bundle for OverlayBundle {
+    /// Get the combined digest of the layers.
+    ///
+    /// If there is just one layer, this is its digest, so that wrapping a
+    /// bundle in an overlay doesn't change anything. Otherwise, it is the
+    /// SHA256 digest of the raw digests of the layers, in order. The digest
+    /// changes if any layer’s contents change, or if the layers are reordered,
+    /// since that can change which files are visible.
+    ///
+    /// Layers without a `SHA256SUM` file, such as directories of custom files,
+    /// get a digest computed from their contents in the same way as
+    /// [`compute_bundle_digest`].
+    fn get_digest(&mut self, status: &mut dyn StatusBackend) -> Result<DigestData> {
+        if let [layer] = &mut self.layers[..] {
+            return layer_digest(&mut **layer, status);
+        }
+
+        let mut dc = digest::create();
+
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let d = atry!(
+                layer_digest(&mut **layer, status);
+                ["failed to get the digest of bundle layer #{}", i + 1]
+            );
+            dc.update(d.as_bytes());
+        }
+
+        Ok(DigestData::from(dc))
+    }
+
+    fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+
+        for layer in &mut self.layers {
+            files.append(&mut layer.all_files(status)?);
+        }
+
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    fn file_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<DigestData>> {
+        for layer in &mut self.layers {
+            if let Some(d) = layer.file_digest(name, status)? {
+                return Ok(Some(d));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn prefetch(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        self.prefetch_by_layer(names, status, |layer, names, status| {
+            layer.prefetch(names, status)
+        })
+    }
+
+    fn prefetch_digests(&mut self, names: &[String], status: &mut dyn StatusBackend) -> Result<()> {
+        self.prefetch_by_layer(names, status, |layer, names, status| {
+            layer.prefetch_digests(names, status)
+        })
+    }
+}
+
+/// Get the digest of one layer, computing it from the layer’s files if it
+/// doesn't provide a `SHA256SUM` file.
+fn layer_digest(layer: &mut dyn Bundle, status: &mut dyn StatusBackend) -> Result<DigestData> {
+    let err = match layer.get_digest(status) {
+        Ok(d) => return Ok(d),
+        Err(e) => e,
+    };
+
+    match layer.input_open_name(digest::DIGEST_NAME, status) {
+        OpenResult::NotAvailable => {}
+        _ => return Err(err),
+    }
+
+    let mut digests = Vec::new();
+
+    for name in layer.all_files(status)? {
+        if let Some(d) = layer.file_digest(&name, status)? {
+            digests.push((name, d));
+        }
+    }
+
+    Ok(compute_bundle_digest(
+        digests.iter().map(|(name, d)| (name.as_str(), d)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dir::DirBundle;
+    use std::{cell::RefCell, fs, io::Read, path::Path, rc::Rc};
+    use tectonic_status_base::NoopStatusBackend;
+
+    fn make_dir(files: &[(&str, &str)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+
+        for (name, contents) in files {
+            fs::write(dir.path().join(name), contents).unwrap();
+        }
+
+        dir
+    }
+
+    fn overlay(dirs: &[&Path]) -> OverlayBundle {
+        OverlayBundle::new(
+            dirs.iter()
+                .map(|d| Box::new(DirBundle::new(d)) as Box<dyn Bundle>)
+                .collect(),
+        )
+    }
+
+    fn read_file(bundle: &mut dyn Bundle, name: &str) -> Option<String> {
+        let mut status = NoopStatusBackend::default();
+
+        match bundle.input_open_name(name, &mut status) {
+            OpenResult::Ok(mut ih) => {
+                let mut s = String::new();
+                ih.read_to_string(&mut s).unwrap();
+                Some(s)
+            }
+            OpenResult::NotAvailable => None,
+            OpenResult::Err(e) => panic!("failed to open `{}`: {}", name, e),
+        }
+    }
+
+    /// A layer that records the names that it is asked to prefetch.
+    struct Recording(DirBundle, Rc<RefCell<Vec<String>>>);
+
+    impl IoProvider for Recording {
+        fn input_open_name(
+            &mut self,
+            name: &str,
+            status: &mut dyn StatusBackend,
+        ) -> OpenResult<InputHandle> {
+            self.0.input_open_name(name, status)
+        }
+    }
+
+    impl Bundle for Recording {
+        fn all_files(&mut self, status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+            self.0.all_files(status)
+        }
+
+        fn prefetch(&mut self, names: &[String], _status: &mut dyn StatusBackend) -> Result<()> {
+            self.1.borrow_mut().extend_from_slice(names);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        let top = make_dir(&[("a.sty", "custom"), ("b.sty", "extra")]);
+        let base = make_dir(&[("a.sty", "standard"), ("c.cls", "class")]);
+        let mut bundle = overlay(&[top.path(), base.path()]);
+        let mut status = NoopStatusBackend::default();
+
+        assert_eq!(read_file(&mut bundle, "a.sty").as_deref(), Some("custom"));
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("extra"));
+        assert_eq!(read_file(&mut bundle, "c.cls").as_deref(), Some("class"));
+        assert_eq!(read_file(&mut bundle, "d.tex"), None);
+
+        assert_eq!(
+            bundle.all_files(&mut status).unwrap(),
+            vec!["a.sty", "b.sty", "c.cls"]
+        );
+        assert_eq!(
+            bundle.file_digest("a.sty", &mut status).unwrap(),
+            Some(DigestData::from({
+                let mut dc = digest::create();
+                dc.update(b"custom");
+                dc
+            }))
+        );
+    }
+
+    #[test]
+    fn digests() {
+        let mut status = NoopStatusBackend::default();
+
+        // A layer with a `SHA256SUM` file uses it.
+        let summed = make_dir(&[
+            ("a.sty", "standard"),
+            (digest::DIGEST_NAME, &"ab".repeat(32)),
+        ]);
+        let mut bundle = overlay(&[summed.path()]);
+        assert_eq!(
+            bundle.get_digest(&mut status).unwrap().to_string(),
+            "ab".repeat(32)
+        );
+
+        // One without gets a digest computed from its files.
+        let custom = make_dir(&[("a.sty", "custom")]);
+        let custom_digest = overlay(&[custom.path()]).get_digest(&mut status).unwrap();
+        let a_digest = DigestData::from({
+            let mut dc = digest::create();
+            dc.update(b"custom");
+            dc
+        });
+        assert_eq!(
+            custom_digest,
+            compute_bundle_digest(vec![("a.sty", &a_digest)])
+        );
+
+        // The combined digest depends on the order of the layers and on their
+        // contents.
+        let d1 = overlay(&[custom.path(), summed.path()])
+            .get_digest(&mut status)
+            .unwrap();
+        let d2 = overlay(&[summed.path(), custom.path()])
+            .get_digest(&mut status)
+            .unwrap();
+        assert_ne!(d1, d2);
+
+        fs::write(custom.path().join("a.sty"), "changed").unwrap();
+        let d3 = overlay(&[custom.path(), summed.path()])
+            .get_digest(&mut status)
+            .unwrap();
+        assert_ne!(d1, d3);
+    }
+
+    #[test]
+    fn prefetch_precedence() {
+        let top = make_dir(&[("a.sty", "custom"), ("b.sty", "extra")]);
+        let base = make_dir(&[("a.sty", "standard"), ("c.cls", "class")]);
+        let top_names = Rc::new(RefCell::new(Vec::new()));
+        let base_names = Rc::new(RefCell::new(Vec::new()));
+        let mut bundle = OverlayBundle::new(vec![
+            Box::new(Recording(DirBundle::new(top.path()), top_names.clone())),
+            Box::new(Recording(DirBundle::new(base.path()), base_names.clone())),
+        ]);
+        let mut status = NoopStatusBackend::default();
+
+        let names = ["a.sty", "b.sty", "c.cls", "d.tex"].map(String::from);
+        bundle.prefetch(&names, &mut status).unwrap();
+
+        assert_eq!(*top_names.borrow(), vec!["a.sty", "b.sty"]);
+        assert_eq!(*base_names.borrow(), vec!["c.cls"]);
+    }
+}
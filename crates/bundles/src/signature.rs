@@ -0,0 +1,121 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Verifying the authenticity of bundles.
+//!
+//! A bundle’s digest, stored in its `SHA256SUM` file, summarizes its contents,
+//! but since it comes from the bundle itself, it doesn't prove anything about
+//! where the bundle came from. A bundle publisher can additionally sign the
+//! digest with an [Ed25519] key. The signature is distributed alongside the
+//! bundle, as a file whose name is that of the bundle plus `.sig`, containing
+//! the hex-encoded signature of the raw 32 bytes of the digest.
+//!
+//! [Ed25519]: https://ed25519.cr.yp.to/
+//!
+//! A [`SignaturePolicy`] lists the keys of the publishers that are trusted.
+//! When a policy with at least one trusted key is given to a
+//! [`crate::cache::Cache`], bundles opened through it must carry a valid
+//! signature by one of those keys before they can be used. The cache then also
+//! requires the bundle's index to give the digest of every file, checks that
+//! these add up to the signed digest as computed by
+//! [`crate::export::compute_bundle_digest`], and checks each file that it
+//! fetches against its digest.
+
+use ed25519_dalek::{Signature, VerifyingKey, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use std::convert::TryFrom;
+use tectonic_errors::prelude::*;
+use tectonic_io_base::digest::{self, DigestData};
+
+/// The suffix added to the name of a bundle to get the name of its signature
+/// file.
+pub const SIGNATURE_SUFFIX: &str = ".sig";
+
+/// A set of keys that are trusted to sign bundles.
+///
+/// The default policy has no keys, in which case signatures are not checked at
+/// all.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SignaturePolicy {
+    keys: Vec<VerifyingKey>,
+}
+
+impl SignaturePolicy {
+    /// Add a trusted key, given as a hex-encoded Ed25519 public key.
+    pub fn add_trusted_key(&mut self, hex_key: &str) -> Result<&mut Self> {
+        let mut bytes = [0u8; PUBLIC_KEY_LENGTH];
+        atry!(
+            digest::hex_to_bytes(hex_key.trim(), &mut bytes);
+            ["bundle signing key `{}` is not a valid hex-encoded public key", hex_key]
+        );
+        let key = atry!(
+            VerifyingKey::from_bytes(&bytes);
+            ["bundle signing key `{}` is not a valid Ed25519 public key", hex_key]
+        );
+        self.keys.push(key);
+        Ok(self)
+    }
+
+    /// Returns true if signatures must be checked under this policy.
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Check a bundle signature.
+    ///
+    /// The *signature_text* is the contents of a bundle signature file. The
+    /// check passes if it is a valid signature of *digest* by any one of the
+    /// trusted keys, or if the policy isn't enabled.
+    pub fn verify(&self, digest: &DigestData, signature_text: &str) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let mut bytes = [0u8; SIGNATURE_LENGTH];
+        atry!(
+            digest::hex_to_bytes(signature_text.trim(), &mut bytes);
+            ["the signature of bundle {} is malformed", digest]
+        );
+        let signature = Signature::try_from(&bytes[..])?;
+
+        for key in &self.keys {
+            if key.verify_strict(digest.as_bytes(), &signature).is_ok() {
+                return Ok(());
+            }
+        }
+
+        bail!(
+            "the signature of bundle {} is not valid for any trusted key; \
+            the bundle may have been tampered with",
+            digest
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn verification() {
+        let signer = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let digest = DigestData::of_nothing();
+        let sig_text = digest::bytes_to_hex(&signer.sign(digest.as_bytes()).to_bytes());
+
+        let mut policy = SignaturePolicy::default();
+        assert!(policy.verify(&digest, "garbage").is_ok());
+
+        policy
+            .add_trusted_key(&digest::bytes_to_hex(other.verifying_key().as_bytes()))
+            .unwrap();
+        assert!(policy.verify(&digest, &sig_text).is_err());
+
+        policy
+            .add_trusted_key(&digest::bytes_to_hex(signer.verifying_key().as_bytes()))
+            .unwrap();
+        assert!(policy.verify(&digest, &sig_text).is_ok());
+        assert!(policy.verify(&DigestData::zeros(), &sig_text).is_err());
+        assert!(policy.verify(&digest, "garbage").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Local tar files as Tectonic bundles.
+//!
+//! This is the same file format that backs the web-based
+//! [`crate::itar::IndexedTarBackend`], but read from the local filesystem. The
+//! tar file may optionally be compressed with gzip.
+
+use flate2::read::GzDecoder;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+};
+use tar::Archive;
+use tectonic_errors::prelude::*;
+use tectonic_io_base::{InputHandle, InputOrigin, IoProvider, OpenResult};
+use tectonic_status_base::StatusBackend;
+
+use crate::{
+    cache::CacheBackend,
+    itar::{FileInfo, IndexedTarBackend},
+    Bundle,
+};
+
+/// A bundle backed by a tar file.
+///
+/// To find files quickly, the bundle needs an index giving the location of
+/// each file within the tar data. This can be provided in the same format
+/// used by [`crate::itar::IndexedTarBackend`]; otherwise, it is built by
+/// scanning the whole file when the bundle is created.
+pub struct TarBundle<R: Read + Seek> {
+    reader: R,
+    index: HashMap<String, FileInfo>,
+
+    /// The size of the tar data, against which index entries are checked.
+    size: u64,
+}
+
+impl<R: Read + Seek> TarBundle<R> {
+    /// Create a new tar bundle for a generic readable and seekable stream of
+    /// uncompressed tar data.
+    ///
+    /// The stream is scanned to build an index of its contents.
+    pub fn new(reader: R) -> Result<TarBundle<R>> {
+        let mut archive = Archive::new(reader);
+        let mut index = HashMap::new();
+
+        for entry in archive.entries_with_seek()? {
+            let entry = entry?;
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            // Non-UTF8 names can't be requested by the engines anyway.
+            let name = match entry.path()?.to_str() {
+                Some(s) => s.trim_start_matches("./").to_owned(),
+                None => continue,
+            };
+
+            index.insert(
+                name,
+                FileInfo {
+                    offset: entry.raw_file_position(),
+                    length: entry.size(),
+                    digest: None,
+                },
+            );
+        }
+
+        let mut reader = archive.into_inner();
+        let size = reader.seek(SeekFrom::End(0))?;
+        Ok(TarBundle {
+            reader,
+            index,
+            size,
+        })
+    }
+
+    /// Create a new tar bundle with a pre-built index.
+    ///
+    /// The index data should be in the format used by
+    /// [`crate::itar::IndexedTarBackend`]: one line per file, giving its name,
+    /// the byte offset of its data within the tar stream, and its length.
+    pub fn new_with_index<I: Read>(mut reader: R, index_data: I) -> Result<TarBundle<R>> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        let mut index = HashMap::new();
+
+        for line in BufReader::new(index_data).lines() {
+            if let Ok((name, info)) = IndexedTarBackend::parse_index_line(&line?) {
+                index.insert(name, info);
+            }
+        }
+
+        Ok(TarBundle {
+            reader,
+            index,
+            size,
+        })
+    }
+}
+
+impl TarBundle<File> {
+    /// Open a file on the filesystem as a tar bundle.
+    ///
+    /// If the file is compressed with gzip, it is decompressed into a
+    /// temporary file, since the bundle needs to be able to seek within it. If
+    /// there is a file next to it with the same name plus `.index.gz`, it is
+    /// used as the index, saving the need to scan the tar data.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<TarBundle<File>> {
+        let path = path.as_ref();
+        let mut file = atry!(
+            File::open(path);
+            ["failed to open `{}`", path.display()]
+        );
+
+        let mut magic = [0u8; 2];
+        let is_gzipped = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+        file.seek(SeekFrom::Start(0))?;
+
+        if is_gzipped {
+            let mut decompressed = tempfile::tempfile()?;
+            atry!(
+                io::copy(&mut GzDecoder::new(BufReader::new(file)), &mut decompressed);
+                ["failed to decompress `{}`", path.display()]
+            );
+            decompressed.seek(SeekFrom::Start(0))?;
+            file = decompressed;
+        }
+
+        let mut index_path = path.as_os_str().to_owned();
+        index_path.push(".index.gz");
+
+        match File::open(&index_path) {
+            Ok(f) => Self::new_with_index(file, GzDecoder::new(f)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(atry!(
+                Self::new(file);
+                ["failed to read `{}` as a tar file", path.display()]
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<R: Read + Seek> IoProvider for TarBundle<R> {
+    fn input_open_name(
+        &mut self,
+        name: &str,
+        _status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        let info = match self.index.get(name) {
+            Some(i) => *i,
+            None => return OpenResult::NotAvailable,
+        };
+
+        // A bad index could make us allocate a huge buffer below.
+        match info.offset.checked_add(info.length) {
+            Some(end) if end <= self.size => {}
+            _ => {
+                return OpenResult::Err(anyhow!(
+                    "the index entry for `{}` extends past the end of the tar data",
+                    name
+                ))
+            }
+        }
+
+        // As with ZIP bundles, the simplest approach is to read the whole file
+        // into memory.
+
+        let mut buf = vec![0; info.length as usize];
+
+        if let Err(e) = self.reader.seek(SeekFrom::Start(info.offset)) {
+            return OpenResult::Err(e.into());
+        }
+
+        if let Err(e) = self.reader.read_exact(&mut buf) {
+            return OpenResult::Err(e.into());
+        }
+
+        OpenResult::Ok(InputHandle::new_read_only(
+            name,
+            Cursor::new(buf),
+            InputOrigin::Other,
+        ))
+    }
+}
+
+impl<R: Read + Seek> Bundle for TarBundle<R> {
+    fn all_files(&mut self, _status: &mut dyn StatusBackend) -> Result<Vec<String>> {
+        Ok(self.index.keys().cloned().collect())
+    }
+}
+
+/// Returns true if the path has a file extension suggesting that it is a
+/// tar file, possibly compressed: `.tar`, `.tar.gz`, or `.tgz`.
+pub fn has_tar_extension<P: AsRef<Path>>(path: P) -> bool {
+    match path.as_ref().file_name().and_then(|s| s.to_str()) {
+        Some(name) => {
+            let name = name.to_ascii_lowercase();
+            name.ends_with(".tar") || name.ends_with(".tar.gz") || name.ends_with(".tgz")
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tectonic_status_base::NoopStatusBackend;
+
+    fn make_tar() -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+
+        for (name, data) in [("./a.tex", &b"alpha"[..]), ("b.sty", &b"beta!"[..])] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append_data(&mut header, name, data).unwrap();
+        }
+
+        builder.into_inner().unwrap()
+    }
+
+    /// Build an index for tar data in the `IndexedTarBackend` format.
+    fn make_index(data: &[u8]) -> String {
+        let mut archive = Archive::new(Cursor::new(data));
+        let mut index = String::new();
+
+        for entry in archive.entries_with_seek().unwrap() {
+            let entry = entry.unwrap();
+            index.push_str(&format!(
+                "{} {} {}\n",
+                entry
+                    .path()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .trim_start_matches("./"),
+                entry.raw_file_position(),
+                entry.size()
+            ));
+        }
+
+        index
+    }
+
+    fn read_file<R: Read + Seek>(bundle: &mut TarBundle<R>, name: &str) -> Option<String> {
+        let mut status = NoopStatusBackend::default();
+
+        match bundle.input_open_name(name, &mut status) {
+            OpenResult::Ok(mut ih) => {
+                let mut s = String::new();
+                ih.read_to_string(&mut s).unwrap();
+                Some(s)
+            }
+            OpenResult::NotAvailable => None,
+            OpenResult::Err(e) => panic!("failed to open `{}`: {}", name, e),
+        }
+    }
+
+    #[test]
+    fn scan_and_read() {
+        let data = make_tar();
+        let mut bundle = TarBundle::new(Cursor::new(data)).unwrap();
+        let mut status = NoopStatusBackend::default();
+
+        let mut files = bundle.all_files(&mut status).unwrap();
+        files.sort();
+        assert_eq!(files, vec!["a.tex", "b.sty"]);
+
+        let mut contents = String::new();
+        bundle
+            .input_open_name("b.sty", &mut status)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "beta!");
+
+        assert!(matches!(
+            bundle.input_open_name("c.cls", &mut status),
+            OpenResult::NotAvailable
+        ));
+    }
+
+    #[test]
+    fn with_index() {
+        let data = make_tar();
+        let index = make_index(&data);
+        let mut bundle = TarBundle::new_with_index(Cursor::new(data), index.as_bytes()).unwrap();
+
+        assert_eq!(read_file(&mut bundle, "a.tex").as_deref(), Some("alpha"));
+        assert_eq!(read_file(&mut bundle, "b.sty").as_deref(), Some("beta!"));
+        assert_eq!(read_file(&mut bundle, "c.cls"), None);
+    }
+
+    #[test]
+    fn bad_index() {
+        let data = make_tar();
+        let size = data.len();
+        let index = format!(
+            "past-end.tex {} 10\nhuge.tex 0 {}\nwrap.tex {} 2\n",
+            size - 5,
+            u64::MAX,
+            u64::MAX
+        );
+        let mut bundle = TarBundle::new_with_index(Cursor::new(data), index.as_bytes()).unwrap();
+        let mut status = NoopStatusBackend::default();
+
+        for name in ["past-end.tex", "huge.tex", "wrap.tex"] {
+            assert!(matches!(
+                bundle.input_open_name(name, &mut status),
+                OpenResult::Err(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn open_gzipped() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let data = make_tar();
+
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&data).unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        std::fs::write(&path, gz.finish().unwrap()).unwrap();
+
+        let mut bundle = TarBundle::open(&path).unwrap();
+        assert_eq!(read_file(&mut bundle, "a.tex").as_deref(), Some("alpha"));
+        assert_eq!(read_file(&mut bundle, "c.cls"), None);
+
+        // With an index next to it, the index is used instead of scanning.
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(b"b.sty 0 5\n").unwrap();
+        std::fs::write(
+            dir.path().join("bundle.tar.gz.index.gz"),
+            gz.finish().unwrap(),
+        )
+        .unwrap();
+
+        let mut bundle = TarBundle::open(&path).unwrap();
+        let mut status = NoopStatusBackend::default();
+        assert_eq!(bundle.all_files(&mut status).unwrap(), vec!["b.sty"]);
+        assert_eq!(read_file(&mut bundle, "a.tex"), None);
+    }
+}
@@ -3,16 +3,15 @@
 
 //! A single Tectonic document.
 //!
-//! Every document is part of a [`crate::workspace::Workspace`]. At the moment
-//! workspaces can only contain a single document each, but in the future it
-//! might become possible for one workspace to contain multiple documents.
+//! Every document is part of a [`crate::workspace::Workspace`], which may
+//! contain just that one document or several of them.
 //!
 //! This crate, on its own, does not provide document-processing capabilities.
 //! The main `tectonic` crate provides extension traits that set up document
 //! processing, in the `tectonic::docmodel` module.
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     env, fs,
     io::{Read, Write},
     path::{Component, Path, PathBuf},
@@ -39,9 +38,9 @@ pub struct Document {
     build_dir: PathBuf,
 
     /// Arbitrary document metadata.
-    /// This has no effect on tectonic's build process.
-    /// Rather, allows users to add easily-accessible information to their documents,
-    /// which may be read by external tools.
+    /// This allows users to add easily-accessible information to their documents,
+    /// which may be read by external tools. The main `tectonic` crate also makes
+    /// it available to the TeX code and to HTML templates.
     pub metadata: Option<toml::Value>,
 
     /// The document name. This will be used to name build artifacts and the
@@ -53,6 +52,20 @@ pub struct Document {
     /// Either a URL or a local path.
     pub bundle_loc: String,
 
+    /// Additional bundles layered on top of the core bundle, as URLs or local
+    /// paths.
+    ///
+    /// Files in these bundles take precedence over those in the core bundle,
+    /// and files in earlier bundles take precedence over those in later ones.
+    /// In `Tectonic.toml`, these are specified by making `bundle` a list, with
+    /// the core bundle last.
+    pub overlay_bundle_locs: Vec<String>,
+
+    /// Whether intermediate files such as `.aux` files should be carried over
+    /// from one build to the next, so that documents whose cross-references
+    /// haven't changed can be built with fewer TeX passes.
+    pub reuse_intermediates: bool,
+
     /// The different outputs that are created from the document source. These
     /// may have different formats (e.g., PDF and HTML) or the same format but
     /// different settings (e.g., PDF with A4 paper and PDF with US Letter
@@ -74,11 +87,47 @@ impl Document {
     ) -> Result<Self> {
         let mut toml_text = String::new();
         toml_data.read_to_string(&mut toml_text)?;
-        let doc: syntax::TomlDocument = toml::from_str(&toml_text)?;
+        Self::new_from_toml_in_workspace(
+            src_dir.into(),
+            build_dir.into(),
+            &toml_text,
+            &syntax::TomlWorkspaceSection::default(),
+        )
+    }
+
+    /// Initialize a Document that is a member of a multi-document workspace.
+    ///
+    /// Settings that the document doesn’t specify itself are taken from the
+    /// `[workspace]` section of the workspace manifest, if they’re given there.
+    pub(crate) fn new_from_toml_in_workspace(
+        src_dir: PathBuf,
+        build_dir: PathBuf,
+        toml_text: &str,
+        workspace: &syntax::TomlWorkspaceSection,
+    ) -> Result<Self> {
+        let doc: syntax::TomlDocument = toml::from_str(toml_text)?;
 
         let mut outputs = HashMap::new();
 
-        for toml_output in &doc.outputs {
+        for toml_output in &resolve_output_profiles(&doc.outputs)? {
+            if toml_output.target_type.is_none() {
+                bail!(
+                    "output `{}` in TOML specification must define a type",
+                    &toml_output.name
+                );
+            }
+
+            for name in toml_output.defines.iter().flat_map(|d| d.keys()) {
+                if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+                    bail!(
+                        "invalid macro name `{}` in the definitions of output `{}`; \
+                        names may only contain the letters A-Z and a-z",
+                        name,
+                        &toml_output.name
+                    );
+                }
+            }
+
             let output: OutputProfile = toml_output.into();
 
             if outputs.insert(output.name.clone(), output).is_some() {
@@ -93,12 +142,34 @@ impl Document {
             bail!("TOML specification must define at least one output");
         }
 
+        let bundle = a_ok_or!(
+            doc.doc.bundle.or_else(|| workspace.bundle.clone());
+            ["TOML specification must define a bundle, unless it is inherited from a workspace"]
+        );
+
+        let (bundle_loc, overlay_bundle_locs) = match bundle {
+            syntax::TomlBundleLocs::One(loc) => (loc, Vec::new()),
+            syntax::TomlBundleLocs::Many(mut locs) => {
+                let loc = a_ok_or!(
+                    locs.pop();
+                    ["TOML specification must define at least one bundle"]
+                );
+                (loc, locs)
+            }
+        };
+
         Ok(Document {
-            src_dir: src_dir.into(),
-            build_dir: build_dir.into(),
+            src_dir,
+            build_dir,
             name: doc.doc.name,
-            bundle_loc: doc.doc.bundle,
+            bundle_loc,
+            overlay_bundle_locs,
             metadata: doc.doc.metadata,
+            reuse_intermediates: doc
+                .doc
+                .reuse_intermediates
+                .or(workspace.reuse_intermediates)
+                .unwrap_or(false),
             outputs,
         })
     }
@@ -119,8 +190,19 @@ impl Document {
         let doc = syntax::TomlDocument {
             doc: syntax::TomlDocSection {
                 name: self.name.clone(),
-                bundle: self.bundle_loc.clone(),
+                bundle: Some(if self.overlay_bundle_locs.is_empty() {
+                    syntax::TomlBundleLocs::One(self.bundle_loc.clone())
+                } else {
+                    let mut locs = self.overlay_bundle_locs.clone();
+                    locs.push(self.bundle_loc.clone());
+                    syntax::TomlBundleLocs::Many(locs)
+                }),
                 metadata: None,
+                reuse_intermediates: if self.reuse_intermediates {
+                    Some(true)
+                } else {
+                    None
+                },
             },
             outputs,
         };
@@ -177,14 +259,29 @@ impl Document {
         p.push(&profile.name);
 
         match profile.target_type {
-            BuildTargetType::Pdf => {
+            BuildTargetType::Aux => {
                 p.push(&profile.name);
-                p.set_extension("pdf");
+                p.set_extension("aux");
+            }
+
+            BuildTargetType::Format => {
+                p.push(&profile.name);
+                p.set_extension("fmt");
             }
 
             BuildTargetType::Html => {
                 p.push("index.html");
             }
+
+            BuildTargetType::Pdf => {
+                p.push(&profile.name);
+                p.set_extension("pdf");
+            }
+
+            BuildTargetType::Xdv => {
+                p.push(&profile.name);
+                p.set_extension("xdv");
+            }
         }
 
         p
@@ -192,7 +289,7 @@ impl Document {
 }
 
 /// Persistent settings for a document build.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct OutputProfile {
     /// The name of this profile.
     pub name: String,
@@ -221,16 +318,134 @@ pub struct OutputProfile {
     /// Directory is not managed and any files created in it will not be deleted.
     ///
     pub shell_escape_cwd: Option<String>,
+
+    /// The initial paper size, as understood by `xdvipdfmx`, such as `a4`. If
+    /// unset, the engine default is used.
+    pub paper_size: Option<String>,
+
+    /// Whether to generate SyncTeX data.
+    pub synctex: bool,
+
+    /// If set, rerun the TeX engine exactly this many times, rather than
+    /// detecting how many reruns are needed.
+    pub reruns: Option<usize>,
+
+    /// The maximum number of times to rerun the TeX engine when detecting
+    /// how many reruns are needed. If unset, the engine default is used.
+    pub max_reruns: Option<usize>,
+
+    /// Files whose changes shouldn't cause the TeX engine to be rerun. A name
+    /// starting with `*` matches every file whose name ends with the rest of
+    /// it, such as `*.toc`.
+    pub rerun_ignored_files: Vec<String>,
+
+    /// Whether it is an error for the document to still need a rerun after
+    /// the maximum number of reruns, rather than just a warning.
+    pub error_on_nonconvergence: bool,
+
+    /// Whether to save intermediate files, such as `.aux` files, in the
+    /// build directory.
+    pub keep_intermediates: bool,
+
+    /// Whether to save the TeX log file in the build directory.
+    pub keep_logs: bool,
+
+    /// Extra directories in which to search for input files, relative to the
+    /// document’s `src` directory. These are ignored if the build is
+    /// untrusted.
+    pub search_paths: Vec<String>,
+
+    /// Files, relative to the document’s `src` directory, that the engines
+    /// should pretend don’t exist.
+    pub hidden_files: Vec<String>,
+
+    /// Whether to ensure a deterministic build environment.
+    pub deterministic_mode: bool,
+
+    /// Whether to compress the streams in PDF output. This is on by default.
+    pub pdf_compression: bool,
+
+    /// Whether to keep going even when the TeX engine encounters severe
+    /// errors.
+    pub continue_on_errors: bool,
+
+    /// The number of cross-references to an entry after which BibTeX includes
+    /// it in the bibliography on its own, like its `-min-crossrefs` option.
+    pub min_crossrefs: Option<u32>,
+
+    /// The `makeindex` style file to use when generating indices.
+    pub index_style: Option<String>,
+
+    /// The resolution of PNG output, in dots per inch.
+    pub png_dpi: Option<u32>,
+
+    /// Whether to embed fonts in SVG output, rather than writing them out
+    /// separately.
+    pub svg_embed_fonts: bool,
+
+    /// Macros to define before the inputs are processed, mapping each macro
+    /// name, without the backslash, to its replacement text.
+    pub defines: BTreeMap<String, String>,
+}
+
+impl OutputProfile {
+    /// Create a new profile with the given name, target type, TeX format, and
+    /// inputs, and default values for all of the other settings.
+    pub fn new<S: Into<String>>(
+        name: S,
+        target_type: BuildTargetType,
+        tex_format: S,
+        inputs: Vec<InputFile>,
+    ) -> Self {
+        OutputProfile {
+            name: name.into(),
+            target_type,
+            tex_format: tex_format.into(),
+            inputs,
+            shell_escape: false,
+            shell_escape_cwd: None,
+            paper_size: None,
+            synctex: false,
+            reruns: None,
+            max_reruns: None,
+            rerun_ignored_files: Vec::new(),
+            error_on_nonconvergence: false,
+            keep_intermediates: false,
+            keep_logs: false,
+            search_paths: Vec::new(),
+            hidden_files: Vec::new(),
+            deterministic_mode: false,
+            pdf_compression: true,
+            continue_on_errors: false,
+            min_crossrefs: None,
+            index_style: None,
+            png_dpi: None,
+            svg_embed_fonts: false,
+            defines: BTreeMap::new(),
+        }
+    }
 }
 
 /// The output target type of a document build.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum BuildTargetType {
+    /// Output only the `.aux` file, without going on to create a PDF. This is
+    /// useful for tools that track the dependencies of a build.
+    Aux,
+
+    /// Output a TeX format file, for initializing the engine. The inputs
+    /// should end with `\dump`.
+    Format,
+
     /// Output a tree of HTML files
     Html,
 
     /// Output to the Portable Document Format (PDF).
     Pdf,
+
+    /// Output an extended DVI (XDV) file, as created by the TeX engine before
+    /// it is converted to PDF.
+    Xdv,
 }
 
 /// An input provided to a document build
@@ -288,27 +503,67 @@ impl Document {
             build_dir,
             name,
             bundle_loc,
+            overlay_bundle_locs: Vec::new(),
             outputs: crate::document::default_outputs(),
             metadata: None,
+            reuse_intermediates: false,
         })
     }
 }
 
+/// Resolve the `extends` settings of a document's output profiles, returning
+/// copies of the profiles with all of their inherited settings filled in.
+fn resolve_output_profiles(
+    profiles: &[syntax::TomlOutputProfile],
+) -> Result<Vec<syntax::TomlOutputProfile>> {
+    let by_name: HashMap<&str, &syntax::TomlOutputProfile> =
+        profiles.iter().map(|p| (p.name.as_str(), p)).collect();
+
+    let mut resolved = Vec::with_capacity(profiles.len());
+
+    for profile in profiles {
+        let mut result = profile.clone();
+        let mut seen = HashSet::new();
+        seen.insert(profile.name.as_str());
+        let mut current = profile;
+
+        while let Some(parent_name) = &current.extends {
+            let parent = a_ok_or!(
+                by_name.get(parent_name.as_str());
+                ["output `{}` extends unknown output `{}`", &current.name, parent_name]
+            );
+
+            if !seen.insert(parent_name.as_str()) {
+                bail!(
+                    "output `{}` extends itself, through `{}`",
+                    &profile.name,
+                    &current.name
+                );
+            }
+
+            result.inherit_from(parent);
+            current = parent;
+        }
+
+        resolved.push(result);
+    }
+
+    Ok(resolved)
+}
+
 pub(crate) fn default_outputs() -> HashMap<String, OutputProfile> {
     let mut outputs = HashMap::new();
     outputs.insert(
         "default".to_owned(),
-        OutputProfile {
-            name: "default".to_owned(),
-            target_type: BuildTargetType::Pdf,
-            tex_format: "latex".to_owned(),
-            inputs: DEFAULT_INPUTS
+        OutputProfile::new(
+            "default",
+            BuildTargetType::Pdf,
+            "latex",
+            DEFAULT_INPUTS
                 .iter()
                 .map(|x| InputFile::File(x.to_string()))
                 .collect(),
-            shell_escape: false,
-            shell_escape_cwd: None,
-        },
+        ),
     );
     outputs
 }
@@ -353,4 +608,208 @@ mod tests {
         let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
         assert!(doc.outputs.get("o").unwrap().shell_escape);
     }
+
+    #[test]
+    fn reuse_intermediates() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+        reuse_intermediates = true
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        assert!(doc.reuse_intermediates);
+    }
+
+    #[test]
+    fn overlay_bundles() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = ["house-style", "extra.zip", "na"]
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        assert_eq!(doc.bundle_loc, "na");
+        assert_eq!(doc.overlay_bundle_locs, ["house-style", "extra.zip"]);
+
+        const EMPTY: &str = r#"
+        [doc]
+        name = "test"
+        bundle = []
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+        "#;
+
+        let mut c = Cursor::new(EMPTY.as_bytes());
+        assert!(Document::new_from_toml(".", ".", &mut c).is_err());
+    }
+
+    #[test]
+    fn extends_and_defines() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "final"
+        type = "pdf"
+        inputs = ["main.tex"]
+        paper_size = "letter"
+
+        [output.defines]
+        draft = "false"
+        papersize = "letter"
+
+        [[output]]
+        name = "draft"
+        extends = "final"
+
+        [output.defines]
+        draft = "true"
+
+        [[output]]
+        name = "draft-a4"
+        extends = "draft"
+        paper_size = "a4"
+        inputs = ["other.tex"]
+
+        [output.defines]
+        papersize = "a4"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+
+        let draft = &doc.outputs["draft"];
+        assert_eq!(draft.target_type, BuildTargetType::Pdf);
+        assert_eq!(draft.inputs, [InputFile::File("main.tex".to_owned())]);
+        assert_eq!(draft.paper_size.as_deref(), Some("letter"));
+        assert_eq!(draft.defines["draft"], "true");
+        assert_eq!(draft.defines["papersize"], "letter");
+
+        let draft_a4 = &doc.outputs["draft-a4"];
+        assert_eq!(draft_a4.inputs, [InputFile::File("other.tex".to_owned())]);
+        assert_eq!(draft_a4.paper_size.as_deref(), Some("a4"));
+        assert_eq!(draft_a4.defines["draft"], "true");
+        assert_eq!(draft_a4.defines["papersize"], "a4");
+
+        for bad in [
+            "[[output]]\nname = 'o'\ntype = 'pdf'\nextends = 'nope'",
+            "[[output]]\nname = 'o'\nextends = 'p'\n[[output]]\nname = 'p'\nextends = 'o'",
+            "[[output]]\nname = 'o'",
+            "[[output]]\nname = 'o'\ntype = 'pdf'\n[output.defines]\nno1 = 'x'",
+        ] {
+            let text = format!("[doc]\nname = 'test'\nbundle = 'na'\n{bad}");
+            let mut c = Cursor::new(text.as_bytes());
+            assert!(Document::new_from_toml(".", ".", &mut c).is_err());
+        }
+    }
+
+    #[test]
+    fn target_types() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "x"
+        type = "xdv"
+
+        [[output]]
+        name = "a"
+        type = "aux"
+
+        [[output]]
+        name = "f"
+        type = "fmt"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", "build", &mut c).unwrap();
+        assert_eq!(doc.outputs["x"].target_type, BuildTargetType::Xdv);
+        assert_eq!(doc.output_main_file("x"), Path::new("build/x/x.xdv"));
+        assert_eq!(doc.outputs["a"].target_type, BuildTargetType::Aux);
+        assert_eq!(doc.output_main_file("a"), Path::new("build/a/a.aux"));
+        assert_eq!(doc.outputs["f"].target_type, BuildTargetType::Format);
+        assert_eq!(doc.output_main_file("f"), Path::new("build/f/f.fmt"));
+    }
+
+    #[test]
+    fn session_settings_round_trip() {
+        const TOML: &str = r#"
+        [doc]
+        name = "test"
+        bundle = "na"
+
+        [[output]]
+        name = "o"
+        type = "pdf"
+        paper_size = "a4"
+        synctex = true
+        reruns = 2
+        max_reruns = 4
+        rerun_ignored_files = ["*.toc"]
+        error_on_nonconvergence = true
+        keep_intermediates = true
+        keep_logs = true
+        search_paths = ["../styles"]
+        hidden_files = ["old.aux"]
+        deterministic_mode = true
+        pdf_compression = false
+        continue_on_errors = true
+        min_crossrefs = 3
+        index_style = "custom.ist"
+        png_dpi = 300
+        svg_embed_fonts = true
+
+        [output.defines]
+        draft = "true"
+        "#;
+
+        let mut c = Cursor::new(TOML.as_bytes());
+        let doc = Document::new_from_toml(".", ".", &mut c).unwrap();
+        let profile = doc.outputs.get("o").unwrap();
+        assert_eq!(profile.paper_size.as_deref(), Some("a4"));
+        assert_eq!(profile.reruns, Some(2));
+        assert_eq!(profile.max_reruns, Some(4));
+        assert_eq!(profile.rerun_ignored_files, ["*.toc"]);
+        assert!(profile.error_on_nonconvergence);
+        assert_eq!(profile.search_paths, ["../styles"]);
+        assert!(!profile.pdf_compression);
+
+        let dir = tempfile::tempdir().unwrap();
+        let doc = Document {
+            src_dir: dir.path().to_owned(),
+            ..doc
+        };
+        doc.create_toml().unwrap();
+
+        let mut f = fs::File::open(dir.path().join("Tectonic.toml")).unwrap();
+        let doc2 = Document::new_from_toml(".", ".", &mut f).unwrap();
+        assert_eq!(doc2.outputs, doc.outputs);
+
+        // Default settings aren't written out.
+        let defaults = syntax::TomlOutputProfile::from(&default_outputs()["default"]);
+        let text = toml::to_string(&defaults).unwrap();
+        assert!(!text.contains("pdf_compression"));
+        assert!(!text.contains("synctex"));
+        assert!(!text.contains("rerun_ignored_files"));
+        assert!(!text.contains("error_on_nonconvergence"));
+    }
 }
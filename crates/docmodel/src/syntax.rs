@@ -8,6 +8,7 @@
 
 use crate::document::{BuildTargetType, InputFile, OutputProfile};
 use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
 
 // This file is an exercise in Rust type conversion.
 //
@@ -28,11 +29,39 @@ pub struct TomlDocument {
 #[serde(deny_unknown_fields)]
 pub struct TomlDocSection {
     pub name: String,
-    pub bundle: String,
+    pub bundle: Option<TomlBundleLocs>,
     pub metadata: Option<toml::Value>,
+    pub reuse_intermediates: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Either a single bundle location, or a list of them to be layered on top of
+/// one another.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TomlBundleLocs {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// A `Tectonic.toml` file that defines a workspace of several documents,
+/// rather than a single document.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlWorkspaceManifest {
+    pub workspace: TomlWorkspaceSection,
+}
+
+/// Settings in the `[workspace]` section are inherited by member documents
+/// that don't specify them themselves.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlWorkspaceSection {
+    pub members: Vec<String>,
+    pub bundle: Option<TomlBundleLocs>,
+    pub reuse_intermediates: Option<bool>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
 #[serde(untagged)]
 pub enum StringOrInputVec {
     String(TomlInputFile),
@@ -86,12 +115,21 @@ impl From<TomlInputFile> for InputFile {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct TomlOutputProfile {
     pub name: String,
+
+    // The name of another profile whose settings are used for any that aren't
+    // given in this one. These are resolved before profiles are converted
+    // into `OutputProfile`s, so this is never written out.
+    #[serde(skip_serializing)]
+    pub extends: Option<String>,
+
+    // This is optional so that it can be inherited, but the resolved profile
+    // must have one.
     #[serde(rename = "type")]
-    pub target_type: TomlBuildTargetType,
+    pub target_type: Option<TomlBuildTargetType>,
     pub tex_format: Option<String>,
     pub shell_escape: Option<bool>,
     pub shell_escape_cwd: Option<String>,
@@ -110,6 +148,112 @@ pub struct TomlOutputProfile {
     pub index_file: Option<String>,
     #[serde(rename = "postamble")]
     pub postamble_file: Option<String>,
+
+    // Session settings. These are all optional, and are only written out if
+    // they differ from the defaults.
+    pub paper_size: Option<String>,
+    pub synctex: Option<bool>,
+    pub reruns: Option<usize>,
+    pub max_reruns: Option<usize>,
+    pub rerun_ignored_files: Option<Vec<String>>,
+    pub error_on_nonconvergence: Option<bool>,
+    pub keep_intermediates: Option<bool>,
+    pub keep_logs: Option<bool>,
+    pub search_paths: Option<Vec<String>>,
+    pub hidden_files: Option<Vec<String>>,
+    pub deterministic_mode: Option<bool>,
+    pub pdf_compression: Option<bool>,
+    pub continue_on_errors: Option<bool>,
+    pub min_crossrefs: Option<u32>,
+    pub index_style: Option<String>,
+    pub png_dpi: Option<u32>,
+    pub svg_embed_fonts: Option<bool>,
+
+    // Macros to `\def` before the inputs. This is last so that it's
+    // serialized as a subtable after all of the simple values.
+    pub defines: Option<BTreeMap<String, String>>,
+}
+
+impl TomlOutputProfile {
+    /// Fill in any settings that this profile doesn't specify from *parent*.
+    ///
+    /// The inputs are treated as a unit: if this profile specifies any of
+    /// them, in either the new or the old-fashioned style, none of the
+    /// parent's are used. Definitions are merged, with this profile's taking
+    /// precedence.
+    pub fn inherit_from(&mut self, parent: &TomlOutputProfile) {
+        macro_rules! inherit {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field.is_none() {
+                        self.$field = parent.$field.clone();
+                    }
+                )*
+            };
+        }
+
+        inherit!(
+            target_type,
+            tex_format,
+            shell_escape,
+            shell_escape_cwd,
+            paper_size,
+            synctex,
+            reruns,
+            max_reruns,
+            rerun_ignored_files,
+            error_on_nonconvergence,
+            keep_intermediates,
+            keep_logs,
+            search_paths,
+            hidden_files,
+            deterministic_mode,
+            pdf_compression,
+            continue_on_errors,
+            min_crossrefs,
+            index_style,
+            png_dpi,
+            svg_embed_fonts
+        );
+
+        if self.inputs.is_none()
+            && self.preamble_file.is_none()
+            && self.index_file.is_none()
+            && self.postamble_file.is_none()
+        {
+            self.inputs = parent.inputs.clone();
+            self.preamble_file = parent.preamble_file.clone();
+            self.index_file = parent.index_file.clone();
+            self.postamble_file = parent.postamble_file.clone();
+        }
+
+        if let Some(parent_defines) = &parent.defines {
+            let defines = self.defines.get_or_insert_with(BTreeMap::new);
+
+            for (name, value) in parent_defines {
+                defines.entry(name.clone()).or_insert_with(|| value.clone());
+            }
+        }
+    }
+}
+
+/// Convert a boolean setting to its TOML form, which is omitted if it has its
+/// default value.
+fn non_default(value: bool, default: bool) -> Option<bool> {
+    if value == default {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Convert a list setting to its TOML form, which is omitted if it is empty.
+fn non_empty(value: &[String]) -> Option<Vec<String>> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_vec())
+    }
 }
 
 impl From<&TomlOutputProfile> for OutputProfile {
@@ -139,7 +283,8 @@ impl From<&TomlOutputProfile> for OutputProfile {
 
         OutputProfile {
             name: val.name.clone(),
-            target_type: val.target_type.into(),
+            // The document loader ensures that the type is set.
+            target_type: val.target_type.unwrap_or(TomlBuildTargetType::Pdf).into(),
             tex_format: val
                 .tex_format
                 .as_ref()
@@ -149,6 +294,24 @@ impl From<&TomlOutputProfile> for OutputProfile {
             inputs,
             shell_escape: val.shell_escape.unwrap_or(shell_escape_default),
             shell_escape_cwd: val.shell_escape_cwd.clone(),
+            paper_size: val.paper_size.clone(),
+            synctex: val.synctex.unwrap_or(false),
+            reruns: val.reruns,
+            max_reruns: val.max_reruns,
+            rerun_ignored_files: val.rerun_ignored_files.clone().unwrap_or_default(),
+            error_on_nonconvergence: val.error_on_nonconvergence.unwrap_or(false),
+            keep_intermediates: val.keep_intermediates.unwrap_or(false),
+            keep_logs: val.keep_logs.unwrap_or(false),
+            search_paths: val.search_paths.clone().unwrap_or_default(),
+            hidden_files: val.hidden_files.clone().unwrap_or_default(),
+            deterministic_mode: val.deterministic_mode.unwrap_or(false),
+            pdf_compression: val.pdf_compression.unwrap_or(true),
+            continue_on_errors: val.continue_on_errors.unwrap_or(false),
+            min_crossrefs: val.min_crossrefs,
+            index_style: val.index_style.clone(),
+            png_dpi: val.png_dpi,
+            svg_embed_fonts: val.svg_embed_fonts.unwrap_or(false),
+            defines: val.defines.clone().unwrap_or_default(),
         }
     }
 }
@@ -169,7 +332,8 @@ impl From<&OutputProfile> for TomlOutputProfile {
 
         TomlOutputProfile {
             name: rt.name.clone(),
-            target_type: TomlBuildTargetType::from(&rt.target_type),
+            extends: None,
+            target_type: Some(TomlBuildTargetType::from(&rt.target_type)),
             tex_format,
             inputs: Some(inputs),
             shell_escape,
@@ -177,24 +341,58 @@ impl From<&OutputProfile> for TomlOutputProfile {
             preamble_file: None,
             index_file: None,
             postamble_file: None,
+            paper_size: rt.paper_size.clone(),
+            synctex: non_default(rt.synctex, false),
+            reruns: rt.reruns,
+            max_reruns: rt.max_reruns,
+            rerun_ignored_files: non_empty(&rt.rerun_ignored_files),
+            error_on_nonconvergence: non_default(rt.error_on_nonconvergence, false),
+            keep_intermediates: non_default(rt.keep_intermediates, false),
+            keep_logs: non_default(rt.keep_logs, false),
+            search_paths: non_empty(&rt.search_paths),
+            hidden_files: non_empty(&rt.hidden_files),
+            deterministic_mode: non_default(rt.deterministic_mode, false),
+            pdf_compression: non_default(rt.pdf_compression, true),
+            continue_on_errors: non_default(rt.continue_on_errors, false),
+            min_crossrefs: rt.min_crossrefs,
+            index_style: rt.index_style.clone(),
+            png_dpi: rt.png_dpi,
+            svg_embed_fonts: non_default(rt.svg_embed_fonts, false),
+            defines: if rt.defines.is_empty() {
+                None
+            } else {
+                Some(rt.defines.clone())
+            },
         }
     }
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum TomlBuildTargetType {
+    #[serde(rename = "aux")]
+    Aux,
+
+    #[serde(rename = "fmt")]
+    Format,
+
     #[serde(rename = "html")]
     Html,
 
     #[serde(rename = "pdf")]
     Pdf,
+
+    #[serde(rename = "xdv")]
+    Xdv,
 }
 
 impl From<TomlBuildTargetType> for BuildTargetType {
     fn from(val: TomlBuildTargetType) -> BuildTargetType {
         match val {
+            TomlBuildTargetType::Aux => BuildTargetType::Aux,
+            TomlBuildTargetType::Format => BuildTargetType::Format,
             TomlBuildTargetType::Html => BuildTargetType::Html,
             TomlBuildTargetType::Pdf => BuildTargetType::Pdf,
+            TomlBuildTargetType::Xdv => BuildTargetType::Xdv,
         }
     }
 }
@@ -202,8 +400,11 @@ impl From<TomlBuildTargetType> for BuildTargetType {
 impl From<&BuildTargetType> for TomlBuildTargetType {
     fn from(s: &BuildTargetType) -> Self {
         match s {
+            BuildTargetType::Aux => TomlBuildTargetType::Aux,
+            BuildTargetType::Format => TomlBuildTargetType::Format,
             BuildTargetType::Html => TomlBuildTargetType::Html,
             BuildTargetType::Pdf => TomlBuildTargetType::Pdf,
+            BuildTargetType::Xdv => TomlBuildTargetType::Xdv,
         }
     }
 }
@@ -3,91 +3,265 @@
 
 //! A Tectonic document-build workspace.
 //!
-//! For the time being, this is just a thin wrapper to provide access to a
-//! `Document` instance. This API exists to future-proof a bit for a potential
-//! world where one workspace can contain multiple documents.
+//! A workspace is a directory tree containing one or more documents. In the
+//! simplest case, the workspace root contains a `Tectonic.toml` file defining a
+//! single document. Alternatively, the root `Tectonic.toml` file can define a
+//! workspace with a `[workspace]` section listing member directories, each of
+//! which contains a document with its own `Tectonic.toml` file. Settings such as
+//! the bundle location can then be given once, in the workspace manifest, and
+//! inherited by all of the members.
 
 use std::{
     env,
     error::Error,
     fmt, fs,
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use tectonic_errors::prelude::*;
 
-use crate::document::Document;
+use crate::{document::Document, syntax};
 
 /// A Tectonic workspace.
 ///
-/// For the time being, a Workspace is just a thin wrapper to provide access to
-/// a `Document` instance. In the future, it might become possible for one
-/// workspace to contain multiple documents.
-///
-/// In most cases, you will want to create a [`Workspace`] by opening an
-/// existing one using [`Workspace::open_from_environment`].
+/// A workspace contains one or more documents. In most cases, you will want to
+/// create a [`Workspace`] by opening an existing one using
+/// [`Workspace::open_from_environment`].
 #[derive(Debug)]
 pub struct Workspace {
     /// The root directory of the workspace.
-    #[allow(dead_code)] // We expect to use this eventually.
     root_dir: PathBuf,
 
-    /// This workspace's document. In the future, there might be more than one.
-    doc: Document,
+    /// This workspace's documents, in the order that they're listed in the
+    /// workspace manifest.
+    docs: Vec<Document>,
+
+    /// The index of the document whose source directory contains the
+    /// directory that the workspace was opened from, if any.
+    current: Option<usize>,
 }
 
 impl Workspace {
+    /// Get the root directory of the workspace.
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    /// Get all of the documents in the workspace.
+    pub fn documents(&self) -> &[Document] {
+        &self.docs
+    }
+
+    /// Get the document in the workspace with the given name, if there is one.
+    pub fn document(&self, name: &str) -> Option<&Document> {
+        self.docs.iter().find(|d| d.name == name)
+    }
+
+    /// Get the document that the workspace was opened from.
+    ///
+    /// This is the workspace member whose source directory contains the
+    /// directory passed to [`Workspace::open_from_directory`]. It is `None` if
+    /// the workspace was opened from its root and the root isn't itself a
+    /// document.
+    pub fn current_document(&self) -> Option<&Document> {
+        self.current.map(|i| &self.docs[i])
+    }
+
     /// Get the first document in the workspace.
     ///
-    /// Right now, workspaces in fact only include one document. That may change
-    /// in the future.
+    /// This is the [current document][Self::current_document], if there is
+    /// one, and otherwise the first member listed in the workspace manifest.
+    /// This is appropriate for commands that operate on just one document.
     pub fn first_document(&self) -> &Document {
-        &self.doc
+        &self.docs[self.current.unwrap_or(0)]
     }
 
     /// Get the first document in the workspace, mutably.
     ///
-    /// Right now, workspaces in fact only include one document. That may change
-    /// in the future.
+    /// See [`Self::first_document`] for how the document is chosen.
     pub fn first_document_mut(&mut self) -> &mut Document {
-        &mut self.doc
+        &mut self.docs[self.current.unwrap_or(0)]
     }
 
     /// Open up a workspace based on the current process environment.
     ///
-    /// This function searches the current directory and its parents for a
-    /// `Tectonic.toml` file. Because workspaces can currently only contain a
-    /// single document, the search stops when the first such file is found. If
-    /// no such file is found, an error downcastable into
-    /// [`NoWorkspaceFoundError`] is returned.
+    /// This is [`Workspace::open_from_directory`] applied to the current
+    /// directory.
     pub fn open_from_environment() -> Result<Self> {
-        let initial_dir = env::current_dir()?;
+        Self::open_from_directory(env::current_dir()?)
+    }
 
-        let mut root_dir = initial_dir.clone();
-        root_dir.push("tmp"); // simplifies loop logic
+    /// Open up a workspace containing the given directory.
+    ///
+    /// This function searches the directory and its parents for a
+    /// `Tectonic.toml` file. If the first one found defines a single document,
+    /// the search continues upwards, in case the document is a member of a
+    /// multi-document workspace; if not, the workspace consists of just that
+    /// document. Once a document has been found, problems with the files
+    /// further up, such as an unrelated `Tectonic.toml` that can't be read or
+    /// parsed, end the search rather than causing errors. If no such file is
+    /// found, an error downcastable into [`NoWorkspaceFoundError`] is returned.
+    pub fn open_from_directory<P: Into<PathBuf>>(initial_dir: P) -> Result<Self> {
+        let initial_dir = initial_dir.into();
+        let mut doc_manifest = None;
 
-        while root_dir.pop() {
-            root_dir.push("Tectonic.toml");
+        for dir in initial_dir.ancestors() {
+            let toml_path = dir.join("Tectonic.toml");
+
+            // Once we've found a document, problems with the manifests further
+            // up don't stop it from being used on its own.
+            let (toml_text, table) = match Self::read_manifest(&toml_path) {
+                Ok(Some(m)) => m,
+                Ok(None) => continue,
+                Err(_) if doc_manifest.is_some() => break,
+                Err(e) => return Err(e),
+            };
 
-            let mut doc_file = match fs::File::open(&root_dir) {
-                Ok(f) => f,
-                Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
-                    root_dir.pop(); // remove "Tectonic.toml"
-                    continue; // this will pop up one directory and try again
+            if !table.contains_key("workspace") {
+                if doc_manifest.is_none() {
+                    doc_manifest = Some((dir.to_owned(), toml_text));
                 }
-                Err(e) => return Err(e.into()),
+
+                continue;
+            }
+
+            let opened = Self::open_workspace_manifest(dir, &toml_path, &toml_text, &initial_dir);
+
+            let ws = match opened {
+                Ok(ws) => ws,
+                Err(_) if doc_manifest.is_some() => break,
+                Err(e) => return Err(e),
             };
 
-            root_dir.pop();
-            let mut doc_build_dir = root_dir.clone();
-            doc_build_dir.push("build");
-            let doc = Document::new_from_toml(root_dir.clone(), doc_build_dir, &mut doc_file)?;
+            // A document that isn't one of this workspace's members stands on
+            // its own.
+            if doc_manifest.is_none() || ws.current.is_some() {
+                return Ok(ws);
+            }
 
-            return Ok(Workspace { root_dir, doc });
+            break;
+        }
+
+        if let Some((root_dir, toml_text)) = doc_manifest {
+            let build_dir = root_dir.join("build");
+            let doc = Document::new_from_toml_in_workspace(
+                root_dir.clone(),
+                build_dir,
+                &toml_text,
+                &syntax::TomlWorkspaceSection::default(),
+            )?;
+
+            return Ok(Workspace {
+                root_dir,
+                docs: vec![doc],
+                current: Some(0),
+            });
         }
 
         Err(NoWorkspaceFoundError { initial_dir }.into())
     }
+
+    /// Read and parse a `Tectonic.toml` file, returning its text and contents,
+    /// or `None` if it doesn't exist.
+    fn read_manifest(toml_path: &Path) -> Result<Option<(String, toml::Table)>> {
+        let toml_text = match fs::read_to_string(toml_path) {
+            Ok(t) => t,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let table = atry!(
+            toml::from_str(&toml_text);
+            ["couldn't parse `{}`", toml_path.display()]
+        );
+        Ok(Some((toml_text, table)))
+    }
+
+    /// Open a multi-document workspace given the text of its manifest.
+    fn open_workspace_manifest(
+        root_dir: &Path,
+        toml_path: &Path,
+        toml_text: &str,
+        initial_dir: &Path,
+    ) -> Result<Self> {
+        let manifest: syntax::TomlWorkspaceManifest = atry!(
+            toml::from_str(toml_text);
+            ["couldn't parse workspace manifest `{}`", toml_path.display()]
+        );
+        Self::open_multi(root_dir, manifest.workspace, initial_dir)
+    }
+
+    /// Load the members of a multi-document workspace.
+    fn open_multi(
+        root_dir: &Path,
+        mut settings: syntax::TomlWorkspaceSection,
+        initial_dir: &Path,
+    ) -> Result<Self> {
+        if settings.members.is_empty() {
+            bail!(
+                "the workspace manifest in `{}` must list at least one member",
+                root_dir.display()
+            );
+        }
+
+        // Relative paths in the inherited bundle locations are relative to the
+        // workspace root, not to the members.
+        settings.bundle = settings.bundle.map(|b| match b {
+            syntax::TomlBundleLocs::One(loc) => {
+                syntax::TomlBundleLocs::One(resolve_bundle_loc(root_dir, loc))
+            }
+            syntax::TomlBundleLocs::Many(locs) => syntax::TomlBundleLocs::Many(
+                locs.into_iter()
+                    .map(|loc| resolve_bundle_loc(root_dir, loc))
+                    .collect(),
+            ),
+        });
+
+        let mut docs: Vec<Document> = Vec::with_capacity(settings.members.len());
+        let mut current = None;
+
+        for member in &settings.members {
+            let src_dir = root_dir.join(member);
+            let toml_path = src_dir.join("Tectonic.toml");
+            let toml_text = atry!(
+                fs::read_to_string(&toml_path);
+                ["couldn't read the manifest of workspace member `{}`", member]
+            );
+
+            let build_dir = src_dir.join("build");
+            let doc = atry!(
+                Document::new_from_toml_in_workspace(src_dir.clone(), build_dir, &toml_text, &settings);
+                ["couldn't load workspace member `{}`", member]
+            );
+
+            if docs.iter().any(|d| d.name == doc.name) {
+                bail!("duplicated document name `{}` in workspace", doc.name);
+            }
+
+            if initial_dir.starts_with(&src_dir) {
+                current = Some(docs.len());
+            }
+
+            docs.push(doc);
+        }
+
+        Ok(Workspace {
+            root_dir: root_dir.to_owned(),
+            docs,
+            current,
+        })
+    }
+}
+
+/// Make a bundle location from a workspace manifest usable from the
+/// workspace's members. URLs are left alone; relative paths are made relative
+/// to the workspace root.
+fn resolve_bundle_loc(root_dir: &Path, loc: String) -> String {
+    if loc.contains("://") || Path::new(&loc).is_absolute() {
+        loc
+    } else {
+        root_dir.join(loc).to_string_lossy().into_owned()
+    }
 }
 
 /// An error for when the environment does not seem to contain a Tectonic
@@ -176,7 +350,106 @@ impl WorkspaceCreator {
 
         Ok(Workspace {
             root_dir: self.root_dir,
-            doc,
+            docs: vec![doc],
+            current: Some(0),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, rel: &str, text: &str) {
+        let path = dir.join(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    fn doc_toml(name: &str, extra: &str) -> String {
+        format!("[doc]\nname = '{name}'\n{extra}\n[[output]]\nname = 'o'\ntype = 'pdf'\n")
+    }
+
+    #[test]
+    fn multi_document() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        write(
+            root,
+            "Tectonic.toml",
+            "[workspace]\nmembers = ['thesis', 'papers/one']\nbundle = ['style', 'https://example.com/b.tar']\n",
+        );
+        write(root, "thesis/Tectonic.toml", &doc_toml("thesis", ""));
+        write(
+            root,
+            "papers/one/Tectonic.toml",
+            &doc_toml("one", "bundle = 'mine'\nreuse_intermediates = true"),
+        );
+        write(
+            root,
+            "other/Tectonic.toml",
+            &doc_toml("other", "bundle = 'x'"),
+        );
+
+        let ws = Workspace::open_from_directory(root).unwrap();
+        assert_eq!(ws.documents().len(), 2);
+        assert!(ws.current_document().is_none());
+        assert_eq!(ws.first_document().name, "thesis");
+
+        let thesis = ws.document("thesis").unwrap();
+        assert_eq!(thesis.bundle_loc, "https://example.com/b.tar");
+        assert_eq!(
+            thesis.overlay_bundle_locs,
+            [root.join("style").to_string_lossy()]
+        );
+        assert!(!thesis.reuse_intermediates);
+
+        let one = ws.document("one").unwrap();
+        assert_eq!(one.bundle_loc, "mine");
+        assert!(one.overlay_bundle_locs.is_empty());
+        assert!(one.reuse_intermediates);
+
+        let ws = Workspace::open_from_directory(root.join("papers/one/src")).unwrap();
+        assert_eq!(ws.current_document().unwrap().name, "one");
+        assert_eq!(ws.root_dir(), root);
+
+        // Documents that aren't members stand alone.
+        let ws = Workspace::open_from_directory(root.join("other")).unwrap();
+        assert_eq!(ws.documents().len(), 1);
+        assert_eq!(ws.root_dir(), root.join("other"));
+    }
+
+    #[test]
+    fn missing_bundle() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        write(root, "Tectonic.toml", "[workspace]\nmembers = ['a']\n");
+        write(root, "a/Tectonic.toml", &doc_toml("a", ""));
+        assert!(Workspace::open_from_directory(root).is_err());
+
+        write(root, "Tectonic.toml", &doc_toml("a", ""));
+        assert!(Workspace::open_from_directory(root).is_err());
+    }
+
+    #[test]
+    fn broken_ancestors() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+
+        write(root, "doc/Tectonic.toml", &doc_toml("doc", "bundle = 'b'"));
+
+        // An unrelated manifest further up that can't be parsed, or a broken
+        // workspace, doesn't affect the document.
+        for text in ["this isn't TOML", "[workspace]\nmembers = []\n"] {
+            write(root, "Tectonic.toml", text);
+            let ws = Workspace::open_from_directory(root.join("doc")).unwrap();
+            assert_eq!(ws.current_document().unwrap().name, "doc");
+            assert_eq!(ws.root_dir(), root.join("doc"));
+
+            // But when there's no document, the problem is reported.
+            assert!(Workspace::open_from_directory(root).is_err());
+        }
+    }
+}
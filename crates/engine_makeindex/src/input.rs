@@ -0,0 +1,524 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Parsing of raw index entries, as written by TeX into `.idx` files.
+
+use std::cmp::Ordering;
+
+use crate::style::Style;
+
+/// The maximum number of levels in an index key, as in makeindex.
+pub(crate) const MAX_LEVELS: usize = 3;
+
+/// One level of an index key: the text used for sorting and the text that is
+/// actually typeset.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Level {
+    pub sort: String,
+    pub display: String,
+}
+
+/// Whether an entry opens or closes an explicit page range.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub(crate) enum RangeMark {
+    Open,
+    None,
+    Close,
+}
+
+/// The different kinds of page number components.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum PageKind {
+    RomanLower,
+    Arabic,
+    AlphaLower,
+    RomanUpper,
+    AlphaUpper,
+}
+
+impl PageKind {
+    fn code(self) -> char {
+        match self {
+            PageKind::RomanLower => 'r',
+            PageKind::Arabic => 'n',
+            PageKind::AlphaLower => 'a',
+            PageKind::RomanUpper => 'R',
+            PageKind::AlphaUpper => 'A',
+        }
+    }
+}
+
+/// A parsed page "number", which may be composite (e.g. `B-12`).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Page {
+    pub text: String,
+    pub components: Vec<(PageKind, u32)>,
+}
+
+impl Page {
+    /// Parse the textual representation of a page number.
+    pub fn parse(text: &str, style: &Style) -> Option<Page> {
+        let mut components = Vec::new();
+
+        let pieces: Vec<&str> = if style.page_compositor.is_empty() {
+            vec![text]
+        } else {
+            text.split(style.page_compositor.as_str()).collect()
+        };
+
+        for piece in pieces {
+            components.push(parse_page_component(piece)?);
+        }
+
+        Some(Page {
+            text: text.to_owned(),
+            components,
+        })
+    }
+
+    /// Compare two pages, using the page-type precedence given in the style.
+    pub fn compare(&self, other: &Page, style: &Style) -> Ordering {
+        let rank = |k: PageKind| {
+            style
+                .page_precedence
+                .find(k.code())
+                .unwrap_or(style.page_precedence.len())
+        };
+
+        for (a, b) in self.components.iter().zip(other.components.iter()) {
+            let o = rank(a.0).cmp(&rank(b.0)).then(a.1.cmp(&b.1));
+
+            if o != Ordering::Equal {
+                return o;
+            }
+        }
+
+        self.components.len().cmp(&other.components.len())
+    }
+
+    /// Determine whether `other` is the page immediately following this one.
+    pub fn is_followed_by(&self, other: &Page) -> bool {
+        let n = self.components.len();
+
+        if n == 0 || n != other.components.len() {
+            return false;
+        }
+
+        if self.components[..n - 1] != other.components[..n - 1] {
+            return false;
+        }
+
+        let (k1, v1) = self.components[n - 1];
+        let (k2, v2) = other.components[n - 1];
+        k1 == k2 && v1.checked_add(1) == Some(v2)
+    }
+}
+
+fn parse_page_component(text: &str) -> Option<(PageKind, u32)> {
+    if text.is_empty() {
+        return None;
+    }
+
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text.parse().ok().map(|n| (PageKind::Arabic, n));
+    }
+
+    if let Some(n) = parse_roman(text, false) {
+        return Some((PageKind::RomanLower, n));
+    }
+
+    if let Some(n) = parse_roman(text, true) {
+        return Some((PageKind::RomanUpper, n));
+    }
+
+    let mut chars = text.chars();
+
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_lowercase() {
+            return Some((PageKind::AlphaLower, c as u32 - 'a' as u32 + 1));
+        }
+
+        if c.is_ascii_uppercase() {
+            return Some((PageKind::AlphaUpper, c as u32 - 'A' as u32 + 1));
+        }
+    }
+
+    None
+}
+
+fn parse_roman(text: &str, upper: bool) -> Option<u32> {
+    let mut total = 0;
+    let mut prev = 0;
+
+    for c in text.chars().rev() {
+        let c = if upper {
+            if !c.is_ascii_uppercase() {
+                return None;
+            }
+            c.to_ascii_lowercase()
+        } else {
+            c
+        };
+
+        let v = match c {
+            'i' => 1,
+            'v' => 5,
+            'x' => 10,
+            'l' => 50,
+            'c' => 100,
+            'd' => 500,
+            'm' => 1000,
+            _ => return None,
+        };
+
+        if v < prev {
+            total -= v;
+        } else {
+            total += v;
+            prev = v;
+        }
+    }
+
+    if total > 0 {
+        Some(total as u32)
+    } else {
+        None
+    }
+}
+
+/// A single raw index entry.
+#[derive(Clone, Debug)]
+pub(crate) struct Entry {
+    pub levels: Vec<Level>,
+    pub encap: Option<String>,
+    pub range: RangeMark,
+    pub page: Page,
+    pub line: usize,
+}
+
+/// The results of parsing an `.idx` file.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedInput {
+    pub entries: Vec<Entry>,
+    pub n_rejected: usize,
+    pub messages: Vec<String>,
+}
+
+/// Parse the text of an `.idx` file.
+pub(crate) fn parse_idx(text: &str, style: &Style) -> ParsedInput {
+    let mut result = ParsedInput::default();
+    let mut scanner = Scanner {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+
+    loop {
+        scanner.skip_whitespace();
+
+        if scanner.at_end() {
+            break;
+        }
+
+        let line = scanner.line;
+
+        if !scanner.eat_str(&style.keyword) {
+            result
+                .messages
+                .push(format!("line {line}: unknown index keyword; skipping line"));
+            result.n_rejected += 1;
+            scanner.skip_line();
+            continue;
+        }
+
+        match parse_entry(&mut scanner, style, line) {
+            Ok(e) => result.entries.push(e),
+            Err(msg) => {
+                result.messages.push(format!("line {line}: {msg}"));
+                result.n_rejected += 1;
+                scanner.skip_line();
+            }
+        }
+    }
+
+    result
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Scanner {
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied();
+
+        if let Some(c) = c {
+            self.pos += 1;
+
+            if c == '\n' {
+                self.line += 1;
+            }
+        }
+
+        c
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.next();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.next() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let n = s.chars().count();
+
+        if self.pos + n > self.chars.len()
+            || !s
+                .chars()
+                .eq(self.chars[self.pos..self.pos + n].iter().copied())
+        {
+            return false;
+        }
+
+        self.pos += n;
+        true
+    }
+}
+
+/// What part of the key we are currently accumulating.
+enum KeyTarget {
+    Sort,
+    Actual,
+    Encap,
+}
+
+fn parse_entry(scanner: &mut Scanner, style: &Style, line: usize) -> Result<Entry, String> {
+    scanner.skip_whitespace();
+
+    if scanner.next() != Some(style.arg_open) {
+        return Err(format!("expected `{}` after index keyword", style.arg_open));
+    }
+
+    let mut levels = Vec::new();
+    let mut sort = String::new();
+    let mut actual = String::new();
+    let mut encap = String::new();
+    let mut target = KeyTarget::Sort;
+    let mut has_actual = false;
+    let mut depth = 0;
+
+    fn finish_level(
+        levels: &mut Vec<Level>,
+        sort: &mut String,
+        actual: &mut String,
+        has_actual: bool,
+    ) -> Result<(), String> {
+        if sort.is_empty() {
+            return Err("empty index key".to_owned());
+        }
+
+        let sort = std::mem::take(sort);
+        let display = if has_actual {
+            std::mem::take(actual)
+        } else {
+            sort.clone()
+        };
+
+        if display.is_empty() {
+            return Err("empty actual key".to_owned());
+        }
+
+        levels.push(Level { sort, display });
+        Ok(())
+    }
+
+    loop {
+        let c = scanner
+            .next()
+            .ok_or_else(|| "unexpected end of input in index key".to_owned())?;
+
+        let mut literal = None;
+
+        if c == style.escape {
+            // The escape character is kept, and protects the following
+            // character from interpretation.
+            let n = scanner
+                .next()
+                .ok_or_else(|| "unexpected end of input in index key".to_owned())?;
+            literal = Some(vec![c, n]);
+        } else if c == style.quote {
+            // The quote character is dropped, and the following character
+            // is taken literally.
+            let n = scanner
+                .next()
+                .ok_or_else(|| "unexpected end of input in index key".to_owned())?;
+            literal = Some(vec![n]);
+        } else if c == style.arg_open {
+            depth += 1;
+        } else if c == style.arg_close {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if matches!(target, KeyTarget::Encap) {
+            // Everything after the encapsulator is taken as-is.
+        } else if c == style.level {
+            if levels.len() + 1 >= MAX_LEVELS {
+                return Err(format!("too many levels (maximum is {MAX_LEVELS})"));
+            }
+
+            finish_level(&mut levels, &mut sort, &mut actual, has_actual)?;
+            target = KeyTarget::Sort;
+            has_actual = false;
+            continue;
+        } else if c == style.actual {
+            if has_actual {
+                return Err(format!("extra `{}` in index key", style.actual));
+            }
+
+            target = KeyTarget::Actual;
+            has_actual = true;
+            continue;
+        } else if c == style.encap {
+            target = KeyTarget::Encap;
+            continue;
+        }
+
+        let dest = match target {
+            KeyTarget::Sort => &mut sort,
+            KeyTarget::Actual => &mut actual,
+            KeyTarget::Encap => &mut encap,
+        };
+
+        match literal {
+            Some(cs) => dest.extend(cs),
+            None => dest.push(c),
+        }
+    }
+
+    finish_level(&mut levels, &mut sort, &mut actual, has_actual)?;
+
+    let (range, encap) = if encap.starts_with(style.range_open) {
+        (
+            RangeMark::Open,
+            encap[style.range_open.len_utf8()..].to_owned(),
+        )
+    } else if encap.starts_with(style.range_close) {
+        (
+            RangeMark::Close,
+            encap[style.range_close.len_utf8()..].to_owned(),
+        )
+    } else {
+        (RangeMark::None, encap)
+    };
+
+    let encap = if encap.is_empty() { None } else { Some(encap) };
+
+    scanner.skip_whitespace();
+
+    if scanner.next() != Some(style.arg_open) {
+        return Err(format!("expected `{}` before page number", style.arg_open));
+    }
+
+    let mut page_text = String::new();
+    let mut depth = 0;
+
+    loop {
+        let c = scanner
+            .next()
+            .ok_or_else(|| "unexpected end of input in page number".to_owned())?;
+
+        if c == style.arg_open {
+            depth += 1;
+        } else if c == style.arg_close {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        }
+
+        page_text.push(c);
+    }
+
+    let page = Page::parse(page_text.trim(), style)
+        .ok_or_else(|| format!("illegal page number `{page_text}`"))?;
+
+    Ok(Entry {
+        levels,
+        encap,
+        range,
+        page,
+        line,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entries() {
+        let style = Style::default();
+        let parsed = parse_idx(
+            "\\indexentry{alpha@\\textit{alpha}!beta|textbf}{12}\n\
+             \\indexentry{a\"!b|(}{iv}\n\
+             \\indexentry{x}{A-3}\n\
+             garbage\n\
+             \\indexentry{y}{?}\n",
+            &style,
+        );
+
+        assert_eq!(parsed.entries.len(), 3);
+        assert_eq!(parsed.n_rejected, 2);
+
+        let e = &parsed.entries[0];
+        assert_eq!(e.levels.len(), 2);
+        assert_eq!(e.levels[0].sort, "alpha");
+        assert_eq!(e.levels[0].display, "\\textit{alpha}");
+        assert_eq!(e.levels[1].sort, "beta");
+        assert_eq!(e.encap.as_deref(), Some("textbf"));
+        assert_eq!(e.page.components, vec![(PageKind::Arabic, 12)]);
+
+        let e = &parsed.entries[1];
+        assert_eq!(e.levels[0].sort, "a!b");
+        assert_eq!(e.range, RangeMark::Open);
+        assert_eq!(e.encap, None);
+        assert_eq!(e.page.components, vec![(PageKind::RomanLower, 4)]);
+
+        let e = &parsed.entries[2];
+        assert_eq!(
+            e.page.components,
+            vec![(PageKind::AlphaUpper, 1), (PageKind::Arabic, 3)]
+        );
+    }
+
+    #[test]
+    fn page_succession() {
+        let style = Style::default();
+        let page = |t| Page::parse(t, &style).unwrap();
+
+        assert!(page("12").is_followed_by(&page("13")));
+        assert!(!page("12").is_followed_by(&page("14")));
+        assert!(page("A-3").is_followed_by(&page("A-4")));
+        assert!(!page("A-3").is_followed_by(&page("B-4")));
+        assert!(!page("4294967295").is_followed_by(&page("0")));
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! A [makeindex]-compatible index processor.
+//!
+//! [makeindex]: https://ctan.org/pkg/makeindex
+//!
+//! This crate reads the raw index entries that TeX writes out when a document
+//! uses `\makeindex` (typically into an `.idx` file), sorts and merges them,
+//! and writes out the typeset index (typically into an `.ind` file) that is
+//! read back in by a subsequent TeX pass. The output format can be customized
+//! with makeindex `.ist` style files, which are looked up through the I/O
+//! stack like any other support file.
+//!
+//! Rather than using this crate directly you should probably use the main
+//! [`tectonic`] crate, which runs this engine automatically when needed.
+//!
+//! [`tectonic`]: https://docs.rs/tectonic/
+
+use std::io::{Read, Write};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_status_base::StatusBackend;
+
+mod input;
+mod output;
+mod style;
+
+use self::{output::OutputOptions, style::Style};
+
+/// A possible outcome from an index engine invocation.
+///
+/// As with the other engines, unrecoverable problems are represented as an
+/// `Err` result rather than a [`MakeindexOutcome`].
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum MakeindexOutcome {
+    /// Nothing bad happened.
+    Spotless = 0,
+
+    /// Warnings were issued, for instance about problems in the style file or
+    /// mismatched page ranges.
+    Warnings = 1,
+
+    /// Some index entries were rejected because they were malformed. The
+    /// index was still generated from the remaining entries.
+    Errors = 2,
+}
+
+/// A struct for invoking the index engine.
+///
+/// This struct has a fairly straightforward "builder" interface: you create it,
+/// apply any settings that you wish, and eventually run the
+/// [`process()`](Self::process) method.
+#[derive(Debug)]
+pub struct MakeindexEngine {
+    style_name: Option<String>,
+    letter_ordering: bool,
+    implicit_ranges: bool,
+}
+
+impl Default for MakeindexEngine {
+    fn default() -> Self {
+        MakeindexEngine {
+            style_name: None,
+            letter_ordering: false,
+            implicit_ranges: true,
+        }
+    }
+}
+
+impl MakeindexEngine {
+    /// Specify an `.ist` style file to apply.
+    ///
+    /// This is equivalent to the `-s` option of makeindex. The file is opened
+    /// through the driver's I/O stack, so it may come from the bundle. If no
+    /// style is specified, the makeindex defaults are used.
+    pub fn style<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.style_name = Some(name.to_string());
+        self
+    }
+
+    /// Specify whether to use "letter ordering" when sorting keys.
+    ///
+    /// This is equivalent to the `-l` option of makeindex. In letter ordering,
+    /// spaces in keys are ignored; in the default "word ordering", a space sorts
+    /// before any other character.
+    pub fn letter_ordering(&mut self, value: bool) -> &mut Self {
+        self.letter_ordering = value;
+        self
+    }
+
+    /// Specify whether runs of consecutive pages should be merged into ranges.
+    ///
+    /// This is enabled by default; disabling it is equivalent to the `-r`
+    /// option of makeindex. Explicit ranges marked with `|(` and `|)` are
+    /// always honored.
+    pub fn implicit_page_ranges(&mut self, value: bool) -> &mut Self {
+        self.implicit_ranges = value;
+        self
+    }
+
+    /// Process a file of raw index entries.
+    ///
+    /// The entries are read from the file named *input* and the index is
+    /// written to the file named *output*. A transcript describing any
+    /// problems is written to the file named *log*. All of these files are
+    /// accessed through the I/O stack of *hooks*, and the driver is notified
+    /// of the file accesses so that it can perform rerun detection.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        input: &str,
+        output: &str,
+        log: &str,
+    ) -> Result<MakeindexOutcome> {
+        let mut transcript = String::new();
+        let mut n_warnings = 0;
+        let mut style = Style::default();
+
+        transcript.push_str("This is the Tectonic makeindex-compatible index processor.\n");
+
+        if let Some(name) = self.style_name.as_ref() {
+            let text = atry!(
+                read_text(hooks, status, name);
+                ["cannot read index style file `{}`", name]
+            );
+            let report = style.apply_ist(&text);
+
+            transcript.push_str(&format!(
+                "Scanning style file {} ... done ({} attributes redefined, {} ignored).\n",
+                name, report.n_redefined, report.n_ignored
+            ));
+
+            for msg in &report.messages {
+                transcript.push_str(&format!("** Style error ({name}, {msg})\n"));
+            }
+
+            n_warnings += report.messages.len();
+        }
+
+        let text = read_text(hooks, status, input)?;
+        let parsed = input::parse_idx(&text, &style);
+
+        transcript.push_str(&format!(
+            "Scanning input file {} ... done ({} entries accepted, {} rejected).\n",
+            input,
+            parsed.entries.len(),
+            parsed.n_rejected
+        ));
+
+        for msg in &parsed.messages {
+            transcript.push_str(&format!("!! Input index error ({input}, {msg})\n"));
+        }
+
+        let n_rejected = parsed.n_rejected;
+        let opts = OutputOptions {
+            letter_ordering: self.letter_ordering,
+            implicit_ranges: self.implicit_ranges,
+        };
+        let index = output::generate(parsed.entries, &style, &opts);
+
+        for msg in &index.messages {
+            transcript.push_str(&format!("## Warning ({input}, {msg})\n"));
+        }
+
+        n_warnings += index.messages.len();
+
+        transcript.push_str(&format!(
+            "Generating output file {} ... done ({} lines written, {} warnings).\n",
+            output, index.n_lines, n_warnings
+        ));
+        transcript.push_str(&format!("Output written in {output}.\n"));
+        transcript.push_str(&format!("Transcript written in {log}.\n"));
+
+        write_text(hooks, status, output, &index.text)?;
+        write_text(hooks, status, log, &transcript)?;
+
+        Ok(if n_rejected > 0 {
+            MakeindexOutcome::Errors
+        } else if n_warnings > 0 {
+            MakeindexOutcome::Warnings
+        } else {
+            MakeindexOutcome::Spotless
+        })
+    }
+}
+
+fn read_text(
+    hooks: &mut dyn DriverHooks,
+    status: &mut dyn StatusBackend,
+    name: &str,
+) -> Result<String> {
+    let mut ih = hooks.io().input_open_name(name, status).must_exist()?;
+    let mut data = Vec::new();
+    atry!(
+        ih.read_to_end(&mut data);
+        ["failed to read `{}`", name]
+    );
+
+    let (name, digest_opt) = ih.into_name_digest();
+    hooks.event_input_closed(name, digest_opt, status);
+
+    // Like makeindex, don't be picky about encodings.
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+fn write_text(
+    hooks: &mut dyn DriverHooks,
+    status: &mut dyn StatusBackend,
+    name: &str,
+    text: &str,
+) -> Result<()> {
+    let mut oh = hooks.io().output_open_name(name).must_exist()?;
+    atry!(
+        oh.write_all(text.as_bytes());
+        ["failed to write `{}`", name]
+    );
+
+    let (name, digest) = oh.into_name_digest();
+    hooks.event_output_closed(name, digest, status);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(idx: &str, style: &Style) -> String {
+        let parsed = input::parse_idx(idx, style);
+        let opts = OutputOptions {
+            letter_ordering: false,
+            implicit_ranges: true,
+        };
+        output::generate(parsed.entries, style, &opts).text
+    }
+
+    #[test]
+    fn basic_index() {
+        let style = Style::default();
+        let ind = run(
+            "\\indexentry{beta}{2}\n\
+             \\indexentry{alpha}{3}\n\
+             \\indexentry{alpha}{1}\n\
+             \\indexentry{alpha!sub}{2}\n\
+             \\indexentry{gamma!sub|textbf}{4}\n\
+             \\indexentry{beta}{3}\n\
+             \\indexentry{beta}{4}\n\
+             \\indexentry{beta}{2}\n\
+             \\indexentry{delta|(}{5}\n\
+             \\indexentry{delta}{6}\n\
+             \\indexentry{delta|)}{9}\n",
+            &style,
+        );
+
+        assert_eq!(
+            ind,
+            "\\begin{theindex}\n\n  \\item alpha, 1, 3\n    \\subitem sub, 2\n\n  \\indexspace\n\n  \
+             \\item beta, 2--4\n\n  \\indexspace\n\n  \\item delta, 5--9\n\n  \\indexspace\n\n  \
+             \\item gamma\n    \\subitem sub, \\textbf{4}\n\n\\end{theindex}\n"
+        );
+    }
+
+    #[test]
+    fn headings_and_groups() {
+        let mut style = Style::default();
+        style.apply_ist("headings_flag 1\nheading_prefix \"{\"\nheading_suffix \"}\"\n");
+
+        let ind = run(
+            "\\indexentry{$x$}{1}\n\
+             \\indexentry{42}{1}\n\
+             \\indexentry{7}{1}\n\
+             \\indexentry{Apple}{1}\n\
+             \\indexentry{apple}{2}\n",
+            &style,
+        );
+
+        assert_eq!(
+            ind,
+            "\\begin{theindex}\n{Symbols}\n  \\item $x$, 1\n\n  \\indexspace\n{Numbers}\n  \
+             \\item 7, 1\n  \\item 42, 1\n\n  \\indexspace\n{A}\n  \\item Apple, 1\n  \
+             \\item apple, 2\n\n\\end{theindex}\n"
+        );
+    }
+
+    #[test]
+    fn line_wrapping() {
+        let style = Style::default();
+        let idx: String = (1..=40)
+            .step_by(2)
+            .map(|p| format!("\\indexentry{{word}}{{{p}}}\n"))
+            .collect();
+        let ind = run(&idx, &style);
+
+        assert!(ind.contains(", \n\t\t"));
+        assert!(ind.lines().all(|l| l.trim_end().len() <= style.line_max));
+    }
+}
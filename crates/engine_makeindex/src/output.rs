@@ -0,0 +1,476 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Sorting of index entries and generation of the formatted index.
+
+use std::cmp::Ordering;
+
+use crate::{
+    input::{Entry, Level, Page, RangeMark},
+    style::Style,
+};
+
+/// The broad classes into which index keys are grouped.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Group {
+    Symbols,
+    Numbers,
+    Letter(char),
+}
+
+impl Group {
+    fn of(key: &str) -> Group {
+        match key.chars().next() {
+            Some(c) if c.is_alphabetic() => Group::Letter(c.to_lowercase().next().unwrap_or(c)),
+            Some(_) if key.bytes().all(|b| b.is_ascii_digit()) => Group::Numbers,
+            _ => Group::Symbols,
+        }
+    }
+}
+
+/// Options affecting sorting and output that don't come from the style file.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OutputOptions {
+    /// Ignore spaces when sorting keys ("letter ordering"), rather than
+    /// treating them as coming before all other characters.
+    pub letter_ordering: bool,
+
+    /// Whether to automatically merge runs of consecutive pages into ranges.
+    pub implicit_ranges: bool,
+}
+
+/// One reference to a page from an index item.
+#[derive(Clone, Debug)]
+struct PageRef {
+    page: Page,
+    encap: Option<String>,
+    range: RangeMark,
+    line: usize,
+}
+
+/// A distinct item in the index, with all of its page references.
+#[derive(Debug)]
+struct Item {
+    levels: Vec<Level>,
+    refs: Vec<PageRef>,
+}
+
+/// A page reference as it will be typeset.
+#[derive(Clone, Debug)]
+enum PageItem {
+    Single(Page, Option<String>),
+    Range(Page, Page, Option<String>),
+    Suffixed(Page, String, Option<String>),
+}
+
+impl PageItem {
+    fn encap(&self) -> &Option<String> {
+        match self {
+            PageItem::Single(_, e) | PageItem::Range(_, _, e) | PageItem::Suffixed(_, _, e) => e,
+        }
+    }
+}
+
+/// The generated index.
+#[derive(Debug, Default)]
+pub(crate) struct GeneratedIndex {
+    pub text: String,
+    pub n_lines: usize,
+    pub messages: Vec<String>,
+}
+
+fn compare_level_keys(a: &Level, b: &Level, opts: &OutputOptions) -> Ordering {
+    let strip = |s: &str| -> String {
+        if opts.letter_ordering {
+            s.chars().filter(|c| *c != ' ').collect()
+        } else {
+            s.to_owned()
+        }
+    };
+
+    let (sa, sb) = (strip(&a.sort), strip(&b.sort));
+    let (ga, gb) = (Group::of(&sa), Group::of(&sb));
+
+    let o = match (ga, gb) {
+        (Group::Numbers, Group::Numbers) => {
+            let na = sa.trim_start_matches('0');
+            let nb = sb.trim_start_matches('0');
+            na.len().cmp(&nb.len()).then_with(|| na.cmp(nb))
+        }
+
+        (Group::Letter(_), Group::Letter(_)) | (Group::Symbols, Group::Symbols) => {
+            let fa = sa.chars().flat_map(char::to_lowercase);
+            let fb = sb.chars().flat_map(char::to_lowercase);
+            fa.cmp(fb)
+        }
+
+        _ => ga.cmp(&gb),
+    };
+
+    o.then_with(|| sa.cmp(&sb))
+        .then_with(|| a.display.cmp(&b.display))
+}
+
+fn compare_keys(a: &[Level], b: &[Level], opts: &OutputOptions) -> Ordering {
+    for (la, lb) in a.iter().zip(b.iter()) {
+        let o = compare_level_keys(la, lb, opts);
+
+        if o != Ordering::Equal {
+            return o;
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
+/// Sort the entries and merge them into distinct items.
+fn collect_items(mut entries: Vec<Entry>, style: &Style, opts: &OutputOptions) -> Vec<Item> {
+    // Stable sort, so that entries with identical keys stay in input order.
+    entries.sort_by(|a, b| compare_keys(&a.levels, &b.levels, opts));
+
+    let mut items: Vec<Item> = Vec::new();
+
+    for e in entries {
+        let r = PageRef {
+            page: e.page,
+            encap: e.encap,
+            range: e.range,
+            line: e.line,
+        };
+
+        match items.last_mut() {
+            Some(item) if item.levels == e.levels => item.refs.push(r),
+            _ => items.push(Item {
+                levels: e.levels,
+                refs: vec![r],
+            }),
+        }
+    }
+
+    for item in &mut items {
+        item.refs.sort_by(|a, b| {
+            a.page
+                .compare(&b.page, style)
+                .then(a.range.cmp(&b.range))
+                .then_with(|| a.encap.cmp(&b.encap))
+        });
+    }
+
+    items
+}
+
+/// Turn an item's sorted page references into the list of things to typeset.
+fn page_items(
+    refs: &[PageRef],
+    style: &Style,
+    opts: &OutputOptions,
+    messages: &mut Vec<String>,
+) -> Vec<PageItem> {
+    let mut items: Vec<PageItem> = Vec::new();
+    let mut open: Option<(usize, Option<String>)> = None;
+
+    // First, handle explicit ranges and duplicates.
+
+    for r in refs {
+        match r.range {
+            RangeMark::Open => {
+                if open.is_some() {
+                    messages.push(format!(
+                        "line {}: range opened while another is still open; ignoring",
+                        r.line
+                    ));
+                } else {
+                    open = Some((items.len(), r.encap.clone()));
+                    items.push(PageItem::Range(
+                        r.page.clone(),
+                        r.page.clone(),
+                        r.encap.clone(),
+                    ));
+                }
+            }
+
+            RangeMark::Close => match open.take() {
+                Some((idx, encap)) => {
+                    if encap != r.encap {
+                        messages.push(format!(
+                            "line {}: range closed with a different encapsulator than it was opened with",
+                            r.line
+                        ));
+                    }
+
+                    if let PageItem::Range(_, ref mut end, _) = items[idx] {
+                        *end = r.page.clone();
+                    }
+                }
+
+                None => {
+                    messages.push(format!(
+                        "line {}: range closed without being opened",
+                        r.line
+                    ));
+                    items.push(PageItem::Single(r.page.clone(), r.encap.clone()));
+                }
+            },
+
+            RangeMark::None => {
+                if let Some((_, ref encap)) = open {
+                    if *encap == r.encap {
+                        continue;
+                    }
+                }
+
+                let dup = items.iter().rev().any(|i| match i {
+                    PageItem::Single(p, e) => p.text == r.page.text && *e == r.encap,
+                    _ => false,
+                });
+
+                if !dup {
+                    items.push(PageItem::Single(r.page.clone(), r.encap.clone()));
+                }
+            }
+        }
+    }
+
+    if let Some((idx, _)) = open {
+        if let PageItem::Range(ref start, _, _) = items[idx] {
+            messages.push(format!(
+                "range opened at page {} was never closed",
+                start.text
+            ));
+        }
+    }
+
+    // Degenerate explicit ranges become single pages.
+
+    for item in &mut items {
+        if let PageItem::Range(start, end, encap) = item {
+            if start.text == end.text {
+                *item = PageItem::Single(start.clone(), encap.clone());
+            }
+        }
+    }
+
+    if !opts.implicit_ranges {
+        return items;
+    }
+
+    // Now merge runs of consecutive pages.
+
+    let mut merged = Vec::new();
+    let mut i = 0;
+
+    while i < items.len() {
+        let mut j = i + 1;
+
+        if let PageItem::Single(ref first, ref encap) = items[i] {
+            let mut last = first;
+
+            while j < items.len() {
+                match items[j] {
+                    PageItem::Single(ref p, ref e) if e == encap && last.is_followed_by(p) => {
+                        last = p;
+                        j += 1;
+                    }
+                    _ => break,
+                }
+            }
+
+            let n = j - i;
+
+            let run = if n == 2 && !style.suffix_2p.is_empty() {
+                Some(PageItem::Suffixed(
+                    first.clone(),
+                    style.suffix_2p.clone(),
+                    encap.clone(),
+                ))
+            } else if n == 3 && !style.suffix_3p.is_empty() {
+                Some(PageItem::Suffixed(
+                    first.clone(),
+                    style.suffix_3p.clone(),
+                    encap.clone(),
+                ))
+            } else if n >= 3 && !style.suffix_mp.is_empty() {
+                Some(PageItem::Suffixed(
+                    first.clone(),
+                    style.suffix_mp.clone(),
+                    encap.clone(),
+                ))
+            } else if n >= 3 {
+                Some(PageItem::Range(first.clone(), last.clone(), encap.clone()))
+            } else {
+                None
+            };
+
+            if let Some(run) = run {
+                merged.push(run);
+                i = j;
+                continue;
+            }
+
+            j = i + 1;
+        }
+
+        merged.push(items[i].clone());
+        i = j;
+    }
+
+    merged
+}
+
+/// A helper for writing the output while keeping track of line lengths.
+struct Writer {
+    text: String,
+    column: usize,
+    n_lines: usize,
+}
+
+impl Writer {
+    fn put(&mut self, s: &str) {
+        for c in s.chars() {
+            if c == '\n' {
+                self.column = 0;
+                self.n_lines += 1;
+            } else {
+                self.column += 1;
+            }
+        }
+
+        self.text.push_str(s);
+    }
+
+    /// Write a page item, wrapping the line first if it would get too long.
+    fn put_wrapped(&mut self, s: &str, style: &Style) {
+        if self.column + s.chars().count() > style.line_max {
+            self.put("\n");
+            self.text.push_str(&style.indent_space);
+            self.column = style.indent_length;
+        }
+
+        self.put(s);
+    }
+}
+
+fn render_page_item(item: &PageItem, style: &Style) -> String {
+    let text = match item {
+        PageItem::Single(p, _) => p.text.clone(),
+        PageItem::Range(start, end, _) => format!("{}{}{}", start.text, style.delim_r, end.text),
+        PageItem::Suffixed(p, suffix, _) => format!("{}{}", p.text, suffix),
+    };
+
+    match item.encap() {
+        Some(e) => format!(
+            "{}{}{}{}{}",
+            style.encap_prefix, e, style.encap_infix, text, style.encap_suffix
+        ),
+        None => text,
+    }
+}
+
+/// Sort the raw entries and generate the text of the index.
+pub(crate) fn generate(entries: Vec<Entry>, style: &Style, opts: &OutputOptions) -> GeneratedIndex {
+    let items = collect_items(entries, style, opts);
+    let mut messages = Vec::new();
+    let mut w = Writer {
+        text: String::new(),
+        column: 0,
+        n_lines: 0,
+    };
+
+    w.put(&style.preamble);
+
+    let mut prev: Option<&Item> = None;
+    let mut cur_group = None;
+
+    // The level of the last thing printed, and whether it had page numbers.
+    let mut last_printed: Option<(usize, bool)> = None;
+
+    for item in &items {
+        let group = Group::of(&item.levels[0].sort);
+
+        // Find the first level at which this item differs from the previous.
+
+        let mut first_level = match prev {
+            Some(p) => p
+                .levels
+                .iter()
+                .zip(item.levels.iter())
+                .take_while(|(a, b)| a == b)
+                .count(),
+            None => 0,
+        };
+
+        if cur_group != Some(group) {
+            if cur_group.is_some() {
+                w.put(&style.group_skip);
+            }
+
+            if style.headings_flag != 0 {
+                let label = match group {
+                    Group::Symbols if style.headings_flag > 0 => style.symhead_positive.clone(),
+                    Group::Symbols => style.symhead_negative.clone(),
+                    Group::Numbers if style.headings_flag > 0 => style.numhead_positive.clone(),
+                    Group::Numbers => style.numhead_negative.clone(),
+                    Group::Letter(c) if style.headings_flag > 0 => c.to_uppercase().collect(),
+                    Group::Letter(c) => c.to_string(),
+                };
+
+                w.put(&style.heading_prefix);
+                w.put(&label);
+                w.put(&style.heading_suffix);
+            }
+
+            cur_group = Some(group);
+            first_level = 0;
+        }
+
+        let last_level = item.levels.len() - 1;
+        let pages = page_items(&item.refs, style, opts, &mut messages);
+
+        for (level, key) in item.levels.iter().enumerate().skip(first_level) {
+            let sep = match (level, last_printed) {
+                (0, _) => &style.item_0,
+                (1, Some((0, true))) => &style.item_01,
+                (1, Some((0, false))) => &style.item_x1,
+                (1, _) => &style.item_1,
+                (_, Some((1, true))) => &style.item_12,
+                (_, Some((1, false))) => &style.item_x2,
+                (_, _) => &style.item_2,
+            };
+
+            w.put(sep);
+            w.put(&key.display);
+
+            let has_pages = level == last_level && !pages.is_empty();
+
+            if has_pages {
+                w.put(match level {
+                    0 => &style.delim_0,
+                    1 => &style.delim_1,
+                    _ => &style.delim_2,
+                });
+
+                for (i, p) in pages.iter().enumerate() {
+                    if i > 0 {
+                        w.put(&style.delim_n);
+                    }
+
+                    w.put_wrapped(&render_page_item(p, style), style);
+                }
+
+                w.put(&style.delim_t);
+            }
+
+            last_printed = Some((level, has_pages));
+        }
+
+        prev = Some(item);
+    }
+
+    w.put(&style.postamble);
+
+    GeneratedIndex {
+        text: w.text,
+        n_lines: w.n_lines,
+        messages,
+    }
+}
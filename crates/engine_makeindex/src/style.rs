@@ -0,0 +1,423 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Index style settings and parsing of makeindex `.ist` style files.
+
+/// The full set of makeindex style parameters.
+///
+/// The field names match the keywords used in `.ist` files, and the default
+/// values match those of the classic makeindex program.
+#[derive(Clone, Debug)]
+pub(crate) struct Style {
+    // Input parameters
+    pub keyword: String,
+    pub arg_open: char,
+    pub arg_close: char,
+    pub range_open: char,
+    pub range_close: char,
+    pub level: char,
+    pub actual: char,
+    pub encap: char,
+    pub quote: char,
+    pub escape: char,
+    pub page_compositor: String,
+
+    // Output parameters
+    pub preamble: String,
+    pub postamble: String,
+    pub setpage_prefix: String,
+    pub setpage_suffix: String,
+    pub group_skip: String,
+    pub headings_flag: i64,
+    pub heading_prefix: String,
+    pub heading_suffix: String,
+    pub symhead_positive: String,
+    pub symhead_negative: String,
+    pub numhead_positive: String,
+    pub numhead_negative: String,
+    pub item_0: String,
+    pub item_1: String,
+    pub item_2: String,
+    pub item_01: String,
+    pub item_x1: String,
+    pub item_12: String,
+    pub item_x2: String,
+    pub delim_0: String,
+    pub delim_1: String,
+    pub delim_2: String,
+    pub delim_n: String,
+    pub delim_r: String,
+    pub delim_t: String,
+    pub encap_prefix: String,
+    pub encap_infix: String,
+    pub encap_suffix: String,
+    pub line_max: usize,
+    pub indent_space: String,
+    pub indent_length: usize,
+    pub suffix_2p: String,
+    pub suffix_3p: String,
+    pub suffix_mp: String,
+    pub page_precedence: String,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            keyword: "\\indexentry".to_owned(),
+            arg_open: '{',
+            arg_close: '}',
+            range_open: '(',
+            range_close: ')',
+            level: '!',
+            actual: '@',
+            encap: '|',
+            quote: '"',
+            escape: '\\',
+            page_compositor: "-".to_owned(),
+
+            preamble: "\\begin{theindex}\n".to_owned(),
+            postamble: "\n\n\\end{theindex}\n".to_owned(),
+            setpage_prefix: "\n  \\setcounter{page}{".to_owned(),
+            setpage_suffix: "}\n".to_owned(),
+            group_skip: "\n\n  \\indexspace\n".to_owned(),
+            headings_flag: 0,
+            heading_prefix: String::new(),
+            heading_suffix: String::new(),
+            symhead_positive: "Symbols".to_owned(),
+            symhead_negative: "symbols".to_owned(),
+            numhead_positive: "Numbers".to_owned(),
+            numhead_negative: "numbers".to_owned(),
+            item_0: "\n  \\item ".to_owned(),
+            item_1: "\n    \\subitem ".to_owned(),
+            item_2: "\n      \\subsubitem ".to_owned(),
+            item_01: "\n    \\subitem ".to_owned(),
+            item_x1: "\n    \\subitem ".to_owned(),
+            item_12: "\n      \\subsubitem ".to_owned(),
+            item_x2: "\n      \\subsubitem ".to_owned(),
+            delim_0: ", ".to_owned(),
+            delim_1: ", ".to_owned(),
+            delim_2: ", ".to_owned(),
+            delim_n: ", ".to_owned(),
+            delim_r: "--".to_owned(),
+            delim_t: String::new(),
+            encap_prefix: "\\".to_owned(),
+            encap_infix: "{".to_owned(),
+            encap_suffix: "}".to_owned(),
+            line_max: 72,
+            indent_space: "\t\t".to_owned(),
+            indent_length: 16,
+            suffix_2p: String::new(),
+            suffix_3p: String::new(),
+            suffix_mp: String::new(),
+            page_precedence: "rnaRA".to_owned(),
+        }
+    }
+}
+
+/// A value appearing in a style file.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    String(String),
+    Char(char),
+    Number(i64),
+}
+
+impl Value {
+    fn into_string(self) -> Result<String, String> {
+        match self {
+            Value::String(s) => Ok(s),
+            _ => Err("expected a string value".to_owned()),
+        }
+    }
+
+    fn into_char(self) -> Result<char, String> {
+        match self {
+            Value::Char(c) => Ok(c),
+            _ => Err("expected a character value".to_owned()),
+        }
+    }
+
+    fn into_number(self) -> Result<i64, String> {
+        match self {
+            Value::Number(n) => Ok(n),
+            _ => Err("expected a numeric value".to_owned()),
+        }
+    }
+
+    fn into_usize(self) -> Result<usize, String> {
+        let n = self.into_number()?;
+
+        if n < 0 {
+            Err("expected a nonnegative value".to_owned())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+/// A token in a style file.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Value(Value),
+}
+
+/// The results of applying a style file.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StyleReport {
+    /// The number of attributes that were successfully set.
+    pub n_redefined: usize,
+
+    /// The number of attributes that were ignored because of problems.
+    pub n_ignored: usize,
+
+    /// Messages describing the problems, with line numbers.
+    pub messages: Vec<String>,
+}
+
+impl Style {
+    /// Apply the settings in the text of a `.ist` style file.
+    ///
+    /// Problems with the file are not fatal; as with makeindex, they are
+    /// logged and the offending specifications are ignored.
+    pub fn apply_ist(&mut self, text: &str) -> StyleReport {
+        let mut report = StyleReport::default();
+        let tokens = tokenize(text, &mut report.messages);
+        let mut iter = tokens.into_iter();
+
+        while let Some((tok, line)) = iter.next() {
+            let key = match tok {
+                Token::Ident(k) => k,
+                Token::Value(_) => {
+                    report
+                        .messages
+                        .push(format!("line {line}: expected a specifier name"));
+                    report.n_ignored += 1;
+                    continue;
+                }
+            };
+
+            let value = match iter.next() {
+                Some((Token::Value(v), _)) => v,
+                _ => {
+                    report
+                        .messages
+                        .push(format!("line {line}: no value given for `{key}`"));
+                    report.n_ignored += 1;
+                    break;
+                }
+            };
+
+            match self.set(&key, value) {
+                Ok(()) => report.n_redefined += 1,
+                Err(e) => {
+                    report
+                        .messages
+                        .push(format!("line {line}: cannot set `{key}`: {e}"));
+                    report.n_ignored += 1;
+                }
+            }
+        }
+
+        report
+    }
+
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        match key {
+            "keyword" => self.keyword = value.into_string()?,
+            "arg_open" => self.arg_open = value.into_char()?,
+            "arg_close" => self.arg_close = value.into_char()?,
+            "range_open" => self.range_open = value.into_char()?,
+            "range_close" => self.range_close = value.into_char()?,
+            "level" => self.level = value.into_char()?,
+            "actual" => self.actual = value.into_char()?,
+            "encap" => self.encap = value.into_char()?,
+            "quote" => self.quote = value.into_char()?,
+            "escape" => self.escape = value.into_char()?,
+            "page_compositor" => self.page_compositor = value.into_string()?,
+            "preamble" => self.preamble = value.into_string()?,
+            "postamble" => self.postamble = value.into_string()?,
+            "setpage_prefix" => self.setpage_prefix = value.into_string()?,
+            "setpage_suffix" => self.setpage_suffix = value.into_string()?,
+            "group_skip" => self.group_skip = value.into_string()?,
+            "headings_flag" | "lethead_flag" => self.headings_flag = value.into_number()?,
+            "heading_prefix" | "lethead_prefix" => self.heading_prefix = value.into_string()?,
+            "heading_suffix" | "lethead_suffix" => self.heading_suffix = value.into_string()?,
+            "symhead_positive" => self.symhead_positive = value.into_string()?,
+            "symhead_negative" => self.symhead_negative = value.into_string()?,
+            "numhead_positive" => self.numhead_positive = value.into_string()?,
+            "numhead_negative" => self.numhead_negative = value.into_string()?,
+            "item_0" => self.item_0 = value.into_string()?,
+            "item_1" => self.item_1 = value.into_string()?,
+            "item_2" => self.item_2 = value.into_string()?,
+            "item_01" => self.item_01 = value.into_string()?,
+            "item_x1" => self.item_x1 = value.into_string()?,
+            "item_12" => self.item_12 = value.into_string()?,
+            "item_x2" => self.item_x2 = value.into_string()?,
+            "delim_0" => self.delim_0 = value.into_string()?,
+            "delim_1" => self.delim_1 = value.into_string()?,
+            "delim_2" => self.delim_2 = value.into_string()?,
+            "delim_n" => self.delim_n = value.into_string()?,
+            "delim_r" => self.delim_r = value.into_string()?,
+            "delim_t" => self.delim_t = value.into_string()?,
+            "encap_prefix" => self.encap_prefix = value.into_string()?,
+            "encap_infix" => self.encap_infix = value.into_string()?,
+            "encap_suffix" => self.encap_suffix = value.into_string()?,
+            "line_max" => self.line_max = value.into_usize()?,
+            "indent_space" => self.indent_space = value.into_string()?,
+            "indent_length" => self.indent_length = value.into_usize()?,
+            "suffix_2p" => self.suffix_2p = value.into_string()?,
+            "suffix_3p" => self.suffix_3p = value.into_string()?,
+            "suffix_mp" => self.suffix_mp = value.into_string()?,
+            "page_precedence" => {
+                let p = value.into_string()?;
+
+                if let Some(c) = p.chars().find(|c| !"rnaRA".contains(*c)) {
+                    return Err(format!("unknown page type `{c}`"));
+                }
+
+                self.page_precedence = p;
+            }
+            _ => return Err("unknown specifier".to_owned()),
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the text of a style file into tokens, annotated with line numbers.
+fn tokenize(text: &str, messages: &mut Vec<String>) -> Vec<(Token, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    fn unescape(c: char) -> char {
+        match c {
+            'n' => '\n',
+            't' => '\t',
+            c => c,
+        }
+    }
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+
+            c if c.is_whitespace() => {}
+
+            '%' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        break;
+                    }
+                }
+            }
+
+            '"' => {
+                let start_line = line;
+                let mut s = String::new();
+                let mut terminated = false;
+
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => {
+                            terminated = true;
+                            break;
+                        }
+                        '\\' => {
+                            if let Some(c) = chars.next() {
+                                if c == '\n' {
+                                    line += 1;
+                                }
+                                s.push(unescape(c));
+                            }
+                        }
+                        '\n' => {
+                            line += 1;
+                            s.push(c);
+                        }
+                        c => s.push(c),
+                    }
+                }
+
+                if !terminated {
+                    messages.push(format!("line {start_line}: unterminated string"));
+                }
+
+                tokens.push((Token::Value(Value::String(s)), start_line));
+            }
+
+            '\'' => {
+                let c = match chars.next() {
+                    Some('\\') => chars.next().map(unescape),
+                    other => other,
+                };
+
+                if chars.next_if_eq(&'\'').is_none() {
+                    messages.push(format!("line {line}: malformed character constant"));
+                }
+
+                if let Some(c) = c {
+                    tokens.push((Token::Value(Value::Char(c)), line));
+                }
+            }
+
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut s = String::new();
+                s.push(c);
+
+                while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                    s.push(d);
+                }
+
+                match s.parse() {
+                    Ok(n) => tokens.push((Token::Value(Value::Number(n)), line)),
+                    Err(_) => messages.push(format!("line {line}: malformed number `{s}`")),
+                }
+            }
+
+            c if c.is_alphabetic() || c == '_' => {
+                let mut s = String::new();
+                s.push(c);
+
+                while let Some(d) = chars.next_if(|d| d.is_alphanumeric() || *d == '_') {
+                    s.push(d);
+                }
+
+                tokens.push((Token::Ident(s), line));
+            }
+
+            c => messages.push(format!("line {line}: unexpected character `{c}`")),
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ist() {
+        let mut style = Style::default();
+        let report = style.apply_ist(
+            "% a comment\n\
+             headings_flag 1\n\
+             heading_prefix \"{\\\\bfseries \"\n\
+             delim_0 \"\\t\" % another comment\n\
+             actual '='\n\
+             quote '\\''\n\
+             bogus \"x\"\n",
+        );
+
+        assert_eq!(report.n_redefined, 5);
+        assert_eq!(report.n_ignored, 1);
+        assert_eq!(style.headings_flag, 1);
+        assert_eq!(style.heading_prefix, "{\\bfseries ");
+        assert_eq!(style.delim_0, "\t");
+        assert_eq!(style.actual, '=');
+        assert_eq!(style.quote, '\'');
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
     finalization::FinalizingState,
     fonts::{FamilyRelativeFontId, FontEnsemble, FontFamilyAnalysis, PathToNewFont},
     html::Element,
+    spacing::SpaceTracker,
     specials::Special,
     templating::Templating,
     Common, FixedPoint, TexFontNum,
@@ -36,8 +37,7 @@ pub(crate) struct EmittingState {
 #[derive(Debug, Default)]
 struct ContentState {
     current_content: String,
-    last_content_x: i32,
-    last_content_space_width: Option<FixedPoint>,
+    spacing: SpaceTracker,
 }
 
 impl ContentState {
@@ -93,40 +93,11 @@ impl ContentState {
             return false;
         }
 
-        // TODO: RTL ASSUMPTION!!!!!
-        //
-        // If the "next" x is smaller than the last one, assume that we've
-        // started a new line. We ignore Y values since those are going to
-        // get hairy with subscripts, etc.
-
-        if x0 < self.last_content_x {
-            return true;
-        }
-
-        // Check the advance against the size of the space, which can be
-        // determined from either the most recent content or the new content,
-        // since in various circumstances either one or the other might not
-        // be defined. If both are defined, use whatever's smaller. There's
-        // probably a smoother way to do this logic?
-
-        let space_width = match (&self.last_content_space_width, &cur_space_width) {
-            (Some(w1), Some(w2)) => FixedPoint::min(*w1, *w2),
-            (Some(w), None) => *w,
-            (None, Some(w)) => *w,
-            (None, None) => 0,
-        };
-
-        // If the x difference is larger than 1/4 of the space_width, let's say that
-        // we need a space. I made up the 1/4.
-        4 * (x0 - self.last_content_x) > space_width
+        self.spacing.is_space_needed(x0, cur_space_width)
     }
 
     fn update_content_pos(&mut self, x: i32, cur_space_width: Option<FixedPoint>) {
-        self.last_content_x = x;
-
-        if cur_space_width.is_some() {
-            self.last_content_space_width = cur_space_width;
-        }
+        self.spacing.update(x, cur_space_width);
     }
 
     /// Maybe push a space into the text content right now, if we think we need one.
@@ -10,7 +10,7 @@
 //! have to do this, but for math and potentially other situations this is
 //! sometimes necessary.
 
-use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder};
 use percent_encoding::{utf8_percent_encode, CONTROLS};
 use pinot::{
     math::MathVariants,
@@ -21,7 +21,10 @@ use pinot::{
 use std::{collections::HashMap, num::Wrapping, path::Path};
 use tectonic_errors::prelude::*;
 
-use crate::FixedPoint;
+use crate::{
+    opentype::{append_simple_cmap, opentype_checksum},
+    FixedPoint,
+};
 
 /// A numerical identifier of a glyph in a font.
 pub type GlyphId = u16;
@@ -622,49 +625,6 @@ fn valid_usvs() -> impl Iterator<Item = Usv> {
     (0..0xD800).chain(0xE000..0x11_0000)
 }
 
-fn opentype_checksum(data: &[u8]) -> u32 {
-    let mut iter = data.chunks_exact(4);
-    let cs: Wrapping<u32> = iter
-        .by_ref()
-        .map(|c| Wrapping(BigEndian::read_u32(c)))
-        .sum();
-
-    let rem = iter.remainder();
-    let mut padded = [0u8; 4];
-    padded[..rem.len()].copy_from_slice(rem);
-    (cs + Wrapping(BigEndian::read_u32(&padded[..]))).0
-}
-
-/// Append a dumb OpenType CMAP table to a buffer.
-///
-/// The input *map* must be sorted by USV value.
-fn append_simple_cmap(buf: &mut Vec<u8>, map: &[(char, GlyphId)]) {
-    buf.write_u16::<BigEndian>(0).unwrap(); // version
-    buf.write_u16::<BigEndian>(1).unwrap(); // numTables
-
-    buf.write_u16::<BigEndian>(0).unwrap(); // EncodingRecord.platformId = Unicode
-    buf.write_u16::<BigEndian>(4).unwrap(); // EncodingRecord.encodingId = Unicode all planes
-    buf.write_u32::<BigEndian>(12).unwrap(); // EncodingRecord.subtableOffset
-
-    buf.write_u16::<BigEndian>(12).unwrap(); // Format 12 subtable identifier
-    buf.write_u16::<BigEndian>(0).unwrap(); // reserved
-
-    let subtable_len = 16 + 12 * map.len() as u32;
-    buf.write_u32::<BigEndian>(subtable_len).unwrap(); // subtable byte length
-    buf.write_u32::<BigEndian>(0).unwrap(); // subtable language
-    buf.write_u32::<BigEndian>(map.len() as u32).unwrap(); // subtable number of groups
-
-    // We could actually try to be smart here, but based on the expected usage
-    // of our glyph variant scheme, I think it is unlikely that we'd realize
-    // any significant efficiencies.
-
-    for (usv, gid) in map {
-        buf.write_u32::<BigEndian>(*usv as u32).unwrap(); // start char
-        buf.write_u32::<BigEndian>(*usv as u32).unwrap(); // end char
-        buf.write_u32::<BigEndian>(*gid as u32).unwrap(); // glyph id
-    }
-}
-
 impl From<crate::assets::syntax::GlyphVariantMapping> for GlyphVariantMapping {
     fn from(m: crate::assets::syntax::GlyphVariantMapping) -> Self {
         GlyphVariantMapping {
@@ -305,7 +305,7 @@ impl InitializationState {
             precomputed.check_runtime_fonts(&mut self.fonts, common)?;
         }
 
-        let mut context = tera::Context::default();
+        let mut context = common.template_context.clone();
 
         // Tera requires that we give it a filesystem path to look for
         // templates, even if we're going to be adding all of our templates
@@ -21,6 +21,8 @@ mod fontfile;
 mod fonts;
 mod html;
 mod initialization;
+pub mod opentype;
+pub mod spacing;
 mod specials;
 mod templating;
 
@@ -36,6 +38,7 @@ pub struct Spx2HtmlEngine {
     precomputed_assets: Option<AssetSpecification>,
     assets_spec_path: Option<String>,
     do_not_emit_assets: bool,
+    template_context: tera::Context,
 }
 
 #[derive(Debug, Default)]
@@ -113,6 +116,20 @@ impl Spx2HtmlEngine {
         self
     }
 
+    /// Make a variable available to the Tera templates used to create the
+    /// HTML output.
+    ///
+    /// Variables set by the document itself, using the
+    /// `tdux:setTemplateVariable` special, take precedence over ones set here.
+    pub fn template_variable<S: Into<String>>(
+        &mut self,
+        name: S,
+        value: TemplateValue,
+    ) -> &mut Self {
+        self.template_context.insert(name, &value);
+        self
+    }
+
     /// Specify the root path for output files.
     ///
     /// Because this driver will, in the generic case, produce a tree of HTML
@@ -145,7 +162,13 @@ impl Spx2HtmlEngine {
         };
 
         {
-            let state = EngineState::new(hooks, status, out_base, self.precomputed_assets.as_ref());
+            let state = EngineState::new(
+                hooks,
+                status,
+                out_base,
+                self.precomputed_assets.as_ref(),
+                &self.template_context,
+            );
             let state = XdvParser::process_with_seeks(&mut input, state)?;
             let (fonts, assets, mut common) = state.finished()?;
 
@@ -168,6 +191,9 @@ impl Spx2HtmlEngine {
 
 pub use assets::AssetSpecification;
 
+/// The type of values that can be given to [`Spx2HtmlEngine::template_variable`].
+pub use tera::Value as TemplateValue;
+
 struct EngineState<'a> {
     common: Common<'a>,
     state: State,
@@ -178,6 +204,7 @@ struct Common<'a> {
     status: &'a mut dyn StatusBackend,
     out_base: Option<&'a Path>,
     precomputed_assets: Option<&'a AssetSpecification>,
+    template_context: &'a tera::Context,
 }
 
 impl<'a> EngineState<'a> {
@@ -186,6 +213,7 @@ impl<'a> EngineState<'a> {
         status: &'a mut dyn StatusBackend,
         out_base: Option<&'a Path>,
         precomputed_assets: Option<&'a AssetSpecification>,
+        template_context: &'a tera::Context,
     ) -> Self {
         Self {
             common: Common {
@@ -193,6 +221,7 @@ impl<'a> EngineState<'a> {
                 status,
                 out_base,
                 precomputed_assets,
+                template_context,
             },
             state: State::Initializing(InitializationState::default()),
         }
@@ -0,0 +1,65 @@
+// Copyright 2018-2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Low-level helpers for writing OpenType font data.
+//!
+//! We create customized versions of the font files used by a document, which
+//! requires building new tables and fixing up the checksums that OpenType
+//! files carry. This logic is shared with other engines that write fonts.
+
+use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
+use std::num::Wrapping;
+
+/// Compute the OpenType checksum of some data: the sum of its big-endian
+/// 32-bit words, with the final word padded with zeros.
+pub fn opentype_checksum(data: &[u8]) -> u32 {
+    let mut iter = data.chunks_exact(4);
+    let cs: Wrapping<u32> = iter
+        .by_ref()
+        .map(|c| Wrapping(BigEndian::read_u32(c)))
+        .sum();
+
+    let rem = iter.remainder();
+    let mut padded = [0u8; 4];
+    padded[..rem.len()].copy_from_slice(rem);
+    (cs + Wrapping(BigEndian::read_u32(&padded[..]))).0
+}
+
+/// Compute the value of the `checksumAdjustment` field of the HEAD table of
+/// a complete font file, given the file data with that field's current value.
+///
+/// The field should be zero when this is called, since its value is part of
+/// the checksum.
+pub fn checksum_adjustment(font: &[u8]) -> u32 {
+    (Wrapping(0xB1B0AFBA) - Wrapping(opentype_checksum(font))).0
+}
+
+/// Append a dumb OpenType CMAP table to a buffer.
+///
+/// The input *map* must be sorted by USV value.
+pub fn append_simple_cmap(buf: &mut Vec<u8>, map: &[(char, u16)]) {
+    buf.write_u16::<BigEndian>(0).unwrap(); // version
+    buf.write_u16::<BigEndian>(1).unwrap(); // numTables
+
+    buf.write_u16::<BigEndian>(0).unwrap(); // EncodingRecord.platformId = Unicode
+    buf.write_u16::<BigEndian>(4).unwrap(); // EncodingRecord.encodingId = Unicode all planes
+    buf.write_u32::<BigEndian>(12).unwrap(); // EncodingRecord.subtableOffset
+
+    buf.write_u16::<BigEndian>(12).unwrap(); // Format 12 subtable identifier
+    buf.write_u16::<BigEndian>(0).unwrap(); // reserved
+
+    let subtable_len = 16 + 12 * map.len() as u32;
+    buf.write_u32::<BigEndian>(subtable_len).unwrap(); // subtable byte length
+    buf.write_u32::<BigEndian>(0).unwrap(); // subtable language
+    buf.write_u32::<BigEndian>(map.len() as u32).unwrap(); // subtable number of groups
+
+    // We could actually try to be smart here, but based on the expected usage
+    // of our glyph variant scheme, I think it is unlikely that we'd realize
+    // any significant efficiencies.
+
+    for (usv, gid) in map {
+        buf.write_u32::<BigEndian>(*usv as u32).unwrap(); // start char
+        buf.write_u32::<BigEndian>(*usv as u32).unwrap(); // end char
+        buf.write_u32::<BigEndian>(*gid as u32).unwrap(); // glyph id
+    }
+}
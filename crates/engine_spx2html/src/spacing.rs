@@ -0,0 +1,71 @@
+// Copyright 2018-2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Guessing where inter-word spaces belong.
+//!
+//! XDV files don't contain spaces as such: TeX just moves the current point
+//! to the right between words. To recover them, we keep track of where the
+//! most recently emitted piece of text ended and compare it to where the next
+//! piece begins. This logic is shared with other engines that reconstruct
+//! text from XDV files.
+
+/// Tracks the end of the most recently emitted text so that we can decide
+/// whether a space is needed before the next bit of text.
+///
+/// Positions and widths are measured in TeX units (1/65536 of a TeX point).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpaceTracker {
+    last_x: i32,
+    last_space_width: Option<i32>,
+}
+
+impl SpaceTracker {
+    /// Decide whether text beginning at `x0` should be preceded by a space.
+    ///
+    /// `cur_space_width` is the width of a space in the font of the new text,
+    /// if known. Callers should separately check that there's actually some
+    /// preceding text, since we never want a leading space.
+    pub fn is_space_needed(&self, x0: i32, cur_space_width: Option<i32>) -> bool {
+        // TODO: RTL ASSUMPTION!!!!!
+        //
+        // If the "next" x is smaller than the last one, assume that we've
+        // started a new line. We ignore Y values since those are going to
+        // get hairy with subscripts, etc.
+
+        if x0 < self.last_x {
+            return true;
+        }
+
+        // Check the advance against the size of the space, which can be
+        // determined from either the most recent content or the new content,
+        // since in various circumstances either one or the other might not
+        // be defined. If both are defined, use whatever's smaller. There's
+        // probably a smoother way to do this logic?
+
+        let space_width = match (&self.last_space_width, &cur_space_width) {
+            (Some(w1), Some(w2)) => i32::min(*w1, *w2),
+            (Some(w), None) => *w,
+            (None, Some(w)) => *w,
+            (None, None) => 0,
+        };
+
+        // If the x difference is larger than 1/4 of the space_width, let's say that
+        // we need a space. I made up the 1/4.
+        4 * (x0 - self.last_x) > space_width
+    }
+
+    /// Record that some text has been emitted, ending at `x`, in a font whose
+    /// space has the width `cur_space_width`, if known.
+    pub fn update(&mut self, x: i32, cur_space_width: Option<i32>) {
+        self.last_x = x;
+
+        if cur_space_width.is_some() {
+            self.last_space_width = cur_space_width;
+        }
+    }
+
+    /// Get the position at which the most recently emitted text ended.
+    pub fn last_x(&self) -> i32 {
+        self.last_x
+    }
+}
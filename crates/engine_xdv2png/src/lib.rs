@@ -0,0 +1,542 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Rasterize Tectonic’s XDV output into PNG images.
+//!
+//! Each page of the XDV file becomes one PNG image of the full page at a
+//! configurable resolution. Glyphs are rendered with [FreeType]: those of
+//! native (OpenType) fonts directly, and those of traditional TFM-based fonts
+//! using the Type 1 fonts found through `pdftex.map`. Rules are drawn as filled
+//! rectangles. This is intended for quick previews and thumbnails, not as a
+//! replacement for the PDF output: `\special`s other than the paper size, such
+//! as graphics inclusions and color changes, are ignored.
+//!
+//! [FreeType]: https://www.freetype.org/
+//!
+//! Rather than using this crate directly you should probably use the main
+//! [`tectonic`] crate, which runs this engine automatically when PNG output is
+//! requested.
+//!
+//! [`tectonic`]: https://docs.rs/tectonic/
+
+use std::collections::HashMap;
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_status_base::{tt_warning, StatusBackend};
+use tectonic_xdv::{FileType, XdvEvents, XdvParser};
+use tectonic_xdv_support::{
+    freetype::{Face, GlyphBitmap, Library},
+    tfm::{TfmFonts, TfmGlyph},
+    write_pages, FontFiles, FontInstance,
+};
+
+mod png;
+mod raster;
+
+use self::raster::{Canvas, Rgb};
+
+/// The default output resolution, in dots per inch.
+pub const DEFAULT_DPI: u32 = 100;
+
+/// The number of TeX points per inch.
+const TEX_POINTS_PER_INCH: f64 = 72.27;
+
+/// The largest number of pixels that we allow in a page image. Each pixel
+/// takes three bytes, so this caps the memory used for the canvas at 768 MiB.
+const MAX_PAGE_PIXELS: f64 = (1u64 << 28) as f64;
+
+/// A struct for invoking the XDV-to-PNG engine.
+///
+/// This struct has a fairly straightforward "builder" interface: you create it,
+/// apply any settings that you wish, and eventually run the
+/// [`process()`](Self::process) method.
+#[derive(Debug)]
+pub struct Xdv2PngEngine {
+    dpi: u32,
+}
+
+impl Default for Xdv2PngEngine {
+    fn default() -> Self {
+        Xdv2PngEngine { dpi: DEFAULT_DPI }
+    }
+}
+
+impl Xdv2PngEngine {
+    /// Set the resolution of the output images, in dots per inch.
+    ///
+    /// The default is [`DEFAULT_DPI`].
+    pub fn dpi(&mut self, dpi: u32) -> &mut Self {
+        self.dpi = dpi;
+        self
+    }
+
+    /// Convert an XDV file into PNG images.
+    ///
+    /// The file named `xdv` is read from the I/O stack. If the document has a
+    /// single page, it is written to `<output_stem>.png`; otherwise, page *N*
+    /// is written to `<output_stem>-<N>.png`, counting from 1.
+    ///
+    /// The page size is taken from a `papersize` special in the document, as
+    /// emitted by packages such as `geometry`, if there is one. Otherwise, US
+    /// Letter is assumed, matching the PDF backend.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        xdv: &str,
+        output_stem: &str,
+    ) -> Result<()> {
+        if self.dpi == 0 {
+            bail!("the PNG output resolution must be positive");
+        }
+
+        let mut input = hooks.io().input_open_name(xdv, status).must_exist()?;
+
+        let pages = {
+            let state = EngineState::new(hooks, status, self.dpi)?;
+            let state = XdvParser::process_with_seeks(&mut input, state)?;
+            state.finished()?
+        };
+
+        let (name, digest_opt) = input.into_name_digest();
+        hooks.event_input_closed(name, digest_opt, status);
+
+        write_pages(hooks, status, output_stem, "png", &pages)
+    }
+}
+
+/// Something to be drawn on the page.
+enum DrawOp {
+    Glyphs {
+        font_num: i32,
+        glyphs: Vec<u16>,
+        x: Vec<i32>,
+        y: Vec<i32>,
+    },
+
+    /// A character in a TFM font.
+    Char { glyph: TfmGlyph, x: i32, y: i32 },
+
+    Rule {
+        x: i32,
+        y: i32,
+        height: i32,
+        width: i32,
+    },
+}
+
+struct EngineState<'a> {
+    hooks: &'a mut dyn DriverHooks,
+    status: &'a mut dyn StatusBackend,
+    dpi: u32,
+
+    /// The paper size, in inches.
+    paper_size: (f64, f64),
+
+    // Note that `faces` must be declared before `library` so that the faces
+    // are dropped first.
+    faces: FontFiles<Face>,
+    library: Library,
+
+    fonts: HashMap<i32, FontInstance>,
+    glyph_cache: HashMap<(usize, i32, u16), GlyphBitmap>,
+    tfm: TfmFonts,
+    tfm_glyph_cache: HashMap<TfmGlyph, GlyphBitmap>,
+    cur_page: Option<Vec<DrawOp>>,
+    finished_pages: Vec<Vec<u8>>,
+    n_failed_glyphs: usize,
+}
+
+impl<'a> EngineState<'a> {
+    fn new(
+        hooks: &'a mut dyn DriverHooks,
+        status: &'a mut dyn StatusBackend,
+        dpi: u32,
+    ) -> Result<Self> {
+        Ok(EngineState {
+            hooks,
+            status,
+            dpi,
+            paper_size: (8.5, 11.),
+            faces: FontFiles::default(),
+            library: Library::new()?,
+            fonts: HashMap::new(),
+            glyph_cache: HashMap::new(),
+            tfm: TfmFonts::new()?,
+            tfm_glyph_cache: HashMap::new(),
+            cur_page: None,
+            finished_pages: Vec::new(),
+            n_failed_glyphs: 0,
+        })
+    }
+
+    fn finished(mut self) -> Result<Vec<Vec<u8>>> {
+        self.finish_page()?;
+
+        if self.n_failed_glyphs > 0 {
+            tt_warning!(
+                self.status,
+                "{} glyph(s) could not be rendered and were omitted from the PNG output",
+                self.n_failed_glyphs
+            );
+        }
+
+        self.tfm.warn_missing(self.status, "PNG");
+        Ok(self.finished_pages)
+    }
+
+    /// Convert a TeX horizontal or vertical position into a fractional pixel
+    /// position. As in the DVI standard, the TeX origin is one inch to the
+    /// right of and below the top left corner of the page.
+    fn tex_to_px(&self, v: i32) -> f64 {
+        (v as f64 / 65536. / TEX_POINTS_PER_INCH + 1.) * self.dpi as f64
+    }
+
+    /// Render the current page, if any, and set it aside.
+    fn finish_page(&mut self) -> Result<()> {
+        let ops = match self.cur_page.take() {
+            Some(ops) => ops,
+            None => return Ok(()),
+        };
+
+        let (width, height) = atry!(
+            page_dimensions(self.paper_size, self.dpi);
+            ["cannot render page {}", self.finished_pages.len() + 1]
+        );
+        let mut canvas = Canvas::new(width, height);
+
+        for op in ops {
+            match op {
+                DrawOp::Rule {
+                    x,
+                    y,
+                    height,
+                    width,
+                } => {
+                    canvas.fill_rect(
+                        self.tex_to_px(x),
+                        self.tex_to_px(y - height),
+                        self.tex_to_px(x + width),
+                        self.tex_to_px(y),
+                        [0, 0, 0],
+                        1.,
+                    );
+                }
+
+                DrawOp::Glyphs {
+                    font_num,
+                    glyphs,
+                    x,
+                    y,
+                } => self.draw_glyphs(&mut canvas, font_num, &glyphs, &x, &y)?,
+
+                DrawOp::Char { glyph, x, y } => self.draw_char(&mut canvas, glyph, x, y),
+            }
+        }
+
+        let mut buf = Vec::new();
+        png::encode_rgb(
+            &mut buf,
+            canvas.width() as u32,
+            canvas.height() as u32,
+            canvas.rgb(),
+        )?;
+        self.finished_pages.push(buf);
+        Ok(())
+    }
+
+    fn draw_glyphs(
+        &mut self,
+        canvas: &mut Canvas,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        let fi = a_ok_or!(
+            self.fonts.get(&font_num);
+            ["reference to undefined native font #{}", font_num]
+        );
+
+        let size_bp = size_bp(fi.size);
+
+        let rgba = fi.color_rgba.unwrap_or(0xFF);
+        let color: Rgb = [(rgba >> 24) as u8, (rgba >> 16) as u8, (rgba >> 8) as u8];
+        let opacity = (rgba & 0xFF) as f64 / 255.;
+
+        for (i, &glyph) in glyphs.iter().enumerate() {
+            let key = (fi.file_idx, fi.size, glyph);
+
+            if !self.glyph_cache.contains_key(&key) {
+                let face = self.faces.get_mut(fi.file_idx);
+                let bitmap = match face.render_glyph(glyph as u32, size_bp, self.dpi) {
+                    Ok(b) => b,
+                    Err(_) => {
+                        self.n_failed_glyphs += 1;
+                        GlyphBitmap::default()
+                    }
+                };
+
+                self.glyph_cache.insert(key, bitmap);
+            }
+
+            canvas.draw_glyph(
+                &self.glyph_cache[&key],
+                self.tex_to_px(x[i]).round() as i64,
+                self.tex_to_px(y[i]).round() as i64,
+                color,
+                opacity,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Draw a character of a TFM font, in black.
+    fn draw_char(&mut self, canvas: &mut Canvas, glyph: TfmGlyph, x: i32, y: i32) {
+        if !self.tfm_glyph_cache.contains_key(&glyph) {
+            let face = self.tfm.face_mut(glyph.face_idx);
+            let bitmap = match face.render_glyph(glyph.glyph, size_bp(glyph.size), self.dpi) {
+                Ok(b) => b,
+                Err(_) => {
+                    self.n_failed_glyphs += 1;
+                    GlyphBitmap::default()
+                }
+            };
+
+            self.tfm_glyph_cache.insert(glyph, bitmap);
+        }
+
+        canvas.draw_glyph(
+            &self.tfm_glyph_cache[&glyph],
+            self.tex_to_px(x).round() as i64,
+            self.tex_to_px(y).round() as i64,
+            [0, 0, 0],
+            1.,
+        );
+    }
+
+    fn push_op(&mut self, op: DrawOp) {
+        self.cur_page.get_or_insert_with(Vec::new).push(op);
+    }
+}
+
+impl<'a> XdvEvents for EngineState<'a> {
+    type Error = Error;
+
+    fn handle_header(&mut self, filetype: FileType, _comment: &[u8]) -> Result<()> {
+        if filetype != FileType::Xdv {
+            bail!("file should be XDV format; got {}", filetype);
+        }
+
+        Ok(())
+    }
+
+    fn handle_begin_page(&mut self, _counters: &[i32], _previous_bop: i32) -> Result<()> {
+        self.finish_page()?;
+        self.cur_page = Some(Vec::new());
+        Ok(())
+    }
+
+    fn handle_special(&mut self, _x: i32, _y: i32, contents: &[u8]) -> Result<()> {
+        if let Some(size) = std::str::from_utf8(contents)
+            .ok()
+            .and_then(|s| s.strip_prefix("papersize="))
+            .and_then(parse_paper_size)
+        {
+            self.paper_size = size;
+        }
+
+        Ok(())
+    }
+
+    fn handle_char(&mut self, font_num: i32, c: i32, x: i32, y: i32) -> Result<i32> {
+        if let Some(glyph) = self.tfm.glyph(font_num, c) {
+            self.push_op(DrawOp::Char { glyph, x, y });
+        }
+
+        Ok(self.tfm.char_width(font_num, c))
+    }
+
+    fn handle_glyph_run(
+        &mut self,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.push_op(DrawOp::Glyphs {
+            font_num,
+            glyphs: glyphs.to_owned(),
+            x: x.to_owned(),
+            y: y.to_owned(),
+        });
+        Ok(())
+    }
+
+    fn handle_text_and_glyphs(
+        &mut self,
+        font_num: i32,
+        _text: &str,
+        _width: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.handle_glyph_run(font_num, glyphs, x, y)
+    }
+
+    fn handle_define_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        _checksum: u32,
+        scale: i32,
+        _design_size: i32,
+    ) -> Result<()> {
+        self.tfm
+            .define(self.hooks, self.status, name, font_num, scale)
+    }
+
+    fn handle_define_native_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        size: i32,
+        face_index: u32,
+        color_rgba: Option<u32>,
+        _extend: Option<u32>,
+        _slant: Option<u32>,
+        _embolden: Option<u32>,
+    ) -> Result<()> {
+        if self.fonts.contains_key(&font_num) {
+            return Ok(());
+        }
+
+        let library = &self.library;
+        let file_idx = self.faces.load(
+            self.hooks,
+            self.status,
+            name,
+            face_index,
+            |buffer, face_index| Face::new(library, buffer, face_index),
+        )?;
+
+        self.fonts.insert(
+            font_num,
+            FontInstance {
+                file_idx,
+                size,
+                color_rgba,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn handle_rule(&mut self, x: i32, y: i32, height: i32, width: i32) -> Result<()> {
+        if height > 0 && width > 0 {
+            self.push_op(DrawOp::Rule {
+                x,
+                y,
+                height,
+                width,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert a font size in TeX units to big points, in which FreeType measures
+/// font sizes.
+fn size_bp(size: i32) -> f64 {
+    size as f64 / 65536. * 72. / TEX_POINTS_PER_INCH
+}
+
+/// Compute the size of a page image in pixels, given the paper size in inches
+/// and the resolution. Pages that would need unreasonably large images are
+/// rejected, since the whole image is held in memory.
+fn page_dimensions(paper_size: (f64, f64), dpi: u32) -> Result<(usize, usize)> {
+    let width = (paper_size.0 * dpi as f64).round().max(1.);
+    let height = (paper_size.1 * dpi as f64).round().max(1.);
+
+    if width * height > MAX_PAGE_PIXELS {
+        bail!(
+            "a {:.2}×{:.2} inch page at {} dpi would need a {}×{} pixel image, which is too large; \
+             use a lower resolution",
+            paper_size.0,
+            paper_size.1,
+            dpi,
+            width,
+            height
+        );
+    }
+
+    Ok((width as usize, height as usize))
+}
+
+/// Parse the value of a `papersize` special, such as `597.50787pt,845.04684pt`,
+/// into a width and height in inches.
+fn parse_paper_size(spec: &str) -> Option<(f64, f64)> {
+    let (w, h) = spec.split_once(',')?;
+    let (w, h) = (parse_length(w.trim())?, parse_length(h.trim())?);
+
+    if w > 0. && h > 0. {
+        Some((w, h))
+    } else {
+        None
+    }
+}
+
+/// Parse a TeX-style length into inches.
+fn parse_length(text: &str) -> Option<f64> {
+    const UNITS: &[(&str, f64)] = &[
+        ("truept", TEX_POINTS_PER_INCH),
+        ("pt", TEX_POINTS_PER_INCH),
+        ("bp", 72.),
+        ("in", 1.),
+        ("cm", 2.54),
+        ("mm", 25.4),
+        ("pc", TEX_POINTS_PER_INCH / 12.),
+    ];
+
+    for (unit, per_inch) in UNITS {
+        if let Some(num) = text.strip_suffix(unit) {
+            return num.trim().parse::<f64>().ok().map(|v| v / per_inch);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paper_sizes() {
+        let (w, h) = parse_paper_size("8.5in,11in").unwrap();
+        assert!((w - 8.5).abs() < 1e-9 && (h - 11.).abs() < 1e-9);
+
+        let (w, h) = parse_paper_size("210mm, 297mm").unwrap();
+        assert!((w - 8.2677).abs() < 1e-4 && (h - 11.6929).abs() < 1e-4);
+
+        let (w, _) = parse_paper_size("597.50787pt,845.04684pt").unwrap();
+        assert!((w - 8.2677).abs() < 1e-4);
+
+        assert_eq!(parse_paper_size("a4"), None);
+        assert_eq!(parse_paper_size("10furlongs,1in"), None);
+        assert_eq!(parse_paper_size("0pt,1in"), None);
+    }
+
+    #[test]
+    fn page_size_limit() {
+        assert_eq!(page_dimensions((8.5, 11.), 100).unwrap(), (850, 1100));
+        assert_eq!(page_dimensions((8.5, 11.), 1200).unwrap(), (10200, 13200));
+        assert!(page_dimensions((8.5, 11.), 2400).is_err());
+        assert!(page_dimensions((1e6, 1e6), 1).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! A minimal PNG encoder.
+//!
+//! We only ever need to write 8-bit RGB images, so this is simple enough that
+//! it's not worth pulling in a full-featured image library.
+
+use flate2::{write::ZlibEncoder, Compression, Crc};
+use std::io::{Result, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Encode an 8-bit RGB image as PNG.
+///
+/// The `rgb` buffer must contain `width * height` pixels of three bytes each,
+/// row by row from the top. Both dimensions must be nonzero.
+pub fn encode_rgb<W: Write>(mut dest: W, width: u32, height: u32, rgb: &[u8]) -> Result<()> {
+    assert!(width > 0 && height > 0);
+    assert_eq!(rgb.len(), width as usize * height as usize * 3);
+
+    dest.write_all(SIGNATURE)?;
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[
+        8, // bit depth
+        2, // color type: RGB
+        0, // compression method: deflate
+        0, // filter method: adaptive
+        0, // interlace method: none
+    ]);
+    write_chunk(&mut dest, b"IHDR", &ihdr)?;
+
+    // Each scanline is preceded by its filter type. We don't bother with
+    // filtering, which compresses mostly-blank pages just fine.
+    let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+    let row_len = width as usize * 3;
+
+    for row in rgb.chunks(row_len) {
+        z.write_all(&[0])?;
+        z.write_all(row)?;
+    }
+
+    write_chunk(&mut dest, b"IDAT", &z.finish()?)?;
+    write_chunk(&mut dest, b"IEND", &[])?;
+    Ok(())
+}
+
+fn write_chunk<W: Write>(dest: &mut W, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    let mut crc = Crc::new();
+    crc.update(kind);
+    crc.update(data);
+
+    dest.write_all(&(data.len() as u32).to_be_bytes())?;
+    dest.write_all(kind)?;
+    dest.write_all(data)?;
+    dest.write_all(&crc.sum().to_be_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::{convert::TryInto, io::Read};
+
+    #[test]
+    fn structure() {
+        let rgb = [0xFF, 0, 0, 0, 0xFF, 0];
+        let mut buf = Vec::new();
+        encode_rgb(&mut buf, 2, 1, &rgb).unwrap();
+
+        assert_eq!(&buf[..8], SIGNATURE);
+        assert_eq!(&buf[8..16], b"\0\0\0\x0dIHDR");
+        assert_eq!(&buf[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+
+        // The CRC of an empty IEND chunk is a well-known constant.
+        assert_eq!(&buf[buf.len() - 12..], b"\0\0\0\0IEND\xae\x42\x60\x82");
+
+        let idat_len = u32::from_be_bytes(buf[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&buf[37..41], b"IDAT");
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&buf[41..41 + idat_len])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw, [0, 0xFF, 0, 0, 0, 0xFF, 0]);
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! A simple RGB canvas that we composite rules and glyph images onto.
+
+use tectonic_xdv_support::freetype::GlyphBitmap;
+
+/// An opaque RGB color.
+pub type Rgb = [u8; 3];
+
+/// An 8-bit RGB image with a white background.
+#[derive(Debug)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+}
+
+impl Canvas {
+    /// Create a new, blank canvas. Both dimensions are forced to be at least
+    /// one pixel.
+    pub fn new(width: usize, height: usize) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        Canvas {
+            width,
+            height,
+            rgb: vec![0xFF; width * height * 3],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rgb(&self) -> &[u8] {
+        &self.rgb[..]
+    }
+
+    /// Blend a color into the pixel at `(x, y)` with the given opacity, from
+    /// 0 to 1. Pixels outside of the canvas are ignored.
+    fn blend(&mut self, x: i64, y: i64, color: Rgb, alpha: f64) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 || alpha <= 0. {
+            return;
+        }
+
+        let alpha = alpha.min(1.);
+        let i = (y as usize * self.width + x as usize) * 3;
+
+        for (c, dest) in color.iter().zip(&mut self.rgb[i..i + 3]) {
+            *dest = (*dest as f64 * (1. - alpha) + *c as f64 * alpha).round() as u8;
+        }
+    }
+
+    /// Fill a rectangle given in fractional pixel coordinates, antialiasing its
+    /// edges according to how much of each pixel it covers.
+    pub fn fill_rect(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: Rgb, opacity: f64) {
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        // How much of the pixel span [i, i+1) is covered by [lo, hi).
+        let coverage = |i: i64, lo: f64, hi: f64| -> f64 {
+            (hi.min(i as f64 + 1.) - lo.max(i as f64)).max(0.)
+        };
+
+        let xmin = (x0.floor() as i64).max(0);
+        let xmax = (x1.ceil() as i64).min(self.width as i64);
+        let ymin = (y0.floor() as i64).max(0);
+        let ymax = (y1.ceil() as i64).min(self.height as i64);
+
+        for y in ymin..ymax {
+            let ycov = coverage(y, y0, y1);
+
+            for x in xmin..xmax {
+                self.blend(x, y, color, opacity * ycov * coverage(x, x0, x1));
+            }
+        }
+    }
+
+    /// Draw a glyph image whose origin is at the pixel `(x, y)`.
+    pub fn draw_glyph(&mut self, glyph: &GlyphBitmap, x: i64, y: i64, color: Rgb, opacity: f64) {
+        let left = x + glyph.left as i64;
+        let top = y - glyph.top as i64;
+
+        for r in 0..glyph.rows {
+            for c in 0..glyph.width {
+                let cov = glyph.coverage[r * glyph.width + c];
+
+                if cov != 0 {
+                    self.blend(
+                        left + c as i64,
+                        top + r as i64,
+                        color,
+                        opacity * cov as f64 / 255.,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(c: &Canvas, x: usize, y: usize) -> Rgb {
+        let i = (y * c.width + x) * 3;
+        [c.rgb[i], c.rgb[i + 1], c.rgb[i + 2]]
+    }
+
+    #[test]
+    fn rect_antialiasing() {
+        let mut c = Canvas::new(4, 2);
+        c.fill_rect(0.5, 0., 2., 1., [0, 0, 0], 1.);
+        assert_eq!(pixel(&c, 0, 0), [0x80, 0x80, 0x80]);
+        assert_eq!(pixel(&c, 1, 0), [0, 0, 0]);
+        assert_eq!(pixel(&c, 2, 0), [0xFF, 0xFF, 0xFF]);
+        assert_eq!(pixel(&c, 1, 1), [0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn glyph_clipping() {
+        let mut c = Canvas::new(2, 2);
+        let glyph = GlyphBitmap {
+            left: -1,
+            top: 1,
+            width: 2,
+            rows: 1,
+            coverage: vec![0xFF, 0xFF],
+        };
+        c.draw_glyph(&glyph, 0, 1, [0xFF, 0, 0], 1.);
+        assert_eq!(pixel(&c, 0, 0), [0xFF, 0, 0]);
+        assert_eq!(pixel(&c, 1, 0), [0xFF, 0xFF, 0xFF]);
+    }
+}
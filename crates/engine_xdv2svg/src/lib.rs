@@ -0,0 +1,519 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Convert Tectonic’s XDV output to SVG.
+//!
+//! Each page of the XDV file becomes a standalone SVG image whose view box is
+//! the bounding box of the page content, which makes the output well-suited to
+//! including TeX-rendered snippets in web pages. Text in native (OpenType)
+//! fonts is emitted as SVG `<text>` elements that use subsets of the
+//! document’s fonts, which are either written out alongside the SVG files and
+//! referenced from them, or embedded directly into each SVG file. Each subset
+//! maps the characters in the text to the glyphs set by TeX, with glyphs that
+//! don't correspond to single characters, such as ligatures, mapped to
+//! private-use characters. Characters in traditional TFM-based fonts are drawn
+//! as paths, using the glyph outlines found through `pdftex.map`. Rules are
+//! emitted as `<rect>` elements.
+//!
+//! Rather than using this crate directly you should probably use the main
+//! [`tectonic`] crate, which runs this engine automatically when SVG output is
+//! requested.
+//!
+//! [`tectonic`]: https://docs.rs/tectonic/
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_status_base::StatusBackend;
+use tectonic_xdv::{FileType, XdvEvents, XdvParser};
+use tectonic_xdv_support::{
+    font::{FontFileData, GlyphId},
+    freetype::GlyphOutline,
+    tfm::{TfmFonts, TfmGlyph},
+    write_output, write_pages, FontFiles, FontInstance,
+};
+
+mod subset;
+mod svg;
+
+use self::svg::{tex_to_pt, PlacedChar, SvgPage};
+
+/// The first of the private-use characters that we assign to glyphs that
+/// don't correspond to any other character. This is the start of
+/// Supplementary Private Use Area-A, which documents are unlikely to use.
+const FIRST_PRIVATE_CHAR: u32 = 0xF_0000;
+
+/// A struct for invoking the XDV-to-SVG engine.
+///
+/// This struct has a fairly straightforward "builder" interface: you create it,
+/// apply any settings that you wish, and eventually run the
+/// [`process()`](Self::process) method.
+#[derive(Debug, Default)]
+pub struct Xdv2SvgEngine {
+    embed_fonts: bool,
+}
+
+impl Xdv2SvgEngine {
+    /// Specify whether font files should be embedded in the SVG output.
+    ///
+    /// By default, a subset of each font file used by the document is written
+    /// out once, next to the SVG files, as `<output_stem>-font<N>.otf` (or
+    /// `.ttf`), and referenced from them by name. If this setting is enabled,
+    /// each SVG file instead embeds subsets containing just the glyphs used on
+    /// its page as `data:` URLs, making every file fully self-contained.
+    pub fn embed_fonts(&mut self, embed: bool) -> &mut Self {
+        self.embed_fonts = embed;
+        self
+    }
+
+    /// Convert an XDV file into SVG.
+    ///
+    /// The file named `xdv` is read from the I/O stack. If the document has a
+    /// single page, it is written to `<output_stem>.svg`; otherwise, page *N*
+    /// is written to `<output_stem>-<N>.svg`, counting from 1.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        xdv: &str,
+        output_stem: &str,
+    ) -> Result<()> {
+        let mut input = hooks.io().input_open_name(xdv, status).must_exist()?;
+
+        let pages = {
+            let state = EngineState::new(hooks, status, self.embed_fonts, output_stem)?;
+            let state = XdvParser::process_with_seeks(&mut input, state)?;
+            state.finished()?
+        };
+
+        let (name, digest_opt) = input.into_name_digest();
+        hooks.event_input_closed(name, digest_opt, status);
+
+        write_pages(hooks, status, output_stem, "svg", &pages)
+    }
+}
+
+/// A font file loaded from the I/O stack.
+struct FontFile {
+    data: FontFileData,
+    face_index: u32,
+
+    /// Whether the font has CFF outlines rather than TrueType ones.
+    is_cff: bool,
+
+    /// The glyph that each character that we emit in this font maps to. Every
+    /// glyph used in the document gets at least one character, which lets us
+    /// give the font subsets a character map that matches the text.
+    char_glyphs: BTreeMap<char, GlyphId>,
+
+    /// The private-use characters assigned to glyphs.
+    private_chars: HashMap<GlyphId, char>,
+}
+
+impl FontFile {
+    fn family(idx: usize) -> String {
+        format!("xdv2svg-font{idx}")
+    }
+
+    /// Get the file extension, CSS format name, and MIME type of the font.
+    fn format(&self) -> (&'static str, &'static str, &'static str) {
+        if self.is_cff {
+            ("otf", "opentype", "font/otf")
+        } else {
+            ("ttf", "truetype", "font/ttf")
+        }
+    }
+
+    /// Get the character to emit for a glyph.
+    ///
+    /// We prefer the character that TeX reported for the glyph, if any, and
+    /// then the one that the font's own character map associates with it. If
+    /// those are unavailable or already used for a different glyph, as
+    /// happens with ligatures and alternate forms, we assign a private-use
+    /// character.
+    fn char_for_glyph(&mut self, glyph: GlyphId, preferred: Option<char>) -> char {
+        let candidates = preferred
+            .into_iter()
+            .chain(self.data.lookup_char(glyph))
+            .filter(|c| !c.is_control());
+
+        for c in candidates {
+            match self.char_glyphs.get(&c) {
+                Some(g) if *g == glyph => return c,
+                Some(_) => {}
+                None => {
+                    self.char_glyphs.insert(c, glyph);
+                    return c;
+                }
+            }
+        }
+
+        if let Some(c) = self.private_chars.get(&glyph) {
+            return *c;
+        }
+
+        // There are fewer glyph IDs than private-use characters from here on,
+        // so this always finds one.
+        let c = (FIRST_PRIVATE_CHAR + self.private_chars.len() as u32..)
+            .filter_map(char::from_u32)
+            .find(|c| !self.char_glyphs.contains_key(c))
+            .unwrap();
+
+        self.char_glyphs.insert(c, glyph);
+        self.private_chars.insert(glyph, c);
+        c
+    }
+
+    /// Create a subset of the font with the given characters.
+    fn subset<'c>(&self, name: &str, chars: impl IntoIterator<Item = &'c char>) -> Result<Vec<u8>> {
+        let cmap: Vec<_> = chars
+            .into_iter()
+            .map(|c| (*c, self.char_glyphs[c]))
+            .collect();
+
+        Ok(atry!(
+            subset::subset_font(self.data.buffer(), self.face_index, &cmap);
+            ["unable to create a subset of font file `{}`", name]
+        ))
+    }
+}
+
+struct EngineState<'a> {
+    hooks: &'a mut dyn DriverHooks,
+    status: &'a mut dyn StatusBackend,
+    embed_fonts: bool,
+    output_stem: &'a str,
+    font_files: FontFiles<FontFile>,
+    fonts: HashMap<i32, FontInstance>,
+    tfm: TfmFonts,
+
+    /// The outlines of the glyphs of TFM fonts, with the units per em of their
+    /// faces, keyed by face index and glyph.
+    tfm_outlines: HashMap<(usize, u32), (GlyphOutline, u16)>,
+
+    cur_page: Option<PageState>,
+    finished_pages: Vec<String>,
+}
+
+/// The state of the page currently being processed.
+#[derive(Default)]
+struct PageState {
+    svg: SvgPage,
+
+    /// The font numbers used on this page, in order of first use.
+    fonts_used: Vec<i32>,
+
+    /// The characters used on this page, for each font file.
+    chars_used: BTreeMap<usize, BTreeSet<char>>,
+}
+
+impl<'a> EngineState<'a> {
+    fn new(
+        hooks: &'a mut dyn DriverHooks,
+        status: &'a mut dyn StatusBackend,
+        embed_fonts: bool,
+        output_stem: &'a str,
+    ) -> Result<Self> {
+        Ok(EngineState {
+            hooks,
+            status,
+            embed_fonts,
+            output_stem,
+            font_files: FontFiles::default(),
+            fonts: HashMap::new(),
+            tfm: TfmFonts::new()?,
+            tfm_outlines: HashMap::new(),
+            cur_page: None,
+            finished_pages: Vec::new(),
+        })
+    }
+
+    fn finished(mut self) -> Result<Vec<String>> {
+        self.finish_page()?;
+
+        if !self.embed_fonts {
+            for file_idx in 0..self.font_files.len() {
+                let ff = self.font_files.get(file_idx);
+
+                if ff.char_glyphs.is_empty() {
+                    continue;
+                }
+
+                let data = ff.subset(self.font_files.name(file_idx), ff.char_glyphs.keys())?;
+                let name = self.font_file_name(file_idx);
+                write_output(self.hooks, self.status, &name, &data)?;
+            }
+        }
+
+        self.tfm.warn_missing(self.status, "SVG");
+        Ok(self.finished_pages)
+    }
+
+    /// The name of the output file containing the subset of a font file.
+    fn font_file_name(&self, file_idx: usize) -> String {
+        let (ext, _, _) = self.font_files.get(file_idx).format();
+        format!("{}-font{}.{}", self.output_stem, file_idx, ext)
+    }
+
+    /// Render the current page, if any, and set it aside.
+    fn finish_page(&mut self) -> Result<()> {
+        let page = match self.cur_page.take() {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        let mut style = String::new();
+
+        for (file_idx, chars) in &page.chars_used {
+            let ff = self.font_files.get(*file_idx);
+            let (_, format, mime) = ff.format();
+
+            let url = if self.embed_fonts {
+                let data = ff.subset(self.font_files.name(*file_idx), chars)?;
+                format!("data:{};base64,{}", mime, STANDARD.encode(data))
+            } else {
+                // The SVG files are written next to the fonts, so we reference
+                // them without any directory.
+                let name = self.font_file_name(*file_idx);
+                name.rsplit('/').next().unwrap_or(&name).to_owned()
+            };
+
+            writeln!(
+                style,
+                "@font-face {{ font-family: \"{}\"; src: url(\"{}\") format(\"{}\"); }}",
+                FontFile::family(*file_idx),
+                url,
+                format
+            )
+            .unwrap();
+        }
+
+        for fnum in &page.fonts_used {
+            let fi = &self.fonts[fnum];
+
+            write!(
+                style,
+                ".f{} {{ font-family: \"{}\"; font-size: {}px;",
+                fnum,
+                FontFile::family(fi.file_idx),
+                tex_to_pt(fi.size)
+            )
+            .unwrap();
+
+            if let Some(rgba) = fi.color_rgba {
+                write!(style, " fill: #{:06x};", rgba >> 8).unwrap();
+
+                let alpha = rgba & 0xFF;
+
+                if alpha != 0xFF {
+                    write!(style, " fill-opacity: {:.3};", alpha as f64 / 255.).unwrap();
+                }
+            }
+
+            style.push_str(" }\n");
+        }
+
+        self.finished_pages.push(page.svg.finish(&style));
+        Ok(())
+    }
+
+    /// Add a run of glyphs to the current page. If `text` is provided and
+    /// contains exactly one character per glyph, its characters are preferred
+    /// as the textual content of the run.
+    fn add_glyphs(
+        &mut self,
+        font_num: i32,
+        text: Option<&str>,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        let fi = *a_ok_or!(
+            self.fonts.get(&font_num);
+            ["reference to undefined native font #{}", font_num]
+        );
+        let ff = self.font_files.get_mut(fi.file_idx);
+
+        let text_chars: Option<Vec<char>> = text
+            .map(|t| t.chars().collect())
+            .filter(|c: &Vec<char>| c.len() == glyphs.len());
+
+        let chars: Vec<_> = glyphs
+            .iter()
+            .enumerate()
+            .map(|(i, &glyph)| PlacedChar {
+                c: ff.char_for_glyph(glyph, text_chars.as_ref().map(|tc| tc[i])),
+                x: x[i],
+                y: y[i],
+            })
+            .collect();
+
+        let last_advance = ff
+            .data
+            .advance(glyphs.last().copied().unwrap_or(0), fi.size);
+        let (ascent, descent) = ff.data.ascent_descent(fi.size);
+
+        let page = self.cur_page.get_or_insert_with(PageState::default);
+        page.svg.add_text(
+            &format!("f{font_num}"),
+            &chars,
+            last_advance,
+            ascent,
+            descent,
+        );
+
+        if !chars.is_empty() && !page.fonts_used.contains(&font_num) {
+            page.fonts_used.push(font_num);
+        }
+
+        page.chars_used
+            .entry(fi.file_idx)
+            .or_default()
+            .extend(chars.iter().map(|pc| pc.c));
+        Ok(())
+    }
+
+    /// Draw a glyph of a TFM font on the current page, with its origin at
+    /// `(x, y)`.
+    fn add_tfm_glyph(&mut self, glyph: TfmGlyph, x: i32, y: i32) -> Result<()> {
+        let key = (glyph.face_idx, glyph.glyph);
+
+        if !self.tfm_outlines.contains_key(&key) {
+            let face = self.tfm.face_mut(glyph.face_idx);
+            let units_per_em = face.units_per_em();
+            let outline = face.outline(glyph.glyph)?;
+            self.tfm_outlines.insert(key, (outline, units_per_em));
+        }
+
+        let (outline, units_per_em) = &self.tfm_outlines[&key];
+
+        if outline.path.is_empty() {
+            return Ok(());
+        }
+
+        let id = format!("g{}-{}", glyph.face_idx, glyph.glyph);
+        let scale = glyph.size as f64 / (*units_per_em).max(1) as f64;
+
+        let page = self.cur_page.get_or_insert_with(PageState::default);
+        page.svg.define_path(&id, &outline.path);
+        page.svg.add_use(&id, x, y, scale, outline.bbox);
+        Ok(())
+    }
+}
+
+impl<'a> XdvEvents for EngineState<'a> {
+    type Error = Error;
+
+    fn handle_header(&mut self, filetype: FileType, _comment: &[u8]) -> Result<()> {
+        if filetype != FileType::Xdv {
+            bail!("file should be XDV format; got {}", filetype);
+        }
+
+        Ok(())
+    }
+
+    fn handle_begin_page(&mut self, _counters: &[i32], _previous_bop: i32) -> Result<()> {
+        self.finish_page()?;
+        self.cur_page = Some(PageState::default());
+        Ok(())
+    }
+
+    fn handle_char(&mut self, font_num: i32, c: i32, x: i32, y: i32) -> Result<i32> {
+        if let Some(glyph) = self.tfm.glyph(font_num, c) {
+            self.add_tfm_glyph(glyph, x, y)?;
+        }
+
+        Ok(self.tfm.char_width(font_num, c))
+    }
+
+    fn handle_glyph_run(
+        &mut self,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.add_glyphs(font_num, None, glyphs, x, y)
+    }
+
+    fn handle_text_and_glyphs(
+        &mut self,
+        font_num: i32,
+        text: &str,
+        _width: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        self.add_glyphs(font_num, Some(text), glyphs, x, y)
+    }
+
+    fn handle_define_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        _checksum: u32,
+        scale: i32,
+        _design_size: i32,
+    ) -> Result<()> {
+        self.tfm
+            .define(self.hooks, self.status, name, font_num, scale)
+    }
+
+    fn handle_define_native_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        size: i32,
+        face_index: u32,
+        color_rgba: Option<u32>,
+        _extend: Option<u32>,
+        _slant: Option<u32>,
+        _embolden: Option<u32>,
+    ) -> Result<()> {
+        if self.fonts.contains_key(&font_num) {
+            return Ok(());
+        }
+
+        let file_idx = self.font_files.load(
+            self.hooks,
+            self.status,
+            name,
+            face_index,
+            |buffer, face_index| {
+                Ok(FontFile {
+                    is_cff: subset::has_cff_outlines(&buffer, face_index)?,
+                    data: FontFileData::from_opentype(buffer, face_index)?,
+                    face_index,
+                    char_glyphs: BTreeMap::new(),
+                    private_chars: HashMap::new(),
+                })
+            },
+        )?;
+
+        self.fonts.insert(
+            font_num,
+            FontInstance {
+                file_idx,
+                size,
+                color_rgba,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn handle_rule(&mut self, x: i32, y: i32, height: i32, width: i32) -> Result<()> {
+        self.cur_page
+            .get_or_insert_with(PageState::default)
+            .svg
+            .add_rule(x, y, height, width);
+        Ok(())
+    }
+}
@@ -0,0 +1,822 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Creating stripped-down copies of font files.
+//!
+//! Rather than shipping the document’s font files as-is, we create a copy of
+//! each one that only contains the outlines of the glyphs that the SVG output
+//! uses, with a new character map that maps the characters we emit to those
+//! glyphs. We keep the glyph IDs unchanged, which keeps this simple: the
+//! outlines of unused glyphs are just replaced with empty ones. We also drop
+//! the layout tables, since we position every glyph ourselves and don't want
+//! browsers to apply ligatures or kerning on top of that.
+//!
+//! Outlines are stripped from TrueType (`glyf`) fonts and from name-keyed CFF
+//! fonts. CID-keyed CFF fonts and fonts with other kinds of outlines are copied
+//! with all of their outlines.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    ops::Range,
+};
+use tectonic_engine_spx2html::opentype::{
+    append_simple_cmap, checksum_adjustment, opentype_checksum,
+};
+use tectonic_errors::prelude::*;
+use tectonic_xdv_support::font::GlyphId;
+
+type Tag = [u8; 4];
+
+/// Tables that we don't copy into the new font. Besides the layout tables,
+/// these are tables that our changes would invalidate, and the character map,
+/// which we replace.
+const DROPPED_TABLES: &[&Tag] = &[
+    b"BASE", b"DSIG", b"GDEF", b"GPOS", b"GSUB", b"JSTF", b"LTSH", b"MATH", b"VDMX", b"cmap",
+    b"feat", b"hdmx", b"kern", b"kerx", b"morx", b"mort",
+];
+
+/// The Type 2 charstring of an empty glyph: just `endchar`.
+const EMPTY_CHARSTRING: &[u8] = &[14];
+
+/// Create a copy of face `face_index` of the font `data`, keeping only the
+/// outlines of the glyphs in `cmap`, which is also the new character map. It
+/// must be sorted by character.
+pub fn subset_font(data: &[u8], face_index: u32, cmap: &[(char, GlyphId)]) -> Result<Vec<u8>> {
+    let (sfnt_version, source_tables) = read_tables(data, face_index)?;
+    let mut tables = BTreeMap::new();
+
+    for (tag, content) in source_tables {
+        if !DROPPED_TABLES.contains(&&tag) {
+            tables.insert(tag, content.to_vec());
+        }
+    }
+
+    let mut keep: BTreeSet<GlyphId> = cmap.iter().map(|(_, g)| *g).collect();
+    keep.insert(0); // .notdef
+
+    let n_glyphs = match tables.get(b"maxp") {
+        Some(maxp) if maxp.len() >= 6 => BigEndian::read_u16(&maxp[4..6]) as usize,
+        _ => bail!("missing or invalid MAXP table"),
+    };
+
+    if tables.get(b"head").map(|h| h.len()).unwrap_or(0) < 54 {
+        bail!("missing or invalid HEAD table");
+    }
+
+    if let (Some(glyf), Some(loca)) = (tables.get(b"glyf"), tables.get(b"loca")) {
+        let long_loca = BigEndian::read_i16(&tables[b"head"][50..52]) != 0;
+        let (glyf, loca) = subset_glyf(glyf, loca, long_loca, n_glyphs, &keep)?;
+        tables.insert(*b"glyf", glyf);
+        tables.insert(*b"loca", loca);
+
+        // The new `loca` table always has the long format.
+        BigEndian::write_i16(&mut tables.get_mut(b"head").unwrap()[50..52], 1);
+    } else if let Some(cff) = tables.get(b"CFF ") {
+        if let Some(cff) = subset_cff(cff, &keep)? {
+            tables.insert(*b"CFF ", cff);
+        }
+    }
+
+    let mut new_cmap = Vec::new();
+    append_simple_cmap(&mut new_cmap, cmap);
+    tables.insert(*b"cmap", new_cmap);
+
+    // The checksum adjustment must be zero while computing the checksums.
+    BigEndian::write_u32(&mut tables.get_mut(b"head").unwrap()[8..12], 0);
+
+    Ok(write_font(sfnt_version, &tables))
+}
+
+/// Determine whether face `face_index` of a font has CFF outlines, as opposed
+/// to TrueType ones. This determines the conventional file extension of the
+/// font.
+pub fn has_cff_outlines(data: &[u8], face_index: u32) -> Result<bool> {
+    let (_, tables) = read_tables(data, face_index)?;
+    Ok(tables.contains_key(b"CFF ") || tables.contains_key(b"CFF2"))
+}
+
+/// Read the table directory of a font, returning the `sfntVersion` and the
+/// tables. Font collections are supported.
+fn read_tables(data: &[u8], face_index: u32) -> Result<(u32, BTreeMap<Tag, &[u8]>)> {
+    let dir_offset = if data.get(..4) == Some(b"ttcf") {
+        let n_fonts = BigEndian::read_u32(get(data, 8..12)?);
+
+        if face_index >= n_fonts {
+            bail!("no face #{} in the font collection", face_index);
+        }
+
+        let ofs = 12 + 4 * face_index as usize;
+        BigEndian::read_u32(get(data, ofs..ofs + 4)?) as usize
+    } else {
+        0
+    };
+
+    let header = get(data, dir_offset..dir_offset + 12)?;
+    let sfnt_version = BigEndian::read_u32(&header[0..4]);
+    let n_tables = BigEndian::read_u16(&header[4..6]) as usize;
+    let mut tables = BTreeMap::new();
+
+    for i in 0..n_tables {
+        let ofs = dir_offset + 12 + 16 * i;
+        let record = get(data, ofs..ofs + 16)?;
+        let mut tag = [0; 4];
+        tag.copy_from_slice(&record[0..4]);
+        let offset = BigEndian::read_u32(&record[8..12]) as usize;
+        let length = BigEndian::read_u32(&record[12..16]) as usize;
+        tables.insert(tag, get(data, offset..offset + length)?);
+    }
+
+    Ok((sfnt_version, tables))
+}
+
+/// Assemble a font file from its tables, which must have the checksum
+/// adjustment of the HEAD table set to zero.
+fn write_font(sfnt_version: u32, tables: &BTreeMap<Tag, Vec<u8>>) -> Vec<u8> {
+    let n_tables = tables.len();
+    let entry_selector = (usize::BITS - 1 - n_tables.max(1).leading_zeros()) as usize;
+    let search_range = 16 << entry_selector;
+
+    let mut buf = Vec::new();
+    push_u32(&mut buf, sfnt_version);
+    push_u16(&mut buf, n_tables as u16);
+    push_u16(&mut buf, search_range as u16);
+    push_u16(&mut buf, entry_selector as u16);
+    push_u16(
+        &mut buf,
+        (n_tables * 16 - search_range.min(n_tables * 16)) as u16,
+    );
+
+    let mut offset = 12 + 16 * n_tables;
+    let mut head_offset = 0;
+
+    for (tag, content) in tables {
+        if tag == b"head" {
+            head_offset = offset;
+        }
+
+        buf.extend_from_slice(tag);
+        push_u32(&mut buf, opentype_checksum(content));
+        push_u32(&mut buf, offset as u32);
+        push_u32(&mut buf, content.len() as u32);
+        offset += pad4(content.len());
+    }
+
+    for content in tables.values() {
+        buf.extend_from_slice(content);
+        buf.resize(pad4(buf.len()), 0);
+    }
+
+    let adjustment = checksum_adjustment(&buf);
+    BigEndian::write_u32(&mut buf[head_offset + 8..head_offset + 12], adjustment);
+    buf
+}
+
+/// Strip the unused glyphs from the `glyf` table, returning new `glyf` and
+/// long-format `loca` tables. Glyphs used as components of composite glyphs
+/// are kept too.
+fn subset_glyf(
+    glyf: &[u8],
+    loca: &[u8],
+    long_loca: bool,
+    n_glyphs: usize,
+    keep: &BTreeSet<GlyphId>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let offsets = (0..=n_glyphs)
+        .map(|i| {
+            Ok(if long_loca {
+                BigEndian::read_u32(get(loca, 4 * i..4 * i + 4)?) as usize
+            } else {
+                2 * BigEndian::read_u16(get(loca, 2 * i..2 * i + 2)?) as usize
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let glyph_data = |g: usize| -> Result<&[u8]> {
+        if g >= n_glyphs || offsets[g] >= offsets[g + 1] {
+            Ok(&[])
+        } else {
+            get(glyf, offsets[g]..offsets[g + 1])
+        }
+    };
+
+    // Find the components of the composite glyphs that we keep.
+
+    let mut keep = keep.clone();
+    let mut todo: Vec<GlyphId> = keep.iter().copied().collect();
+
+    while let Some(g) = todo.pop() {
+        for component in composite_components(glyph_data(g as usize)?)? {
+            if keep.insert(component) {
+                todo.push(component);
+            }
+        }
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity(4 * (n_glyphs + 1));
+
+    for g in 0..n_glyphs {
+        push_u32(&mut new_loca, new_glyf.len() as u32);
+
+        if keep.contains(&(g as GlyphId)) {
+            new_glyf.extend_from_slice(glyph_data(g)?);
+            new_glyf.resize(pad4(new_glyf.len()), 0);
+        }
+    }
+
+    push_u32(&mut new_loca, new_glyf.len() as u32);
+    Ok((new_glyf, new_loca))
+}
+
+/// Get the glyphs that a glyph description refers to, if it is a composite
+/// glyph.
+fn composite_components(data: &[u8]) -> Result<Vec<GlyphId>> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut components = Vec::new();
+
+    if data.len() < 10 || BigEndian::read_i16(&data[0..2]) >= 0 {
+        return Ok(components);
+    }
+
+    let mut pos = 10;
+
+    loop {
+        let record = get(data, pos..pos + 4)?;
+        let flags = BigEndian::read_u16(&record[0..2]);
+        components.push(BigEndian::read_u16(&record[2..4]));
+
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+            4
+        } else {
+            2
+        };
+
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(components);
+        }
+    }
+}
+
+/// Strip the unused glyphs from a name-keyed CFF font. Returns `None` if the
+/// font has a structure that we don't handle.
+///
+/// We rebuild the font with the original header, Name INDEX, String INDEX,
+/// and Global Subrs INDEX, a new CharStrings INDEX, and a Top DICT updated to
+/// point at the new locations of the charset, encoding, CharStrings, and
+/// Private DICT.
+fn subset_cff(cff: &[u8], keep: &BTreeSet<GlyphId>) -> Result<Option<Vec<u8>>> {
+    const OP_CHARSET: u16 = 15;
+    const OP_ENCODING: u16 = 16;
+    const OP_CHARSTRINGS: u16 = 17;
+    const OP_PRIVATE: u16 = 18;
+    const OP_SUBRS: u16 = 19;
+    const OP_ROS: u16 = 12 << 8 | 30;
+
+    let hdr_size = *a_ok_or!(cff.get(2); ["truncated CFF data"]) as usize;
+    let (_, names_end) = read_index(cff, hdr_size)?;
+    let (top_dicts, top_dicts_end) = read_index(cff, names_end)?;
+    let (_, strings_end) = read_index(cff, top_dicts_end)?;
+    let (_, gsubrs_end) = read_index(cff, strings_end)?;
+
+    if top_dicts.len() != 1 {
+        return Ok(None);
+    }
+
+    let top_dict = parse_dict(&cff[top_dicts[0].clone()])?;
+
+    if top_dict.iter().any(|e| e.op == OP_ROS) {
+        return Ok(None);
+    }
+
+    let operand = |op: u16, idx: usize| -> Option<usize> {
+        let entry = top_dict.iter().find(|e| e.op == op)?;
+        usize::try_from(*entry.operands.get(idx)?).ok()
+    };
+
+    let charstrings_offset = match operand(OP_CHARSTRINGS, 0) {
+        Some(o) => o,
+        None => return Ok(None),
+    };
+
+    let (charstrings, _) = read_index(cff, charstrings_offset)?;
+    let n_glyphs = charstrings.len();
+
+    // The charset and encoding can be predefined, in which case the
+    // "offset" is a small identifier and there's nothing to copy.
+
+    let charset = match operand(OP_CHARSET, 0) {
+        Some(o) if o > 2 => Some(get(cff, o..o + charset_len(&cff[o..], n_glyphs)?)?),
+        _ => None,
+    };
+
+    let encoding = match operand(OP_ENCODING, 0) {
+        Some(o) if o > 1 => Some(get(cff, o..o + encoding_len(&cff[o..])?)?),
+        _ => None,
+    };
+
+    // We copy the Private DICT along with its local subroutines, which it
+    // references relative to its own location.
+
+    let private = match (operand(OP_PRIVATE, 0), operand(OP_PRIVATE, 1)) {
+        (Some(size), Some(offset)) => {
+            let dict = get(cff, offset..offset + size)?;
+            let mut end = offset + size;
+
+            if let Some(subrs) = parse_dict(dict)?.iter().find(|e| e.op == OP_SUBRS) {
+                match subrs.operands.first().map(|o| usize::try_from(*o)) {
+                    Some(Ok(rel)) if rel >= size => {
+                        end = end.max(read_index(cff, offset + rel)?.1);
+                    }
+
+                    _ => return Ok(None),
+                }
+            }
+
+            Some((size, get(cff, offset..end)?))
+        }
+
+        _ => None,
+    };
+
+    let mut new_charstrings = Vec::with_capacity(n_glyphs);
+
+    for (g, range) in charstrings.into_iter().enumerate() {
+        if keep.contains(&(g as GlyphId)) {
+            new_charstrings.push(&cff[range]);
+        } else {
+            new_charstrings.push(EMPTY_CHARSTRING);
+        }
+    }
+
+    let new_charstrings = write_index(&new_charstrings);
+
+    // Lay out the new font. Every offset in the new Top DICT is encoded in
+    // five bytes, so its size doesn't depend on the offsets' values.
+
+    let encode_top_dict = |offsets: &[(u16, Vec<i64>)]| -> Vec<u8> {
+        let mut dict = Vec::new();
+
+        for entry in &top_dict {
+            match offsets.iter().find(|(op, _)| *op == entry.op) {
+                Some((op, values)) => {
+                    for v in values {
+                        dict.push(29);
+                        push_u32(&mut dict, *v as i32 as u32);
+                    }
+
+                    if *op > 0xFF {
+                        dict.push((op >> 8) as u8);
+                    }
+
+                    dict.push(*op as u8);
+                }
+
+                None => dict.extend_from_slice(
+                    &cff[top_dicts[0].start + entry.raw.start..][..entry.raw.len()],
+                ),
+            }
+        }
+
+        dict
+    };
+
+    let layout = |top_dict_len: usize| -> Vec<(u16, Vec<i64>)> {
+        let mut pos = names_end + index_len(1, top_dict_len) + (gsubrs_end - top_dicts_end);
+        let mut offsets = Vec::new();
+
+        if let Some(c) = charset {
+            offsets.push((OP_CHARSET, vec![pos as i64]));
+            pos += c.len();
+        }
+
+        if let Some(e) = encoding {
+            offsets.push((OP_ENCODING, vec![pos as i64]));
+            pos += e.len();
+        }
+
+        offsets.push((OP_CHARSTRINGS, vec![pos as i64]));
+        pos += new_charstrings.len();
+
+        if let Some((size, _)) = private {
+            offsets.push((OP_PRIVATE, vec![size as i64, pos as i64]));
+        }
+
+        offsets
+    };
+
+    let top_dict_len = encode_top_dict(&layout(0)).len();
+    let new_top_dict = encode_top_dict(&layout(top_dict_len));
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&cff[..names_end]);
+    out.extend_from_slice(&write_index(&[&new_top_dict[..]]));
+    out.extend_from_slice(&cff[top_dicts_end..gsubrs_end]);
+
+    if let Some(c) = charset {
+        out.extend_from_slice(c);
+    }
+
+    if let Some(e) = encoding {
+        out.extend_from_slice(e);
+    }
+
+    out.extend_from_slice(&new_charstrings);
+
+    if let Some((_, p)) = private {
+        out.extend_from_slice(p);
+    }
+
+    Ok(Some(out))
+}
+
+/// Read a CFF INDEX at `pos`, returning the ranges of its items and the
+/// position of its end.
+fn read_index(data: &[u8], pos: usize) -> Result<(Vec<Range<usize>>, usize)> {
+    let count = BigEndian::read_u16(get(data, pos..pos + 2)?) as usize;
+
+    if count == 0 {
+        return Ok((Vec::new(), pos + 2));
+    }
+
+    let off_size = *a_ok_or!(data.get(pos + 2); ["truncated CFF data"]) as usize;
+
+    if !(1..=4).contains(&off_size) {
+        bail!("invalid CFF INDEX offset size {}", off_size);
+    }
+
+    let offsets_start = pos + 3;
+    let data_base = offsets_start + (count + 1) * off_size - 1;
+
+    let offsets = (0..=count)
+        .map(|i| {
+            let o = offsets_start + i * off_size;
+            Ok(BigEndian::read_uint(get(data, o..o + off_size)?, off_size) as usize + data_base)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut items = Vec::with_capacity(count);
+
+    for w in offsets.windows(2) {
+        get(data, w[0]..w[1])?;
+        items.push(w[0]..w[1]);
+    }
+
+    Ok((items, offsets[count]))
+}
+
+/// The size of a CFF INDEX with `count` items totalling `data_len` bytes.
+fn index_len(count: usize, data_len: usize) -> usize {
+    2 + 1 + (count + 1) * offset_size(data_len + 1) + data_len
+}
+
+fn offset_size(max_offset: usize) -> usize {
+    match max_offset {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x10000..=0xFF_FFFF => 3,
+        _ => 4,
+    }
+}
+
+fn write_index(items: &[&[u8]]) -> Vec<u8> {
+    let data_len: usize = items.iter().map(|i| i.len()).sum();
+    let off_size = offset_size(data_len + 1);
+    let mut buf = Vec::with_capacity(index_len(items.len(), data_len));
+    push_u16(&mut buf, items.len() as u16);
+    buf.push(off_size as u8);
+
+    let mut offset = 1;
+
+    for item in items.iter().map(|i| i.len()).chain(std::iter::once(0)) {
+        buf.extend_from_slice(&(offset as u32).to_be_bytes()[4 - off_size..]);
+        offset += item;
+    }
+
+    for item in items {
+        buf.extend_from_slice(item);
+    }
+
+    buf
+}
+
+/// An entry in a CFF DICT.
+struct DictEntry {
+    op: u16,
+
+    /// The integer operands. Real-number operands are recorded as zero.
+    operands: Vec<i64>,
+
+    /// The location of the entry's operands and operator in the DICT data.
+    raw: Range<usize>,
+}
+
+fn parse_dict(data: &[u8]) -> Result<Vec<DictEntry>> {
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    let mut start = 0;
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let b0 = data[pos];
+        let byte =
+            |i: usize| -> Result<i64> { Ok(*a_ok_or!(data.get(i); ["truncated CFF DICT"]) as i64) };
+
+        match b0 {
+            0..=21 => {
+                let op = if b0 == 12 {
+                    pos += 1;
+                    12 << 8 | byte(pos)? as u16
+                } else {
+                    b0 as u16
+                };
+
+                pos += 1;
+                entries.push(DictEntry {
+                    op,
+                    operands: std::mem::take(&mut operands),
+                    raw: start..pos,
+                });
+                start = pos;
+            }
+
+            28 => {
+                operands.push((byte(pos + 1)? << 8 | byte(pos + 2)?) as i16 as i64);
+                pos += 3;
+            }
+
+            29 => {
+                operands.push(
+                    (byte(pos + 1)? << 24
+                        | byte(pos + 2)? << 16
+                        | byte(pos + 3)? << 8
+                        | byte(pos + 4)?) as i32 as i64,
+                );
+                pos += 5;
+            }
+
+            30 => {
+                // A real number, encoded in nibbles terminated by 0xF.
+                pos += 1;
+
+                loop {
+                    let b = byte(pos)?;
+                    pos += 1;
+
+                    if b & 0x0F == 0x0F || b & 0xF0 == 0xF0 {
+                        break;
+                    }
+                }
+
+                operands.push(0);
+            }
+
+            32..=246 => {
+                operands.push(b0 as i64 - 139);
+                pos += 1;
+            }
+
+            247..=250 => {
+                operands.push((b0 as i64 - 247) * 256 + byte(pos + 1)? + 108);
+                pos += 2;
+            }
+
+            251..=254 => {
+                operands.push(-(b0 as i64 - 251) * 256 - byte(pos + 1)? - 108);
+                pos += 2;
+            }
+
+            _ => bail!("invalid byte {} in CFF DICT", b0),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The size of a CFF charset in one of the three formats.
+fn charset_len(data: &[u8], n_glyphs: usize) -> Result<usize> {
+    let format = *a_ok_or!(data.first(); ["truncated CFF charset"]);
+    let n_named = n_glyphs.saturating_sub(1); // .notdef is implicit
+
+    let range_size = match format {
+        0 => return Ok(1 + 2 * n_named),
+        1 => 3,
+        2 => 4,
+        _ => bail!("invalid CFF charset format {}", format),
+    };
+
+    let mut pos = 1;
+    let mut covered = 0;
+
+    while covered < n_named {
+        let range = get(data, pos..pos + range_size)?;
+        let n_left = BigEndian::read_uint(&range[2..], range_size - 2) as usize;
+        covered += n_left + 1;
+        pos += range_size;
+    }
+
+    Ok(pos)
+}
+
+/// The size of a CFF encoding in one of the two formats, with optional
+/// supplements.
+fn encoding_len(data: &[u8]) -> Result<usize> {
+    let header = get(data, 0..2)?;
+    let (format, n) = (header[0], header[1] as usize);
+
+    let mut len = match format & 0x7F {
+        0 => 2 + n,
+        1 => 2 + 2 * n,
+        f => bail!("invalid CFF encoding format {}", f),
+    };
+
+    if format & 0x80 != 0 {
+        let n_sups = *a_ok_or!(data.get(len); ["truncated CFF encoding"]) as usize;
+        len += 1 + 3 * n_sups;
+    }
+
+    Ok(len)
+}
+
+fn get(data: &[u8], range: Range<usize>) -> Result<&[u8]> {
+    Ok(a_ok_or!(data.get(range); ["truncated or malformed font data"]))
+}
+
+fn pad4(n: usize) -> usize {
+    (n + 3) & !3
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinot::{FontDataRef, TableProvider};
+    use tectonic_xdv_support::freetype::{Face, Library};
+
+    fn load_asset(name: &str) -> Vec<u8> {
+        std::fs::read(format!(
+            "{}/../../tests/assets/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        ))
+        .unwrap()
+    }
+
+    /// Subset a font and check that the result parses, maps the given
+    /// character to its glyph, and is much smaller than the original.
+    fn check_subset(name: &str) {
+        let data = load_asset(name);
+        let font = FontDataRef::new(&data).unwrap().get(0).unwrap();
+        let glyph = font.cmap().unwrap().map('a' as u32).unwrap();
+
+        let subset = subset_font(&data, 0, &[('a', glyph), ('\u{F0000}', glyph)]).unwrap();
+        assert!(
+            subset.len() * 2 < data.len(),
+            "{}: {} bytes",
+            name,
+            subset.len()
+        );
+        assert_eq!(opentype_checksum(&subset), 0xB1B0AFBA);
+
+        let font = FontDataRef::new(&subset).unwrap().get(0).unwrap();
+        let cmap = font.cmap().unwrap();
+        assert_eq!(cmap.map('a' as u32), Some(glyph));
+        assert_eq!(cmap.map(0xF0000), Some(glyph));
+        assert_eq!(cmap.map('b' as u32), None);
+        assert!(font.gsub().is_none());
+
+        // The outline of the kept glyph survives, while others are emptied.
+        let lib = Library::new().unwrap();
+        let mut face = Face::new(&lib, subset, 0).unwrap();
+        assert!(!face.outline(glyph as u32).unwrap().path.is_empty());
+        assert!(face.outline(glyph as u32 + 1).unwrap().path.is_empty());
+    }
+
+    /// Subset a font and check the resulting tables against the original
+    /// ones: the tables that we don't touch are copied verbatim, the kept
+    /// glyphs have the same outlines as before, and the table directory has
+    /// correct checksums.
+    fn check_tables(name: &str) {
+        let data = load_asset(name);
+        let font = FontDataRef::new(&data).unwrap().get(0).unwrap();
+        let cmap = font.cmap().unwrap();
+        let map: Vec<_> = ['a', 'b', '\u{e9}']
+            .iter()
+            .filter_map(|c| cmap.map(*c as u32).map(|g| (*c, g)))
+            .collect();
+        assert_eq!(map.len(), 3, "{}", name);
+
+        let subset = subset_font(&data, 0, &map).unwrap();
+        let (_, orig_tables) = read_tables(&data, 0).unwrap();
+        let (_, new_tables) = read_tables(&subset, 0).unwrap();
+
+        for (tag, orig) in &orig_tables {
+            let new = new_tables.get(tag);
+
+            if tag == b"cmap" {
+                assert!(new.is_some());
+            } else if DROPPED_TABLES.contains(&tag) {
+                assert!(new.is_none());
+            } else if tag == b"head" {
+                let new = new.unwrap();
+                assert_eq!(new.len(), orig.len());
+                assert_eq!(new[..8], orig[..8]);
+                assert_eq!(new[12..50], orig[12..50]);
+                assert_eq!(new[52..], orig[52..]);
+            } else if tag != b"glyf" && tag != b"loca" && tag != b"CFF " {
+                assert_eq!(new, Some(orig), "{}: table {:?}", name, tag);
+            }
+        }
+
+        // The checksum of the HEAD table is computed with a zero checksum
+        // adjustment.
+        let n_tables = BigEndian::read_u16(&subset[4..6]) as usize;
+        assert_eq!(n_tables, new_tables.len());
+
+        for i in 0..n_tables {
+            let record = &subset[12 + 16 * i..28 + 16 * i];
+            let mut table = new_tables[&record[0..4]].to_vec();
+
+            if &record[0..4] == b"head" {
+                BigEndian::write_u32(&mut table[8..12], 0);
+            }
+
+            assert_eq!(
+                BigEndian::read_u32(&record[4..8]),
+                opentype_checksum(&table)
+            );
+        }
+
+        if let Some(loca) = new_tables.get(b"loca") {
+            let n_glyphs = BigEndian::read_u16(&new_tables[b"maxp"][4..6]) as usize;
+            let offsets: Vec<_> = loca.chunks(4).map(BigEndian::read_u32).collect();
+            assert_eq!(offsets.len(), n_glyphs + 1);
+            assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+            assert_eq!(*offsets.last().unwrap() as usize, new_tables[b"glyf"].len());
+        }
+
+        let lib = Library::new().unwrap();
+        let mut orig_face = Face::new(&lib, data.clone(), 0).unwrap();
+        let mut new_face = Face::new(&lib, subset, 0).unwrap();
+
+        for (_, glyph) in &map {
+            let orig = orig_face.outline(*glyph as u32).unwrap();
+            assert!(!orig.path.is_empty());
+            assert_eq!(new_face.outline(*glyph as u32).unwrap().path, orig.path);
+        }
+    }
+
+    #[test]
+    fn cff_font_tables() {
+        check_tables("lmroman12-regular.otf");
+    }
+
+    #[test]
+    fn truetype_font_tables() {
+        check_tables("LinLibertine_R_G.ttf");
+    }
+
+    #[test]
+    fn subset_cff_font() {
+        check_subset("lmroman12-regular.otf");
+    }
+
+    #[test]
+    fn subset_truetype_font() {
+        check_subset("LinLibertine_R_G.ttf");
+    }
+
+    #[test]
+    fn cff_index_roundtrip() {
+        let items: &[&[u8]] = &[b"abc", b"", b"de"];
+        let buf = write_index(items);
+        assert_eq!(buf.len(), index_len(3, 5));
+
+        let (ranges, end) = read_index(&buf, 0).unwrap();
+        assert_eq!(end, buf.len());
+        let read: Vec<_> = ranges.into_iter().map(|r| &buf[r]).collect();
+        assert_eq!(read, items);
+    }
+}
@@ -0,0 +1,269 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Accumulating the content of a single SVG page.
+//!
+//! All positions are in TeX units (1/65536 of a TeX point). When we emit the
+//! SVG, we convert them to points, so that one SVG user unit is one TeX point.
+
+use std::{collections::HashSet, fmt::Write};
+
+/// A single glyph placed on the page.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct PlacedChar {
+    pub c: char,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// The content of an SVG page under construction.
+#[derive(Debug, Default)]
+pub(crate) struct SvgPage {
+    body: String,
+
+    /// Paths defined for reuse, and their IDs.
+    defs: String,
+    defined: HashSet<String>,
+
+    /// The bounding box of all of the content: `(x0, y0, x1, y1)`, with `y`
+    /// increasing downwards as in TeX.
+    bbox: Option<(i32, i32, i32, i32)>,
+}
+
+impl SvgPage {
+    /// Add a run of text in the font identified by the CSS class `class`.
+    ///
+    /// The run's vertical extent is given by `ascent` and `descent` (the latter
+    /// typically negative), and its horizontal extent by the position of the
+    /// last character plus `last_advance`.
+    pub fn add_text(
+        &mut self,
+        class: &str,
+        chars: &[PlacedChar],
+        last_advance: i32,
+        ascent: i32,
+        descent: i32,
+    ) {
+        let (first, last) = match (chars.first(), chars.last()) {
+            (Some(f), Some(l)) => (f, l),
+            _ => return,
+        };
+
+        let mut xs = String::new();
+        let mut text = String::new();
+
+        for (i, pc) in chars.iter().enumerate() {
+            if i > 0 {
+                xs.push(' ');
+            }
+
+            xs.push_str(&tex_to_pt(pc.x));
+            push_escaped(&mut text, pc.c);
+        }
+
+        let ys = if chars.iter().all(|pc| pc.y == first.y) {
+            tex_to_pt(first.y)
+        } else {
+            chars
+                .iter()
+                .map(|pc| tex_to_pt(pc.y))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        writeln!(
+            self.body,
+            "<text class=\"{class}\" x=\"{xs}\" y=\"{ys}\">{text}</text>"
+        )
+        .unwrap();
+
+        for pc in chars {
+            self.extend_bbox(pc.x, pc.y - ascent, pc.x, pc.y - descent);
+        }
+
+        self.extend_bbox(
+            last.x,
+            last.y - ascent,
+            last.x + last_advance,
+            last.y - descent,
+        );
+    }
+
+    /// Define a path that can be drawn with [`Self::add_use`], unless one with
+    /// the same ID has already been defined.
+    pub fn define_path(&mut self, id: &str, d: &str) {
+        if self.defined.insert(id.to_owned()) {
+            writeln!(self.defs, "<path id=\"{id}\" d=\"{d}\"/>").unwrap();
+        }
+    }
+
+    /// Draw the path `id`, with its origin at `(x, y)`.
+    ///
+    /// The path is scaled by `scale` TeX units per path unit and flipped
+    /// vertically, since glyph outlines have `y` increasing upwards. Its
+    /// extent, in path units, is given by `bbox`.
+    pub fn add_use(&mut self, id: &str, x: i32, y: i32, scale: f64, bbox: (i32, i32, i32, i32)) {
+        let s = trim_number(format!("{:.6}", scale / 65536.));
+
+        writeln!(
+            self.body,
+            "<use xlink:href=\"#{id}\" transform=\"matrix({s} 0 0 -{s} {} {})\"/>",
+            tex_to_pt(x),
+            tex_to_pt(y)
+        )
+        .unwrap();
+
+        let scaled = |v: i32| (v as f64 * scale) as i32;
+        let (x0, y0, x1, y1) = bbox;
+        self.extend_bbox(
+            x + scaled(x0),
+            y - scaled(y1),
+            x + scaled(x1),
+            y - scaled(y0),
+        );
+    }
+
+    /// Add a rule whose bottom-left corner is at `(x, y)`.
+    pub fn add_rule(&mut self, x: i32, y: i32, height: i32, width: i32) {
+        if height <= 0 || width <= 0 {
+            return;
+        }
+
+        writeln!(
+            self.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+            tex_to_pt(x),
+            tex_to_pt(y - height),
+            tex_to_pt(width),
+            tex_to_pt(height)
+        )
+        .unwrap();
+
+        self.extend_bbox(x, y - height, x + width, y);
+    }
+
+    fn extend_bbox(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+        self.bbox = Some(match self.bbox {
+            None => (x0, y0, x1, y1),
+            Some((bx0, by0, bx1, by1)) => (bx0.min(x0), by0.min(y0), bx1.max(x1), by1.max(y1)),
+        });
+    }
+
+    /// Render the complete SVG document, with `style` as the contents of its
+    /// stylesheet.
+    pub fn finish(self, style: &str) -> String {
+        let (x0, y0, x1, y1) = self.bbox.unwrap_or_default();
+        let width = tex_to_pt(x1 - x0);
+        let height = tex_to_pt(y1 - y0);
+
+        let defs = if self.defs.is_empty() {
+            self.defs
+        } else {
+            format!("<defs>\n{}</defs>\n", self.defs)
+        };
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" xml:space=\"preserve\" \
+             width=\"{width}pt\" height=\"{height}pt\" viewBox=\"{} {} {width} {height}\">\n\
+             <style>\n{style}</style>\n\
+             {defs}{}</svg>\n",
+            tex_to_pt(x0),
+            tex_to_pt(y0),
+            self.body
+        )
+    }
+}
+
+/// Format a TeX dimension as a number of points, trimming needless digits.
+pub(crate) fn tex_to_pt(v: i32) -> String {
+    trim_number(format!("{:.3}", v as f64 / 65536.))
+}
+
+fn trim_number(mut s: String) -> String {
+    if s.contains('.') {
+        while s.ends_with('0') {
+            s.pop();
+        }
+
+        if s.ends_with('.') {
+            s.pop();
+        }
+    }
+
+    if s == "-0" {
+        s.remove(0);
+    }
+
+    s
+}
+
+fn push_escaped(buf: &mut String, c: char) {
+    match c {
+        '&' => buf.push_str("&amp;"),
+        '<' => buf.push_str("&lt;"),
+        '>' => buf.push_str("&gt;"),
+        c => buf.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points() {
+        assert_eq!(tex_to_pt(0), "0");
+        assert_eq!(tex_to_pt(65536), "1");
+        assert_eq!(tex_to_pt(-32768), "-0.5");
+        assert_eq!(tex_to_pt(10 * 65536 + 6554), "10.1");
+        assert_eq!(tex_to_pt(-1), "0");
+    }
+
+    #[test]
+    fn rule_bbox() {
+        let mut page = SvgPage::default();
+        page.add_rule(65536, 10 * 65536, 2 * 65536, 3 * 65536);
+        page.add_rule(0, 0, 0, 65536); // not typeset
+        let svg = page.finish("");
+        assert!(svg.contains("<rect x=\"1\" y=\"8\" width=\"3\" height=\"2\"/>"));
+        assert!(svg.contains("viewBox=\"1 8 3 2\""));
+    }
+
+    #[test]
+    fn text_escaping() {
+        let mut page = SvgPage::default();
+        let chars = [
+            PlacedChar {
+                c: 'a',
+                x: 0,
+                y: 65536,
+            },
+            PlacedChar {
+                c: '<',
+                x: 65536,
+                y: 65536,
+            },
+        ];
+        page.add_text("f1", &chars, 65536, 65536, 0);
+        let svg = page.finish("");
+        assert!(svg.contains("<text class=\"f1\" x=\"0 1\" y=\"1\">a&lt;</text>"));
+        assert!(svg.contains("viewBox=\"0 0 2 1\""));
+    }
+
+    #[test]
+    fn glyph_paths() {
+        let mut page = SvgPage::default();
+        page.define_path("g0-1", "M0 0L10 0L10 10Z");
+        page.define_path("g0-1", "M0 0L10 0L10 10Z");
+        page.add_use("g0-1", 65536, 2 * 65536, 655.36, (0, -10, 100, 100));
+        let svg = page.finish("");
+        assert_eq!(svg.matches("<path id=\"g0-1\"").count(), 1);
+        assert!(svg.contains("<defs>\n<path id=\"g0-1\" d=\"M0 0L10 0L10 10Z\"/>\n</defs>"));
+        assert!(
+            svg.contains("<use xlink:href=\"#g0-1\" transform=\"matrix(0.01 0 0 -0.01 1 2)\"/>")
+        );
+        assert!(svg.contains("viewBox=\"1 1 1 1.1\""));
+    }
+}
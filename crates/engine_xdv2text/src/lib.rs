@@ -0,0 +1,242 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Extract plain text from Tectonic’s XDV output.
+//!
+//! The text of each page of the XDV file is reconstructed in the order in
+//! which it was typeset, which for most documents is reading order. XDV files
+//! don't record spaces or line breaks, so we infer them from the positions of
+//! the glyphs, using the same heuristics as the HTML output. The result is
+//! meant for uses like search indexing, not faithful reproduction of the
+//! layout.
+//!
+//! Text in native (OpenType) fonts is handled best, since XeTeX records the
+//! original text and the glyph positions. For text in traditional TFM-based
+//! fonts, we assume the OT1 encoding of TeX’s standard text fonts, and since
+//! the positions of the characters aren't known without loading the font
+//! metrics, every run of characters is treated as a separate word.
+//!
+//! Rather than using this crate directly you should probably use the main
+//! [`tectonic`] crate, which runs this engine automatically when text output
+//! is requested.
+//!
+//! [`tectonic`]: https://docs.rs/tectonic/
+
+use std::collections::HashMap;
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_status_base::{tt_warning, StatusBackend};
+use tectonic_xdv::{FileType, XdvEvents, XdvParser};
+use tectonic_xdv_support::{font::FontFileData, write_pages, FontFiles, FontInstance};
+
+mod page;
+
+use self::page::{ot1_text, PageText};
+
+/// A struct for invoking the XDV-to-text engine.
+///
+/// This struct has a fairly straightforward "builder" interface: you create it,
+/// apply any settings that you wish, and eventually run the
+/// [`process()`](Self::process) method.
+#[derive(Debug, Default)]
+pub struct Xdv2TextEngine {}
+
+impl Xdv2TextEngine {
+    /// Extract the text of an XDV file.
+    ///
+    /// The file named `xdv` is read from the I/O stack. The output is UTF-8
+    /// text. If the document has a single page, it is written to
+    /// `<output_stem>.txt`; otherwise, the text of page *N* is written to
+    /// `<output_stem>-<N>.txt`, counting from 1.
+    pub fn process(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        xdv: &str,
+        output_stem: &str,
+    ) -> Result<()> {
+        let mut input = hooks.io().input_open_name(xdv, status).must_exist()?;
+
+        let pages = {
+            let state = EngineState::new(hooks, status);
+            let state = XdvParser::process_with_seeks(&mut input, state)?;
+            state.finished()
+        };
+
+        let (name, digest_opt) = input.into_name_digest();
+        hooks.event_input_closed(name, digest_opt, status);
+
+        write_pages(hooks, status, output_stem, "txt", &pages)
+    }
+}
+
+struct EngineState<'a> {
+    hooks: &'a mut dyn DriverHooks,
+    status: &'a mut dyn StatusBackend,
+
+    font_files: FontFiles<FontFileData>,
+
+    fonts: HashMap<i32, FontInstance>,
+    cur_page: Option<PageText>,
+    finished_pages: Vec<String>,
+    n_unmapped_glyphs: usize,
+}
+
+impl<'a> EngineState<'a> {
+    fn new(hooks: &'a mut dyn DriverHooks, status: &'a mut dyn StatusBackend) -> Self {
+        EngineState {
+            hooks,
+            status,
+            font_files: FontFiles::default(),
+            fonts: HashMap::new(),
+            cur_page: None,
+            finished_pages: Vec::new(),
+            n_unmapped_glyphs: 0,
+        }
+    }
+
+    fn finished(mut self) -> Vec<String> {
+        self.finish_page();
+
+        if self.n_unmapped_glyphs > 0 {
+            tt_warning!(
+                self.status,
+                "{} glyph(s) could not be mapped to Unicode characters and were omitted from the text output",
+                self.n_unmapped_glyphs
+            );
+        }
+
+        self.finished_pages
+    }
+
+    fn finish_page(&mut self) {
+        if let Some(page) = self.cur_page.take() {
+            self.finished_pages.push(page.finish());
+        }
+    }
+
+    fn page(&mut self) -> &mut PageText {
+        self.cur_page.get_or_insert_with(PageText::default)
+    }
+}
+
+impl<'a> XdvEvents for EngineState<'a> {
+    type Error = Error;
+
+    fn handle_header(&mut self, filetype: FileType, _comment: &[u8]) -> Result<()> {
+        if filetype != FileType::Xdv {
+            bail!("file should be XDV format; got {}", filetype);
+        }
+
+        Ok(())
+    }
+
+    fn handle_begin_page(&mut self, _counters: &[i32], _previous_bop: i32) -> Result<()> {
+        self.finish_page();
+        self.cur_page = Some(PageText::default());
+        Ok(())
+    }
+
+    fn handle_char_run(&mut self, _font_num: i32, chars: &[i32]) -> Result<()> {
+        let text: String = chars.iter().filter_map(|c| ot1_text(*c)).collect();
+        self.page().push_unpositioned(&text);
+        Ok(())
+    }
+
+    fn handle_glyph_run(
+        &mut self,
+        font_num: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        let fi = a_ok_or!(
+            self.fonts.get(&font_num);
+            ["reference to undefined native font #{}", font_num]
+        );
+        let ff = self.font_files.get(fi.file_idx);
+        let space_width = ff.space_width(fi.size);
+        let page = self.cur_page.get_or_insert_with(PageText::default);
+        let mut buf = [0u8; 4];
+
+        for (i, &glyph) in glyphs.iter().enumerate() {
+            if let Some(c) = ff.lookup_char(glyph) {
+                page.push_positioned(
+                    c.encode_utf8(&mut buf),
+                    x[i],
+                    y[i],
+                    x[i] + ff.advance(glyph, fi.size),
+                    space_width,
+                );
+            } else {
+                self.n_unmapped_glyphs += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_text_and_glyphs(
+        &mut self,
+        font_num: i32,
+        text: &str,
+        _width: i32,
+        glyphs: &[u16],
+        x: &[i32],
+        y: &[i32],
+    ) -> Result<()> {
+        if glyphs.is_empty() {
+            return Ok(());
+        }
+
+        let fi = a_ok_or!(
+            self.fonts.get(&font_num);
+            ["reference to undefined native font #{}", font_num]
+        );
+        let ff = self.font_files.get(fi.file_idx);
+        let last = glyphs.len() - 1;
+        let x1 = x[last] + ff.advance(glyphs[last], fi.size);
+        let space_width = ff.space_width(fi.size);
+
+        self.cur_page
+            .get_or_insert_with(PageText::default)
+            .push_positioned(text, x[0], y[0], x1, space_width);
+        Ok(())
+    }
+
+    fn handle_define_native_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        size: i32,
+        face_index: u32,
+        _color_rgba: Option<u32>,
+        _extend: Option<u32>,
+        _slant: Option<u32>,
+        _embolden: Option<u32>,
+    ) -> Result<()> {
+        if self.fonts.contains_key(&font_num) {
+            return Ok(());
+        }
+
+        let file_idx = self.font_files.load(
+            self.hooks,
+            self.status,
+            name,
+            face_index,
+            FontFileData::from_opentype,
+        )?;
+
+        self.fonts.insert(
+            font_num,
+            FontInstance {
+                file_idx,
+                size,
+                color_rgba: None,
+            },
+        );
+        Ok(())
+    }
+}
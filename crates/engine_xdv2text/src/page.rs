@@ -0,0 +1,135 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Accumulating the text of a single page.
+
+use tectonic_engine_spx2html::spacing::SpaceTracker;
+
+/// The text of a page under construction.
+#[derive(Debug, Default)]
+pub(crate) struct PageText {
+    text: String,
+    spacing: SpaceTracker,
+
+    /// The baseline of the most recently added text.
+    last_y: i32,
+}
+
+impl PageText {
+    /// Add some positioned text.
+    ///
+    /// The text starts at `(x0, y0)` and ends at `x1`. `space_width` is the
+    /// width of a space in the text's font, if known. If the text starts to
+    /// the left of where the previous text ended, on a lower baseline, we
+    /// assume that a new line has started. Otherwise, we use the same
+    /// heuristics as the HTML output to decide whether a space is needed.
+    pub fn push_positioned(
+        &mut self,
+        text: &str,
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        space_width: Option<i32>,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+
+        if !self.text.is_empty() {
+            if x0 < self.spacing.last_x() && y0 > self.last_y {
+                self.text.push('\n');
+            } else if self.spacing.is_space_needed(x0, space_width) {
+                self.text.push(' ');
+            }
+        }
+
+        self.text.push_str(text);
+        self.spacing.update(x1, space_width);
+        self.last_y = y0;
+    }
+
+    /// Add a run of text whose position is unknown. We assume that it is a
+    /// separate word from whatever came before.
+    pub fn push_unpositioned(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        if !self.text.is_empty() && !self.text.ends_with(char::is_whitespace) {
+            self.text.push(' ');
+        }
+
+        self.text.push_str(text);
+    }
+
+    /// Get the final text of the page, terminated with a newline unless it is
+    /// empty.
+    pub fn finish(mut self) -> String {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+
+        self.text
+    }
+}
+
+/// Map a character code in a TFM-based font to text, assuming the OT1
+/// encoding used by TeX’s traditional text fonts.
+pub(crate) fn ot1_text(code: i32) -> Option<&'static str> {
+    const LOW: [&str; 32] = [
+        "Γ", "Δ", "Θ", "Λ", "Ξ", "Π", "Σ", "Υ", "Φ", "Ψ", "Ω", "ff", "fi", "fl", "ffi", "ffl", "ı",
+        "ȷ", "`", "´", "ˇ", "˘", "¯", "˚", "¸", "ß", "æ", "œ", "ø", "Æ", "Œ", "Ø",
+    ];
+    const ASCII: &str = "!”#$%&’()*+,-./0123456789:;¡=¿?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[“]ˆ˙‘abcdefghijklmnopqrstuvwxyz–—˝˜¨";
+
+    match code {
+        0..=31 => Some(LOW[code as usize]),
+        // Code 32 is the stroke used to build up "ł", which has no meaning on
+        // its own.
+        33..=127 => {
+            let idx = (code - 33) as usize;
+            ASCII
+                .char_indices()
+                .nth(idx)
+                .map(|(start, c)| &ASCII[start..start + c.len_utf8()])
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PT: i32 = 65536;
+
+    #[test]
+    fn spaces_and_lines() {
+        let mut page = PageText::default();
+        page.push_positioned("Hello", 0, 10 * PT, 20 * PT, Some(3 * PT));
+        page.push_positioned("world", 23 * PT, 10 * PT, 45 * PT, Some(3 * PT));
+        page.push_positioned(".", 45 * PT, 10 * PT, 46 * PT, Some(3 * PT));
+        page.push_positioned("Next", 0, 22 * PT, 18 * PT, Some(3 * PT));
+        page.push_positioned("2", 18 * PT, 20 * PT, 19 * PT, Some(3 * PT));
+        assert_eq!(page.finish(), "Hello world.\nNext2\n");
+    }
+
+    #[test]
+    fn unpositioned() {
+        let mut page = PageText::default();
+        page.push_unpositioned("This");
+        page.push_unpositioned("is");
+        assert_eq!(page.finish(), "This is\n");
+        assert_eq!(PageText::default().finish(), "");
+    }
+
+    #[test]
+    fn ot1() {
+        let text: String = [84, 12, 120, 92, 34, 123]
+            .iter()
+            .filter_map(|c| ot1_text(*c))
+            .collect();
+        assert_eq!(text, "Tfix“”–");
+        assert_eq!(ot1_text(200), None);
+    }
+}
@@ -89,6 +89,11 @@ impl DigestData {
         Self::from(dc)
     }
 
+    /// Get the raw bytes of this digest.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
     /// Given a base path, create a child path from this digest's value. The
     /// child path has a subdirectory from the hex value of the first byte of
     /// the digest, then a name consisting of the rest of the hex data. **The
@@ -15,9 +15,11 @@
 use sha2::Digest;
 use std::{
     borrow::Cow,
+    cell::RefCell,
     fs::File,
     io::{self, Cursor, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    rc::Rc,
 };
 use tectonic_errors::{
     anyhow::{bail, ensure},
@@ -589,6 +591,63 @@ impl<P: IoProvider + ?Sized> IoProvider for Box<P> {
     }
 }
 
+/// A provider shared between several owners, such as a bundle that backs
+/// several processing sessions, can be used like any other provider. Each
+/// method borrows the provider for the duration of the call.
+impl<P: IoProvider + ?Sized> IoProvider for Rc<RefCell<P>> {
+    fn output_open_name(&mut self, name: &str) -> OpenResult<OutputHandle> {
+        self.borrow_mut().output_open_name(name)
+    }
+
+    fn output_open_stdout(&mut self) -> OpenResult<OutputHandle> {
+        self.borrow_mut().output_open_stdout()
+    }
+
+    fn input_open_name(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        self.borrow_mut().input_open_name(name, status)
+    }
+
+    fn input_open_name_with_abspath(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
+        self.borrow_mut().input_open_name_with_abspath(name, status)
+    }
+
+    fn input_open_primary(&mut self, status: &mut dyn StatusBackend) -> OpenResult<InputHandle> {
+        self.borrow_mut().input_open_primary(status)
+    }
+
+    fn input_open_primary_with_abspath(
+        &mut self,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<(InputHandle, Option<PathBuf>)> {
+        self.borrow_mut().input_open_primary_with_abspath(status)
+    }
+
+    fn input_open_format(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> OpenResult<InputHandle> {
+        self.borrow_mut().input_open_format(name, status)
+    }
+
+    fn write_format(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        status: &mut dyn StatusBackend,
+    ) -> Result<()> {
+        self.borrow_mut().write_format(name, data, status)
+    }
+}
+
 // Some generically helpful InputFeatures impls
 
 impl InputFeatures for Cursor<Vec<u8>> {
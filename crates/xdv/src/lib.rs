@@ -148,6 +148,20 @@ pub trait XdvEvents {
         Ok(())
     }
 
+    /// Handle a single character set in a traditional (TFM-based) font at the
+    /// position `(x, y)`.
+    ///
+    /// This is called for every character, before the run containing it is
+    /// passed to `handle_char_run`. The return value is the width of the
+    /// character in TeX units: XDV files don't record it, so the parser relies
+    /// on this to keep track of the horizontal position. The default
+    /// implementation returns zero, in which case the positions of everything
+    /// following the character on the same line are off by its width.
+    #[allow(unused)]
+    fn handle_char(&mut self, font_num: i32, c: i32, x: i32, y: i32) -> Result<i32, Self::Error> {
+        Ok(0)
+    }
+
     /// Handle a sequence of glyphs.
     #[allow(unused)]
     fn handle_glyph_run(
@@ -174,6 +188,21 @@ pub trait XdvEvents {
         Ok(())
     }
 
+    /// Handle the definition of a traditional (TFM-based) font
+    ///
+    /// The `scale` and `design_size` are in TeX units.
+    #[allow(unused)]
+    fn handle_define_font(
+        &mut self,
+        name: &str,
+        font_num: i32,
+        checksum: u32,
+        scale: i32,
+        design_size: i32,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
     /// Handle the definition of a native font
     #[allow(unused)]
     #[allow(clippy::too_many_arguments)]
@@ -629,26 +658,20 @@ impl<T: XdvEvents> XdvParser<T> {
             return Err(XdvError::IllegalOpcode(opcode, cursor.global_offset()).into_internal());
         }
 
-        let _font_num = cursor.get_compact_i32_smpos(opcode - Opcode::DefineFont1 as u8)?;
-        let _checksum = cursor.get_u32()?;
-        let _scale_factor = cursor.get_u32()?;
-        let _design_size = cursor.get_u32()?;
+        let font_num = cursor.get_compact_i32_smpos(opcode - Opcode::DefineFont1 as u8)?;
+        let checksum = cursor.get_u32()?;
+        let scale = cursor.get_i32()?;
+        let design_size = cursor.get_i32()?;
         let area_len = cursor.get_u8()?;
         let name_len = cursor.get_u8()?;
+        let offset = cursor.global_offset();
+        let name_str =
+            std::str::from_utf8(cursor.get_slice(area_len as usize + name_len as usize)?)
+                .map_err(|_| XdvError::FromUTF8(offset).into_internal())?
+                .to_owned();
 
-        // TODO: figure out what to do with these. In Tectonic's context,
-        // non-"native" font definitions are a bad sign, since they correspond
-        // to fonts that we wouldn't be able to express in HTML. But note that
-        // this crate should support generic XDV decoding, not necessarily
-        // targeting HTML, as best it can.
-
-        use std::str::from_utf8;
-        let _area_str = from_utf8(cursor.get_slice(area_len as usize)?)
-            .unwrap()
-            .to_owned();
-        let _name_str = from_utf8(cursor.get_slice(name_len as usize)?)
-            .unwrap()
-            .to_owned();
+        self.events
+            .handle_define_font(&name_str, font_num, checksum, scale, design_size)?;
         Ok(())
     }
 
@@ -938,9 +961,8 @@ impl<T: XdvEvents> XdvParser<T> {
             return Err(XdvError::IllegalOpcode(opcode, cursor.global_offset()).into_internal());
         }
 
-        let char_num = opcode - Opcode::SetCharNumber0 as u8;
-        self.cur_char_run.push(i32::from(char_num));
-        Ok(())
+        let char_num = i32::from(opcode - Opcode::SetCharNumber0 as u8);
+        self.set_char(char_num)
     }
 
     /// This variant takes an argument that encodes the character number.
@@ -950,6 +972,15 @@ impl<T: XdvEvents> XdvParser<T> {
         }
 
         let char_num = cursor.get_compact_i32_smpos(opcode - Opcode::SetChar1 as u8)?;
+        self.set_char(char_num)
+    }
+
+    fn set_char(&mut self, char_num: i32) -> InternalResult<(), T::Error> {
+        let state = self.stack.last_mut().unwrap();
+        let width = self
+            .events
+            .handle_char(self.cur_font_num, char_num, state.h, state.v)?;
+        state.h += width;
         self.cur_char_run.push(char_num);
         Ok(())
     }
@@ -1419,3 +1450,116 @@ enum NativeFontFlags {
     Slant = 0x2000,
     Embolden = 0x4000,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder {
+        fonts: Vec<(String, i32, i32)>,
+        chars: Vec<(i32, i32, i32)>,
+        rules: Vec<(i32, i32)>,
+    }
+
+    impl XdvEvents for Recorder {
+        type Error = XdvError;
+
+        fn handle_define_font(
+            &mut self,
+            name: &str,
+            font_num: i32,
+            _checksum: u32,
+            scale: i32,
+            _design_size: i32,
+        ) -> Result<(), Self::Error> {
+            self.fonts.push((name.to_owned(), font_num, scale));
+            Ok(())
+        }
+
+        fn handle_char(
+            &mut self,
+            font_num: i32,
+            c: i32,
+            x: i32,
+            y: i32,
+        ) -> Result<i32, Self::Error> {
+            self.chars.push((font_num, c, x));
+            assert_eq!(y, 0);
+            Ok(100 * c)
+        }
+
+        fn handle_rule(
+            &mut self,
+            x: i32,
+            y: i32,
+            _height: i32,
+            _width: i32,
+        ) -> Result<(), Self::Error> {
+            self.rules.push((x, y));
+            Ok(())
+        }
+    }
+
+    fn push_u32(buf: &mut Vec<u8>, v: u32) {
+        buf.extend_from_slice(&v.to_be_bytes());
+    }
+
+    /// Build a one-page XDV file that sets the characters 1, 2 and 200 in a
+    /// TFM font, followed by a rule.
+    fn one_page() -> Vec<u8> {
+        let mut buf = vec![Opcode::Preamble as u8, IdByte::Xdv as u8];
+        push_u32(&mut buf, 25_400_000);
+        push_u32(&mut buf, 473_628_672);
+        push_u32(&mut buf, 1000);
+        buf.push(0);
+
+        buf.push(Opcode::BeginningOfPage as u8);
+        for _ in 0..10 {
+            push_u32(&mut buf, 0);
+        }
+        push_u32(&mut buf, 0xFFFF_FFFF);
+
+        buf.extend_from_slice(&[Opcode::DefineFont1 as u8, 3]);
+        push_u32(&mut buf, 0x1234_5678);
+        push_u32(&mut buf, 655_360);
+        push_u32(&mut buf, 655_360);
+        buf.extend_from_slice(&[0, 5]);
+        buf.extend_from_slice(b"cmr10");
+
+        buf.push(Opcode::SetFontNumber0 as u8 + 3);
+        buf.push(Opcode::SetCharNumber0 as u8 + 1);
+        buf.push(Opcode::SetCharNumber0 as u8 + 2);
+        buf.extend_from_slice(&[Opcode::SetChar1 as u8, 200]);
+        buf.push(Opcode::SetRule as u8);
+        push_u32(&mut buf, 10);
+        push_u32(&mut buf, 10);
+        buf.push(Opcode::EndOfPage as u8);
+
+        buf.push(Opcode::Postamble as u8);
+        push_u32(&mut buf, 0);
+        push_u32(&mut buf, 25_400_000);
+        push_u32(&mut buf, 473_628_672);
+        push_u32(&mut buf, 1000);
+        push_u32(&mut buf, 0);
+        push_u32(&mut buf, 0);
+        buf.extend_from_slice(&[0, 1, 0, 1]);
+
+        buf.push(Opcode::DoublePostamble as u8);
+        push_u32(&mut buf, 0);
+        buf.push(IdByte::Xdv as u8);
+        buf.extend_from_slice(&[0xDF; 4]);
+        buf
+    }
+
+    #[test]
+    fn char_widths() {
+        let mut parser = XdvParser::new(Recorder::default());
+        parser.parse(&one_page()).unwrap();
+        let events = parser.finish().unwrap();
+
+        assert_eq!(events.fonts, vec![("cmr10".to_owned(), 3, 655_360)]);
+        assert_eq!(events.chars, vec![(3, 1, 0), (3, 2, 100), (3, 200, 300)]);
+        assert_eq!(events.rules, vec![(20_300, 0)]);
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Data pertaining to the OpenType font files referenced by an XDV file.
+//!
+//! XDV glyph runs are defined in terms of glyph IDs, so to turn them into text
+//! we need a reverse character map. We also need glyph advances and the font
+//! ascent and descent to figure out the extents of the text, and the width of
+//! the space character to figure out where the spaces go.
+
+use pinot::{FontDataRef, TableProvider};
+use std::collections::HashMap;
+use tectonic_errors::prelude::*;
+
+/// A numerical identifier of a glyph in a font.
+pub type GlyphId = u16;
+
+/// Information extracted from an OpenType font file.
+#[derive(Debug)]
+pub struct FontFileData {
+    /// The complete font data.
+    buffer: Vec<u8>,
+
+    /// Map from glyph ID to the first Unicode character that produces it.
+    gmap: HashMap<GlyphId, char>,
+
+    /// The glyph for the basic space character, or zero (typically .notdef) if
+    /// it can't be found.
+    space_glyph: GlyphId,
+
+    units_per_em: u16,
+
+    /// The advance width of each glyph, in font units.
+    advances: Vec<u16>,
+
+    ascender: i16,
+
+    /// This value is typically negative.
+    descender: i16,
+}
+
+impl FontFileData {
+    /// Load glyph data from OpenType font data.
+    ///
+    /// We take ownership of the font data that we're given.
+    pub fn from_opentype(buffer: Vec<u8>, face_index: u32) -> Result<Self> {
+        let font_data = a_ok_or!(
+            FontDataRef::new(&buffer);
+            ["unable to parse buffer as OpenType font"]
+        );
+
+        let font = a_ok_or!(
+            font_data.get(face_index);
+            ["unable to load face #{} in the OpenType font", face_index]
+        );
+
+        let head = a_ok_or!(
+            font.head();
+            ["unable to parse OpenType font: missing/invalid HEAD table"]
+        );
+
+        let units_per_em = head.units_per_em();
+
+        let cmap = a_ok_or!(
+            font.cmap();
+            ["unable to parse OpenType font: missing/invalid CMAP table"]
+        );
+
+        let mut gmap = HashMap::new();
+        let mut space_glyph = 0;
+
+        for usv in valid_usvs() {
+            let c = char::from_u32(usv).unwrap();
+
+            let gidx = match cmap.map(usv) {
+                Some(g) if g != 0 => g,
+                _ => {
+                    continue;
+                }
+            };
+
+            if c == ' ' {
+                space_glyph = gidx;
+            }
+
+            gmap.entry(gidx).or_insert(c);
+        }
+
+        let hhea = a_ok_or!(
+            font.hhea();
+            ["unable to parse OpenType font: missing/invalid HHEA table"]
+        );
+
+        let ascender = hhea.ascender();
+        let descender = hhea.descender();
+
+        let hmtx = a_ok_or!(
+            font.hmtx();
+            ["unable to parse OpenType font: missing/invalid HMTX table"]
+        );
+
+        let mut advances: Vec<_> = hmtx.hmetrics().iter().map(|hm| hm.advance_width).collect();
+
+        // Glyphs past the end of the hmetrics array share the advance of the
+        // last entry.
+        let last_advance = advances.last().copied().unwrap_or_default();
+        advances.resize(advances.len() + hmtx.lsbs().len(), last_advance);
+
+        Ok(FontFileData {
+            buffer,
+            gmap,
+            space_glyph,
+            units_per_em,
+            advances,
+            ascender,
+            descender,
+        })
+    }
+
+    /// Get the raw font data.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer[..]
+    }
+
+    /// Get the Unicode character that maps to the given glyph, if any.
+    pub fn lookup_char(&self, glyph: GlyphId) -> Option<char> {
+        self.gmap.get(&glyph).copied()
+    }
+
+    /// Get the advance width of a glyph, in TeX units, for a font of the
+    /// given size in TeX units.
+    pub fn advance(&self, glyph: GlyphId, tex_size: i32) -> i32 {
+        let adv = self.advances.get(glyph as usize).copied().unwrap_or(0);
+        self.to_tex(adv as f64, tex_size)
+    }
+
+    /// Get the width of the space character as a TeX size.
+    pub fn space_width(&self, tex_size: i32) -> Option<i32> {
+        if self.space_glyph == 0 {
+            None
+        } else {
+            Some(self.advance(self.space_glyph, tex_size))
+        }
+    }
+
+    /// Get the ascent and descent of the font, in TeX units, for a font of the
+    /// given size in TeX units. The descent is typically negative.
+    pub fn ascent_descent(&self, tex_size: i32) -> (i32, i32) {
+        (
+            self.to_tex(self.ascender as f64, tex_size),
+            self.to_tex(self.descender as f64, tex_size),
+        )
+    }
+
+    fn to_tex(&self, funits: f64, tex_size: i32) -> i32 {
+        // Float-to-int casts saturate, which is a fine fallback for
+        // pathological inputs.
+        (funits * tex_size as f64 / self.units_per_em.max(1) as f64) as i32
+    }
+}
+
+fn valid_usvs() -> impl Iterator<Item = u32> {
+    (0..0xD800).chain(0xE000..0x11_0000)
+}
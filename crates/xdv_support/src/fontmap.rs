@@ -0,0 +1,182 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Parsing font map and encoding files.
+//!
+//! A TFM file only provides metrics. To draw the characters of a traditional
+//! font, we look it up in the `pdftex.map` font map, just as `xdvipdfmx` does,
+//! to find the Type 1 (or OpenType) font file with the glyph outlines, and
+//! possibly an encoding file that maps character codes to glyph names.
+
+use std::collections::HashMap;
+
+/// The entry for one TFM font in a font map.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FontMapEntry {
+    /// The name of the font file containing the glyphs, if the entry gives
+    /// one.
+    pub font_file: Option<String>,
+
+    /// The name of the encoding file, if the entry gives one. If not, the
+    /// font's built-in encoding should be used.
+    pub encoding_file: Option<String>,
+}
+
+/// A parsed font map, in the format used by `pdftex.map`.
+#[derive(Clone, Debug, Default)]
+pub struct FontMap {
+    entries: HashMap<String, FontMapEntry>,
+}
+
+impl FontMap {
+    /// Parse the text of a font map file.
+    ///
+    /// Each non-comment line names a TFM font, followed by an optional
+    /// PostScript font name, optional quoted PostScript instructions, and
+    /// file names prefixed by `<`, `<<`, or `<[`. Files with an `.enc`
+    /// extension are encodings. Lines that we don't understand are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut entries = HashMap::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with(['%', '#', '*', ';']) {
+                continue;
+            }
+
+            let mut tokens = Tokens(line);
+            let tfm_name = match tokens.next() {
+                Some(t) => t.to_owned(),
+                None => continue,
+            };
+
+            let mut entry = FontMapEntry::default();
+
+            while let Some(tok) = tokens.next() {
+                let file = match tok.strip_prefix('<') {
+                    Some(f) => f.trim_start_matches(['<', '[']),
+                    None => continue,
+                };
+
+                // The file name can be separated from the `<` by spaces.
+                let file = if file.is_empty() {
+                    match tokens.next() {
+                        Some(f) => f,
+                        None => break,
+                    }
+                } else {
+                    file
+                };
+
+                if file.ends_with(".enc") {
+                    entry.encoding_file = Some(file.to_owned());
+                } else {
+                    entry.font_file = Some(file.to_owned());
+                }
+            }
+
+            // As in other map readers, the first entry for a font wins.
+            entries.entry(tfm_name).or_insert(entry);
+        }
+
+        FontMap { entries }
+    }
+
+    /// Look up the entry for a TFM font.
+    pub fn get(&self, tfm_name: &str) -> Option<&FontMapEntry> {
+        self.entries.get(tfm_name)
+    }
+}
+
+/// An iterator over the whitespace-separated tokens of a map line, treating
+/// double-quoted strings as single tokens.
+struct Tokens<'a>(&'a str);
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.0.trim_start();
+
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = if let Some(quoted) = rest.strip_prefix('"') {
+            quoted.find('"').map(|i| i + 2).unwrap_or(rest.len())
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+
+        let (tok, rest) = rest.split_at(end);
+        self.0 = rest;
+        Some(tok)
+    }
+}
+
+/// Parse a PostScript encoding vector file, returning the glyph name for each
+/// character code.
+///
+/// These files look like `/Name [ /glyph0 /glyph1 ... ] def`, with `%`
+/// comments.
+pub fn parse_encoding(text: &str) -> Vec<String> {
+    let code: String = text
+        .lines()
+        .map(|line| line.split('%').next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let vector = code
+        .split_once('[')
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(vector, _)| vector)
+        .unwrap_or_default();
+
+    vector
+        .split_whitespace()
+        .filter_map(|tok| tok.strip_prefix('/'))
+        .map(|name| name.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_lines() {
+        let map = FontMap::parse(
+            "% comment\n\
+             cmr10 CMR10 <cmr10.pfb\n\
+             ec-lmr10 LMRoman10-Regular \"enclmec ReEncodeFont\" <lm-ec.enc <lmr10.pfb\n\
+             foo Foo \"0.167 SlantFont\" < foo.pfb\n\
+             cmr10 Other <other.pfb\n\
+             bare\n",
+        );
+
+        assert_eq!(
+            map.get("cmr10").unwrap().font_file.as_deref(),
+            Some("cmr10.pfb")
+        );
+
+        let e = map.get("ec-lmr10").unwrap();
+        assert_eq!(e.font_file.as_deref(), Some("lmr10.pfb"));
+        assert_eq!(e.encoding_file.as_deref(), Some("lm-ec.enc"));
+
+        assert_eq!(
+            map.get("foo").unwrap().font_file.as_deref(),
+            Some("foo.pfb")
+        );
+        assert_eq!(map.get("bare").unwrap(), &FontMapEntry::default());
+        assert_eq!(map.get("nope"), None);
+    }
+
+    #[test]
+    fn encoding() {
+        let names = parse_encoding(
+            "% an encoding\n/Enc [ % start\n/.notdef /A\n/B] def\n/Other [/C] def\n",
+        );
+        assert_eq!(names, vec![".notdef", "A", "B"]);
+    }
+}
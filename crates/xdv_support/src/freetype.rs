@@ -0,0 +1,495 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! A minimal, safe wrapper around the parts of the FreeType C API that we need
+//! to render glyphs and extract their outlines.
+//!
+//! The FreeType library itself is provided by the `tectonic_bridge_freetype2`
+//! crate, which doesn't provide any Rust bindings, so we declare the handful
+//! of functions and structures that we use here. The structure declarations
+//! only cover the leading fields that we actually access, which is fine since
+//! we only ever look at them through pointers handed to us by FreeType.
+
+use libc::{c_char, c_int, c_long, c_short, c_uchar, c_uint, c_ulong, c_ushort, c_void};
+use std::{ffi::CString, fmt::Write, ptr};
+use tectonic_errors::prelude::*;
+
+#[allow(unused_imports)]
+#[allow(clippy::single_component_path_imports)]
+use tectonic_bridge_freetype2;
+
+type FtError = c_int;
+type FtLibrary = *mut c_void;
+type FtFace = *mut FtFaceRec;
+type FtPos = c_long;
+
+const FT_LOAD_NO_SCALE: i32 = 1;
+const FT_LOAD_RENDER: i32 = 1 << 2;
+const FT_PIXEL_MODE_MONO: c_uchar = 1;
+const FT_PIXEL_MODE_GRAY: c_uchar = 2;
+
+const fn ft_enc_tag(tag: &[u8; 4]) -> c_uint {
+    u32::from_be_bytes(*tag) as c_uint
+}
+
+const FT_ENCODING_ADOBE_CUSTOM: c_uint = ft_enc_tag(b"ADBC");
+const FT_ENCODING_ADOBE_STANDARD: c_uint = ft_enc_tag(b"ADOB");
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct FtVector {
+    x: FtPos,
+    y: FtPos,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct FtOutline {
+    n_contours: c_short,
+    n_points: c_short,
+    points: *mut FtVector,
+    tags: *mut c_char,
+    contours: *mut c_short,
+    flags: c_int,
+}
+
+#[repr(C)]
+struct FtOutlineFuncs {
+    move_to: extern "C" fn(*const FtVector, *mut c_void) -> c_int,
+    line_to: extern "C" fn(*const FtVector, *mut c_void) -> c_int,
+    conic_to: extern "C" fn(*const FtVector, *const FtVector, *mut c_void) -> c_int,
+    cubic_to:
+        extern "C" fn(*const FtVector, *const FtVector, *const FtVector, *mut c_void) -> c_int,
+    shift: c_int,
+    delta: FtPos,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct FtGeneric {
+    data: *mut c_void,
+    finalizer: *mut c_void,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct FtFaceRec {
+    num_faces: c_long,
+    face_index: c_long,
+    face_flags: c_long,
+    style_flags: c_long,
+    num_glyphs: c_long,
+    family_name: *mut c_void,
+    style_name: *mut c_void,
+    num_fixed_sizes: c_int,
+    available_sizes: *mut c_void,
+    num_charmaps: c_int,
+    charmaps: *mut c_void,
+    generic: FtGeneric,
+    bbox: [FtPos; 4],
+    units_per_em: c_ushort,
+    ascender: c_short,
+    descender: c_short,
+    height: c_short,
+    max_advance_width: c_short,
+    max_advance_height: c_short,
+    underline_position: c_short,
+    underline_thickness: c_short,
+    glyph: *mut FtGlyphSlotRec,
+    // ... more fields that we don't use
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct FtBitmap {
+    rows: c_uint,
+    width: c_uint,
+    pitch: c_int,
+    buffer: *mut c_uchar,
+    num_grays: c_ushort,
+    pixel_mode: c_uchar,
+    palette_mode: c_uchar,
+    palette: *mut c_void,
+}
+
+#[allow(dead_code)]
+#[repr(C)]
+struct FtGlyphSlotRec {
+    library: FtLibrary,
+    face: FtFace,
+    next: *mut FtGlyphSlotRec,
+    glyph_index: c_uint,
+    generic: FtGeneric,
+    metrics: [FtPos; 8],
+    linear_hori_advance: c_long,
+    linear_vert_advance: c_long,
+    advance: [FtPos; 2],
+    format: c_uint,
+    bitmap: FtBitmap,
+    bitmap_left: c_int,
+    bitmap_top: c_int,
+    outline: FtOutline,
+    // ... more fields that we don't use
+}
+
+extern "C" {
+    fn FT_Init_FreeType(alibrary: *mut FtLibrary) -> FtError;
+    fn FT_Done_FreeType(library: FtLibrary) -> FtError;
+    fn FT_New_Memory_Face(
+        library: FtLibrary,
+        file_base: *const c_uchar,
+        file_size: c_long,
+        face_index: c_long,
+        aface: *mut FtFace,
+    ) -> FtError;
+    fn FT_Done_Face(face: FtFace) -> FtError;
+    fn FT_Set_Char_Size(
+        face: FtFace,
+        char_width: c_long,
+        char_height: c_long,
+        horz_resolution: c_uint,
+        vert_resolution: c_uint,
+    ) -> FtError;
+    fn FT_Load_Glyph(face: FtFace, glyph_index: c_uint, load_flags: i32) -> FtError;
+    fn FT_Get_Char_Index(face: FtFace, charcode: c_ulong) -> c_uint;
+    fn FT_Get_Name_Index(face: FtFace, glyph_name: *const c_char) -> c_uint;
+    fn FT_Select_Charmap(face: FtFace, encoding: c_uint) -> FtError;
+    fn FT_Outline_Decompose(
+        outline: *mut FtOutline,
+        func_interface: *const FtOutlineFuncs,
+        user: *mut c_void,
+    ) -> FtError;
+}
+
+/// A handle to an instance of the FreeType library.
+pub struct Library(FtLibrary);
+
+impl Library {
+    /// Initialize a new instance of the library.
+    pub fn new() -> Result<Self> {
+        let mut lib = ptr::null_mut();
+        let error = unsafe { FT_Init_FreeType(&mut lib) };
+
+        if error != 0 {
+            bail!(
+                "failed to initialize the FreeType library (error {})",
+                error
+            );
+        }
+
+        Ok(Library(lib))
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            FT_Done_FreeType(self.0);
+        }
+    }
+}
+
+/// A font face, along with the data that it was loaded from.
+///
+/// Faces must be dropped before the [`Library`] that created them.
+pub struct Face {
+    face: FtFace,
+
+    // FreeType doesn't copy memory-based font data, so we need to keep it
+    // alive as long as the face is.
+    _data: Vec<u8>,
+}
+
+/// A glyph outline, expressed as SVG path data in font units, with `y`
+/// increasing upwards.
+#[derive(Debug, Default)]
+pub struct GlyphOutline {
+    /// The path data, which is empty if the glyph has no outline.
+    pub path: String,
+
+    /// The bounding box of the glyph, in font units: `(x0, y0, x1, y1)`,
+    /// with `y` increasing upwards.
+    pub bbox: (i32, i32, i32, i32),
+}
+
+/// A rendered glyph image.
+#[derive(Debug, Default)]
+pub struct GlyphBitmap {
+    /// The horizontal offset of the left edge of the bitmap from the glyph
+    /// origin, in pixels.
+    pub left: i32,
+
+    /// The vertical offset of the top edge of the bitmap from the glyph
+    /// origin, in pixels. Positive values are upwards.
+    pub top: i32,
+
+    /// The width of the image, in pixels.
+    pub width: usize,
+
+    /// The height of the image, in pixels.
+    pub rows: usize,
+
+    /// Coverage values, row by row, from 0 (transparent) to 255 (opaque).
+    pub coverage: Vec<u8>,
+}
+
+impl Face {
+    /// Load a face from font data.
+    pub fn new(lib: &Library, data: Vec<u8>, face_index: u32) -> Result<Self> {
+        let mut face = ptr::null_mut();
+        let error = unsafe {
+            FT_New_Memory_Face(
+                lib.0,
+                data.as_ptr(),
+                data.len() as c_long,
+                face_index as c_long,
+                &mut face,
+            )
+        };
+
+        if error != 0 {
+            bail!("FreeType failed to load the font (error {})", error);
+        }
+
+        Ok(Face { face, _data: data })
+    }
+
+    /// Get the number of font units per em.
+    pub fn units_per_em(&self) -> u16 {
+        unsafe { (*self.face).units_per_em }
+    }
+
+    /// Look up the glyph with the given PostScript name.
+    pub fn glyph_by_name(&self, name: &str) -> Option<u32> {
+        let name = CString::new(name).ok()?;
+
+        match unsafe { FT_Get_Name_Index(self.face, name.as_ptr()) } {
+            0 => None,
+            g => Some(g),
+        }
+    }
+
+    /// Look up the glyph for a character code in the font's built-in
+    /// encoding, as used by Type 1 fonts. If the font has no such encoding,
+    /// the code is looked up in its default character map.
+    pub fn glyph_by_builtin_code(&mut self, code: u32) -> Option<u32> {
+        unsafe {
+            if FT_Select_Charmap(self.face, FT_ENCODING_ADOBE_CUSTOM) != 0 {
+                FT_Select_Charmap(self.face, FT_ENCODING_ADOBE_STANDARD);
+            }
+
+            match FT_Get_Char_Index(self.face, code as c_ulong) {
+                0 => None,
+                g => Some(g),
+            }
+        }
+    }
+
+    /// Get the outline of a glyph.
+    pub fn outline(&mut self, glyph: u32) -> Result<GlyphOutline> {
+        let error = unsafe { FT_Load_Glyph(self.face, glyph as c_uint, FT_LOAD_NO_SCALE) };
+
+        if error != 0 {
+            bail!("FreeType failed to load glyph #{} (error {})", glyph, error);
+        }
+
+        let funcs = FtOutlineFuncs {
+            move_to: outline_move_to,
+            line_to: outline_line_to,
+            conic_to: outline_conic_to,
+            cubic_to: outline_cubic_to,
+            shift: 0,
+            delta: 0,
+        };
+
+        let mut outline = GlyphOutline::default();
+
+        let error = unsafe {
+            FT_Outline_Decompose(
+                &mut (*(*self.face).glyph).outline,
+                &funcs,
+                &mut outline as *mut GlyphOutline as *mut c_void,
+            )
+        };
+
+        if error != 0 {
+            bail!(
+                "FreeType failed to decompose glyph #{} (error {})",
+                glyph,
+                error
+            );
+        }
+
+        if !outline.path.is_empty() {
+            outline.path.push('Z');
+        }
+
+        // The metrics are width, height, horiBearingX, and horiBearingY.
+        let m = unsafe { (*(*self.face).glyph).metrics };
+        let (x0, y1) = (m[2] as i32, m[3] as i32);
+        outline.bbox = (x0, y1 - m[1] as i32, x0 + m[0] as i32, y1);
+        Ok(outline)
+    }
+
+    /// Render a glyph at the given size, expressed in big (PostScript) points,
+    /// and resolution, expressed in dots per inch.
+    ///
+    /// Glyphs that FreeType can't render to a grayscale or monochrome bitmap
+    /// yield an empty image.
+    pub fn render_glyph(&mut self, glyph: u32, size_bp: f64, dpi: u32) -> Result<GlyphBitmap> {
+        let size_26_6 = (size_bp * 64.).round() as c_long;
+
+        let error = unsafe { FT_Set_Char_Size(self.face, 0, size_26_6, dpi, dpi) };
+
+        if error != 0 {
+            bail!("FreeType failed to set the font size (error {})", error);
+        }
+
+        let error = unsafe { FT_Load_Glyph(self.face, glyph, FT_LOAD_RENDER) };
+
+        if error != 0 {
+            bail!(
+                "FreeType failed to render glyph #{} (error {})",
+                glyph,
+                error
+            );
+        }
+
+        let slot = unsafe { &*(*self.face).glyph };
+        let bitmap = &slot.bitmap;
+        let width = bitmap.width as usize;
+        let rows = bitmap.rows as usize;
+        let mut coverage = vec![0; width * rows];
+
+        if !bitmap.buffer.is_null()
+            && (bitmap.pixel_mode == FT_PIXEL_MODE_GRAY || bitmap.pixel_mode == FT_PIXEL_MODE_MONO)
+        {
+            let stride = bitmap.pitch.unsigned_abs() as usize;
+
+            for r in 0..rows {
+                // A negative pitch means that the rows are stored bottom-up.
+                let offset = if bitmap.pitch >= 0 {
+                    r * stride
+                } else {
+                    (rows - 1 - r) * stride
+                };
+
+                let row = unsafe { std::slice::from_raw_parts(bitmap.buffer.add(offset), stride) };
+
+                for c in 0..width {
+                    coverage[r * width + c] = if bitmap.pixel_mode == FT_PIXEL_MODE_GRAY {
+                        row[c]
+                    } else if row[c / 8] & (0x80 >> (c % 8)) != 0 {
+                        0xFF
+                    } else {
+                        0
+                    };
+                }
+            }
+        }
+
+        Ok(GlyphBitmap {
+            left: slot.bitmap_left,
+            top: slot.bitmap_top,
+            width,
+            rows,
+            coverage,
+        })
+    }
+}
+
+impl Drop for Face {
+    fn drop(&mut self) {
+        unsafe {
+            FT_Done_Face(self.face);
+        }
+    }
+}
+
+// Callbacks for `FT_Outline_Decompose`, appending to the path of the
+// `GlyphOutline` passed as the user data.
+
+fn outline_path<'a>(user: *mut c_void) -> &'a mut String {
+    unsafe { &mut (*(user as *mut GlyphOutline)).path }
+}
+
+extern "C" fn outline_move_to(to: *const FtVector, user: *mut c_void) -> c_int {
+    let path = outline_path(user);
+    let to = unsafe { *to };
+
+    if !path.is_empty() {
+        path.push('Z');
+    }
+
+    write!(path, "M{} {}", to.x, to.y).unwrap();
+    0
+}
+
+extern "C" fn outline_line_to(to: *const FtVector, user: *mut c_void) -> c_int {
+    let to = unsafe { *to };
+    write!(outline_path(user), "L{} {}", to.x, to.y).unwrap();
+    0
+}
+
+extern "C" fn outline_conic_to(
+    control: *const FtVector,
+    to: *const FtVector,
+    user: *mut c_void,
+) -> c_int {
+    let (c, to) = unsafe { (*control, *to) };
+    write!(outline_path(user), "Q{} {} {} {}", c.x, c.y, to.x, to.y).unwrap();
+    0
+}
+
+extern "C" fn outline_cubic_to(
+    control1: *const FtVector,
+    control2: *const FtVector,
+    to: *const FtVector,
+    user: *mut c_void,
+) -> c_int {
+    let (c1, c2, to) = unsafe { (*control1, *control2, *to) };
+    write!(
+        outline_path(user),
+        "C{} {} {} {} {} {}",
+        c1.x,
+        c1.y,
+        c2.x,
+        c2.y,
+        to.x,
+        to.y
+    )
+    .unwrap();
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type1_glyphs() {
+        let data = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/assets/cmr10.pfb"
+        ))
+        .unwrap();
+        let lib = Library::new().unwrap();
+        let mut face = Face::new(&lib, data, 0).unwrap();
+        assert_eq!(face.units_per_em(), 1000);
+
+        let a = face.glyph_by_builtin_code(b'A' as u32).unwrap();
+        assert_eq!(face.glyph_by_name("A"), Some(a));
+        assert_eq!(face.glyph_by_name("no-such-glyph"), None);
+
+        let outline = face.outline(a).unwrap();
+        assert!(outline.path.starts_with('M'));
+        assert!(outline.path.ends_with('Z'));
+
+        // "A" sits on the baseline and is about 0.68 em tall.
+        let (x0, y0, x1, y1) = outline.bbox;
+        assert!(x0 < x1 && y0.abs() <= 1 && (650..720).contains(&y1));
+
+        let bitmap = face.render_glyph(a, 10., 100).unwrap();
+        assert!(bitmap.coverage.iter().any(|&c| c != 0));
+    }
+}
@@ -0,0 +1,213 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+#![deny(missing_docs)]
+
+//! Support code shared by the Tectonic engines that convert XDV files into
+//! other formats.
+//!
+//! The SVG, PNG, and plain-text engines all need to load the font files
+//! referenced by an XDV file through the I/O stack, keep track of the font
+//! instances that the file defines, and write out one output file per page.
+//! The engines that draw glyphs also need to handle traditional TFM-based
+//! fonts, which XDV files reference only by name. This crate provides those
+//! pieces, so that each engine only has to deal with its own output format.
+
+use std::io::{Read, Write};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_io_base::OpenResult;
+use tectonic_status_base::StatusBackend;
+
+pub mod font;
+pub mod fontmap;
+pub mod freetype;
+pub mod tfm;
+
+/// A native font defined in an XDV file.
+#[derive(Clone, Copy, Debug)]
+pub struct FontInstance {
+    /// The index of the font's file in the engine's [`FontFiles`].
+    pub file_idx: usize,
+
+    /// The font size in TeX units.
+    pub size: i32,
+
+    /// The color of the text, as RGBA, if one was specified.
+    pub color_rgba: Option<u32>,
+}
+
+/// A collection of font files loaded from the I/O stack.
+///
+/// Each file is identified by the name under which it was loaded and a face
+/// index, and is stored in whatever form the engine needs, such as
+/// [`font::FontFileData`].
+#[derive(Debug)]
+pub struct FontFiles<T> {
+    /// The names and face indices of the font files that have been loaded,
+    /// parallel to `files`.
+    keys: Vec<(String, u32)>,
+    files: Vec<T>,
+}
+
+impl<T> Default for FontFiles<T> {
+    fn default() -> Self {
+        FontFiles {
+            keys: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl<T> FontFiles<T> {
+    /// Load a font file, returning its index.
+    ///
+    /// The file is looked up in the I/O stack under the name `name`, and then
+    /// with an `.otf` extension added, just as the TeX engine does. If the file
+    /// has already been loaded, the existing index is returned. Otherwise, its
+    /// contents and `face_index` are passed to `load` to create the stored
+    /// value.
+    pub fn load<F>(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        name: &str,
+        face_index: u32,
+        load: F,
+    ) -> Result<usize>
+    where
+        F: FnOnce(Vec<u8>, u32) -> Result<T>,
+    {
+        let mut texpath = String::default();
+        let mut buffer = None;
+
+        for ext in &["", ".otf"] {
+            texpath = format!("{name}{ext}");
+
+            if let Some(idx) = self
+                .keys
+                .iter()
+                .position(|(p, fi)| *p == texpath && *fi == face_index)
+            {
+                return Ok(idx);
+            }
+
+            buffer = read_input(hooks, status, &texpath)?;
+
+            if buffer.is_some() {
+                break;
+            }
+        }
+
+        let buffer = a_ok_or!(buffer;
+            ["failed to find a font file associated with the name `{}`", name]
+        );
+
+        let data = atry!(
+            load(buffer, face_index);
+            ["unable to load font file `{}`", texpath]
+        );
+
+        self.keys.push((texpath, face_index));
+        self.files.push(data);
+        Ok(self.files.len() - 1)
+    }
+
+    /// Get a loaded font file.
+    pub fn get(&self, idx: usize) -> &T {
+        &self.files[idx]
+    }
+
+    /// Get a loaded font file mutably.
+    pub fn get_mut(&mut self, idx: usize) -> &mut T {
+        &mut self.files[idx]
+    }
+
+    /// Get the number of font files that have been loaded.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Get whether no font files have been loaded.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Get the name under which a font file was loaded.
+    pub fn name(&self, idx: usize) -> &str {
+        &self.keys[idx].0
+    }
+}
+
+/// Read the complete contents of a file from the I/O stack, returning `None`
+/// if it doesn't exist.
+pub fn read_input(
+    hooks: &mut dyn DriverHooks,
+    status: &mut dyn StatusBackend,
+    name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let mut ih = match hooks.io().input_open_name(name, status) {
+        OpenResult::Ok(h) => h,
+        OpenResult::NotAvailable => return Ok(None),
+        OpenResult::Err(e) => return Err(e),
+    };
+
+    let mut buffer = Vec::new();
+    atry!(
+        ih.read_to_end(&mut buffer);
+        ["unable to read file `{}`", name]
+    );
+
+    let (ih_name, digest_opt) = ih.into_name_digest();
+    hooks.event_input_closed(ih_name, digest_opt, status);
+    Ok(Some(buffer))
+}
+
+/// Write out the output files of a multi-page document.
+///
+/// If the document has a single page, it is written to
+/// `<output_stem>.<extension>`; otherwise, page *N* is written to
+/// `<output_stem>-<N>.<extension>`, counting from 1.
+pub fn write_pages<D: AsRef<[u8]>>(
+    hooks: &mut dyn DriverHooks,
+    status: &mut dyn StatusBackend,
+    output_stem: &str,
+    extension: &str,
+    pages: &[D],
+) -> Result<()> {
+    let n_pages = pages.len();
+
+    for (idx, content) in pages.iter().enumerate() {
+        let name = if n_pages == 1 {
+            format!("{output_stem}.{extension}")
+        } else {
+            format!("{}-{}.{}", output_stem, idx + 1, extension)
+        };
+
+        write_output(hooks, status, &name, content.as_ref())?;
+    }
+
+    Ok(())
+}
+
+/// Write a single output file through the I/O stack.
+pub fn write_output(
+    hooks: &mut dyn DriverHooks,
+    status: &mut dyn StatusBackend,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut output = atry!(
+        hooks.io().output_open_name(name).must_exist();
+        ["cannot open output file `{}`", name]
+    );
+
+    atry!(
+        output.write_all(data);
+        ["cannot write output file `{}`", name]
+    );
+
+    let (name, digest) = output.into_name_digest();
+    hooks.event_output_closed(name, digest, status);
+    Ok(())
+}
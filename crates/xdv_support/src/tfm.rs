@@ -0,0 +1,399 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+//! Traditional fonts based on TeX font metric (TFM) files.
+//!
+//! XDV files don't record the widths of the characters set in traditional
+//! fonts, so we need to read them from the TFM files to know where each
+//! character goes. See the `tftopl` program for the definitive description of
+//! the file format. To draw the characters, we find the font's glyphs through
+//! the font map, as described in [`crate::fontmap`].
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    convert::TryFrom,
+};
+use tectonic_bridge_core::DriverHooks;
+use tectonic_errors::prelude::*;
+use tectonic_status_base::{tt_warning, StatusBackend};
+
+use crate::{
+    fontmap::{parse_encoding, FontMap},
+    freetype::{Face, Library},
+    read_input, FontFiles,
+};
+
+/// The name of the font map that we use, as does `xdvipdfmx`.
+const FONT_MAP_NAME: &str = "pdftex.map";
+
+/// A glyph to be drawn for a character in a TFM font.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TfmGlyph {
+    /// The index of the face containing the glyph, for use with
+    /// [`TfmFonts::face_mut`].
+    pub face_idx: usize,
+
+    /// The glyph's index in the face.
+    pub glyph: u32,
+
+    /// The font size in TeX units.
+    pub size: i32,
+}
+
+/// A traditional font defined in an XDV file.
+#[derive(Debug)]
+struct TfmFont {
+    /// The font's size in TeX units.
+    scale: i32,
+
+    /// The font's metrics, if its TFM file could be loaded.
+    metrics: Option<TfmMetrics>,
+
+    /// The face with the font's glyphs, if it could be found.
+    face_idx: Option<usize>,
+
+    /// The glyph for each character code, where known.
+    glyphs: HashMap<i32, u32>,
+}
+
+/// The traditional fonts defined in an XDV file.
+///
+/// For each font, we load its metrics so that we can position its characters,
+/// and the glyphs for its character codes, so that we can draw them. If either
+/// can't be found, the font's characters are still reported but can't be drawn
+/// or positioned properly; [`TfmFonts::warn_missing`] reports such fonts.
+pub struct TfmFonts {
+    fonts: HashMap<i32, TfmFont>,
+
+    /// The font map, loaded when the first font is defined.
+    map: Option<FontMap>,
+
+    encodings: HashMap<String, Vec<String>>,
+
+    /// The fonts whose metrics or glyphs couldn't be found.
+    missing_metrics: BTreeSet<String>,
+    missing_glyphs: BTreeSet<String>,
+
+    // Note that `faces` must be declared before `library` so that the faces
+    // are dropped first.
+    faces: FontFiles<Face>,
+    library: Library,
+}
+
+impl TfmFonts {
+    /// Create a new, empty set of fonts.
+    pub fn new() -> Result<Self> {
+        Ok(TfmFonts {
+            fonts: HashMap::new(),
+            map: None,
+            encodings: HashMap::new(),
+            missing_metrics: BTreeSet::new(),
+            missing_glyphs: BTreeSet::new(),
+            faces: FontFiles::default(),
+            library: Library::new()?,
+        })
+    }
+
+    /// Handle the definition of a font in the XDV file.
+    ///
+    /// Missing files are not errors: they are recorded to be reported by
+    /// [`Self::warn_missing`].
+    pub fn define(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        name: &str,
+        font_num: i32,
+        scale: i32,
+    ) -> Result<()> {
+        if self.fonts.contains_key(&font_num) {
+            return Ok(());
+        }
+
+        let tfm_name = format!("{name}.tfm");
+        let metrics = match read_input(hooks, status, &tfm_name)? {
+            Some(data) => Some(atry!(
+                TfmMetrics::from_bytes(&data);
+                ["unable to load font metrics file `{}`", tfm_name]
+            )),
+
+            None => {
+                self.missing_metrics.insert(name.to_owned());
+                None
+            }
+        };
+
+        let (face_idx, glyphs) = match self.load_glyphs(hooks, status, name)? {
+            Some((idx, glyphs)) => (Some(idx), glyphs),
+            None => {
+                self.missing_glyphs.insert(name.to_owned());
+                (None, HashMap::new())
+            }
+        };
+
+        self.fonts.insert(
+            font_num,
+            TfmFont {
+                scale,
+                metrics,
+                face_idx,
+                glyphs,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Find the face containing the glyphs of a font and map its character
+    /// codes to glyphs, returning `None` if we can't.
+    fn load_glyphs(
+        &mut self,
+        hooks: &mut dyn DriverHooks,
+        status: &mut dyn StatusBackend,
+        name: &str,
+    ) -> Result<Option<(usize, HashMap<i32, u32>)>> {
+        if self.map.is_none() {
+            let map = read_input(hooks, status, FONT_MAP_NAME)?
+                .map(|data| FontMap::parse(&String::from_utf8_lossy(&data)))
+                .unwrap_or_default();
+            self.map = Some(map);
+        }
+
+        let entry = match self.map.as_ref().and_then(|m| m.get(name)) {
+            Some(e) => e.clone(),
+            None => return Ok(None),
+        };
+
+        let font_file = match entry.font_file {
+            Some(f) => f,
+            None => return Ok(None),
+        };
+
+        let library = &self.library;
+        let face_idx = match self.faces.load(hooks, status, &font_file, 0, |data, idx| {
+            Face::new(library, data, idx)
+        }) {
+            Ok(idx) => idx,
+            Err(_) => return Ok(None),
+        };
+
+        let encoding = match entry.encoding_file {
+            Some(enc) => {
+                if !self.encodings.contains_key(&enc) {
+                    let names = match read_input(hooks, status, &enc)? {
+                        Some(data) => parse_encoding(&String::from_utf8_lossy(&data)),
+                        None => return Ok(None),
+                    };
+
+                    self.encodings.insert(enc.clone(), names);
+                }
+
+                Some(&self.encodings[&enc])
+            }
+
+            None => None,
+        };
+
+        let face = self.faces.get_mut(face_idx);
+        let mut glyphs = HashMap::new();
+
+        for code in 0..256 {
+            let glyph = match encoding {
+                Some(names) => names.get(code).and_then(|n| face.glyph_by_name(n)),
+                None => face.glyph_by_builtin_code(code as u32),
+            };
+
+            if let Some(g) = glyph {
+                glyphs.insert(code as i32, g);
+            }
+        }
+
+        Ok(Some((face_idx, glyphs)))
+    }
+
+    /// Get the width of a character, in TeX units. This is zero if the
+    /// character's width isn't known.
+    pub fn char_width(&self, font_num: i32, c: i32) -> i32 {
+        self.fonts
+            .get(&font_num)
+            .and_then(|f| f.metrics.as_ref()?.width(c, f.scale))
+            .unwrap_or(0)
+    }
+
+    /// Get the glyph to draw for a character, if known.
+    pub fn glyph(&self, font_num: i32, c: i32) -> Option<TfmGlyph> {
+        let font = self.fonts.get(&font_num)?;
+
+        Some(TfmGlyph {
+            face_idx: font.face_idx?,
+            glyph: *font.glyphs.get(&c)?,
+            size: font.scale,
+        })
+    }
+
+    /// Get a face that contains glyphs of the fonts.
+    pub fn face_mut(&mut self, face_idx: usize) -> &mut Face {
+        self.faces.get_mut(face_idx)
+    }
+
+    /// Warn about any fonts whose metrics or glyphs couldn't be found. The
+    /// `format` describes the output being created, such as "SVG".
+    pub fn warn_missing(&self, status: &mut dyn StatusBackend, format: &str) {
+        if !self.missing_metrics.is_empty() {
+            tt_warning!(
+                status,
+                "could not find the metrics of the font(s) {}; text in them will be misplaced in the {} output",
+                join_names(&self.missing_metrics),
+                format
+            );
+        }
+
+        if !self.missing_glyphs.is_empty() {
+            tt_warning!(
+                status,
+                "could not find the glyphs of the font(s) {} in `{}`; text in them will be missing from the {} output",
+                join_names(&self.missing_glyphs),
+                FONT_MAP_NAME,
+                format
+            );
+        }
+    }
+}
+
+fn join_names(names: &BTreeSet<String>) -> String {
+    names
+        .iter()
+        .map(|n| format!("`{n}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The character widths of a TFM font.
+#[derive(Clone, Debug)]
+pub struct TfmMetrics {
+    /// The smallest character code in the font.
+    first_char: i32,
+
+    /// The width of each character from `first_char` onwards, as an index
+    /// into `widths`. Zero means that the character doesn't exist.
+    width_indices: Vec<u8>,
+
+    /// The distinct widths, as TFM "fix_word" values.
+    widths: Vec<[u8; 4]>,
+}
+
+impl TfmMetrics {
+    /// Parse the contents of a TFM file.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let half = |i: usize| -> Result<usize> {
+            let b = a_ok_or!(data.get(2 * i..2 * i + 2); ["TFM data are truncated"]);
+            Ok(u16::from_be_bytes([b[0], b[1]]) as usize)
+        };
+
+        let lh = half(1)?;
+        let bc = half(2)?;
+        let ec = half(3)?;
+        let nw = half(4)?;
+
+        // The "empty" font has bc = ec + 1.
+        if ec > 255 || bc > ec + 1 {
+            bail!("invalid character range {}–{} in TFM data", bc, ec);
+        }
+
+        let n_chars = ec + 1 - bc;
+        let char_info_start = 4 * (6 + lh);
+        let widths_start = char_info_start + 4 * n_chars;
+        let widths_end = widths_start + 4 * nw;
+
+        if data.len() < widths_end {
+            bail!("TFM data are truncated");
+        }
+
+        let width_indices = (0..n_chars)
+            .map(|i| data[char_info_start + 4 * i])
+            .collect::<Vec<_>>();
+
+        if width_indices.iter().any(|&w| w as usize >= nw.max(1)) {
+            bail!("invalid width index in TFM data");
+        }
+
+        let widths = data[widths_start..widths_end]
+            .chunks_exact(4)
+            .map(|w| [w[0], w[1], w[2], w[3]])
+            .collect();
+
+        Ok(TfmMetrics {
+            first_char: bc as i32,
+            width_indices,
+            widths,
+        })
+    }
+
+    /// Get the width of a character, in TeX units, for the font loaded at the
+    /// given scale in TeX units. This is `None` if the character doesn't
+    /// exist in the font.
+    pub fn width(&self, c: i32, scale: i32) -> Option<i32> {
+        let idx = *self
+            .width_indices
+            .get(usize::try_from(c.checked_sub(self.first_char)?).ok()?)?;
+
+        if idx == 0 {
+            None
+        } else {
+            Some(scale_fix_word(self.widths[idx as usize], scale))
+        }
+    }
+}
+
+/// Scale a TFM "fix_word" by a font size, in exactly the way that TeX does,
+/// so that the positions that we compute match TeX's. This is the algorithm
+/// of §572 of *TeX: The Program*.
+fn scale_fix_word(fw: [u8; 4], scale: i32) -> i32 {
+    let mut z = scale.max(0) as i64;
+    let mut alpha = 16;
+
+    while z >= 0o40000000 {
+        z /= 2;
+        alpha += alpha;
+    }
+
+    let beta = 256 / alpha;
+    alpha *= z;
+
+    let [a, b, c, d] = fw.map(|x| x as i64);
+    let sw = (((((d * z) / 256) + (c * z)) / 256) + (b * z)) / beta;
+
+    if a == 255 {
+        (sw - alpha) as i32
+    } else {
+        sw as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaling() {
+        // 0.5 and -0.5 at 10pt.
+        assert_eq!(scale_fix_word([0, 0x08, 0, 0], 10 << 16), 5 << 16);
+        assert_eq!(scale_fix_word([0xFF, 0xF8, 0, 0], 10 << 16), -(5 << 16));
+    }
+
+    #[test]
+    fn cmr10() {
+        let data = std::fs::read(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/assets/cmr10.tfm"
+        ))
+        .unwrap();
+        let tfm = TfmMetrics::from_bytes(&data).unwrap();
+
+        // The width of "A" in cmr10 is 0.750002 of the design size.
+        let w = tfm.width(b'A' as i32, 10 << 16).unwrap();
+        assert!((w - 491521).abs() <= 1, "{}", w);
+        assert_eq!(tfm.width(300, 10 << 16), None);
+        assert_eq!(tfm.width(-1, 10 << 16), None);
+    }
+}
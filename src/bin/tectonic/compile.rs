@@ -29,7 +29,7 @@ pub struct CompileOptions {
     #[arg(long, short, name = "path", default_value = "latex")]
     format: String,
 
-    /// Use this directory or Zip-format bundle file to find resource files instead of the default
+    /// Use this directory or Zip- or tar-format bundle file to find resource files instead of the default
     #[arg(long, short, name = "file_path")]
     bundle: Option<PathBuf>,
 
@@ -37,9 +37,10 @@ pub struct CompileOptions {
     #[arg(short = 'C', long)]
     only_cached: bool,
 
-    /// The kind of output to generate
-    #[arg(long, name = "format", default_value = "pdf")]
-    outfmt: OutputFormat,
+    /// The kind of output to generate; separate multiple kinds with commas
+    /// (html can't be combined with xdv, pdf, svg, png, or txt)
+    #[arg(long, name = "format", default_value = "pdf", value_delimiter = ',')]
+    outfmt: Vec<OutputFormat>,
 
     /// Write Makefile-format rules expressing the dependencies of this run to <dest_path>
     #[arg(long, name = "dest_path")]
@@ -73,6 +74,10 @@ pub struct CompileOptions {
     #[arg(long = "print", short)]
     print_stdout: bool,
 
+    /// Write a JSON report describing the build to <report_path>
+    #[arg(long, name = "report_path")]
+    report: Option<PathBuf>,
+
     /// The directory in which to place output files [default: the directory containing <input>]
     #[arg(name = "outdir", short, long)]
     outdir: Option<PathBuf>,
@@ -109,6 +114,11 @@ impl CompileOptions {
             SecurityStance::MaybeAllowInsecures
         };
 
+        let (outfmt, extra_outfmts) = match self.outfmt.split_first() {
+            Some(x) => x,
+            None => return Err(errmsg!("at least one output format must be given")),
+        };
+
         let mut sess_builder =
             ProcessingSessionBuilder::new_with_security(SecuritySettings::new(stance));
         let format_path = self.format;
@@ -120,9 +130,13 @@ impl CompileOptions {
             .keep_intermediates(self.keep_intermediates)
             .format_cache_path(config.format_cache_path()?)
             .synctex(self.synctex)
-            .output_format(self.outfmt)
+            .output_format(*outfmt)
             .pass(self.pass);
 
+        for f in extra_outfmts {
+            sess_builder.extra_output_format(*f);
+        }
+
         if let Some(s) = self.reruns {
             sess_builder.reruns(s);
         }
@@ -131,6 +145,10 @@ impl CompileOptions {
             sess_builder.makefile_output_path(p);
         }
 
+        if let Some(p) = self.report {
+            sess_builder.build_report_path(p);
+        }
+
         // Input and path setup
 
         let input_path = self.input;
@@ -1,13 +1,15 @@
 use clap::Args;
+use std::path::PathBuf;
 use tectonic::{
     config::is_config_test_mode_activated,
     config::PersistentConfig,
     docmodel::{DocumentExt, DocumentSetupOptions},
+    errmsg,
     errors::Result,
     tt_error, tt_note,
 };
 use tectonic_bridge_core::{SecuritySettings, SecurityStance};
-use tectonic_docmodel::workspace::Workspace;
+use tectonic_docmodel::{document::Document, workspace::Workspace};
 use tectonic_status_base::StatusBackend;
 
 use crate::v2cli::{CommandCustomizations, TectonicCommand};
@@ -35,6 +37,14 @@ pub struct BuildCommand {
     #[arg(long = "print", short)]
     print_stdout: bool,
 
+    /// Rebuild even if nothing seems to have changed since the last build
+    #[arg(long)]
+    force: bool,
+
+    /// Write a JSON report describing the build to <report_path>
+    #[arg(long, name = "report_path")]
+    report: Option<PathBuf>,
+
     /// Open built document using system handler
     #[arg(long)]
     open: bool,
@@ -43,6 +53,10 @@ pub struct BuildCommand {
     #[arg(long, help = "Specify the target of the build.")]
     target: Option<String>,
 
+    /// Build only the workspace member document with this name
+    #[arg(long, name = "name")]
+    document: Option<String>,
+
     /// Use this URL to find resource files instead of the default
     #[arg(long, short, name = "url", overrides_with = "url", global(true))]
     web_bundle: Option<String>,
@@ -59,7 +73,19 @@ impl TectonicCommand for BuildCommand {
             tt_note!(status, "using workspace bundle configuration");
         }
         let ws = Workspace::open_from_environment()?;
-        let doc = ws.first_document();
+
+        // By default, we build the document that we're in, or all of the
+        // documents in the workspace if we're not in any one of them.
+        let docs: Vec<&Document> = if let Some(ref name) = self.document {
+            match ws.document(name) {
+                Some(d) => vec![d],
+                None => return Err(errmsg!("no document named `{}` in the workspace", name)),
+            }
+        } else if let Some(d) = ws.current_document() {
+            vec![d]
+        } else {
+            ws.documents().iter().collect()
+        };
 
         // Default to allowing insecure since it would be super duper annoying
         // to have to pass `--trusted` every time to build a personal document
@@ -73,23 +99,88 @@ impl TectonicCommand for BuildCommand {
 
         let mut setup_options =
             DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
-        setup_options.only_cached(self.only_cached);
+        setup_options
+            .only_cached(self.only_cached)
+            .signature_policy(config.signature_policy()?);
 
-        for output_name in doc.output_names() {
-            if let Some(out) = self.target.as_ref() {
-                if out != output_name {
-                    continue;
-                }
+        for doc in &docs {
+            if docs.len() > 1 {
+                tt_note!(status, "building document `{}`", doc.name);
             }
 
-            let mut builder = doc.setup_session(output_name, &setup_options, status)?;
+            self.build_document(doc, docs.len() > 1, &config, &setup_options, status)?;
+        }
+
+        Ok(0)
+    }
+}
 
+impl BuildCommand {
+    /// Build the selected outputs of one document. If *qualify_reports* is
+    /// true, the name of the document is included in the names of any build
+    /// reports, since several documents are being built.
+    fn build_document(
+        &self,
+        doc: &Document,
+        qualify_reports: bool,
+        config: &PersistentConfig,
+        setup_options: &DocumentSetupOptions,
+        status: &mut dyn StatusBackend,
+    ) -> Result<()> {
+        let output_names: Vec<&str> = doc
+            .output_names()
+            .filter(|name| match self.target {
+                Some(ref t) => t == name,
+                None => true,
+            })
+            .collect();
+
+        let builders = doc.setup_sessions(&output_names, setup_options, status)?;
+
+        for (output_name, mut builder) in output_names.iter().copied().zip(builders) {
             builder
                 .format_cache_path(config.format_cache_path()?)
-                .keep_intermediates(self.keep_intermediates)
-                .keep_logs(self.keep_logs)
                 .print_stdout(self.print_stdout);
 
+            // These options can also be turned on in the output profile.
+            if self.keep_intermediates {
+                builder.keep_intermediates(true);
+            }
+
+            if self.keep_logs {
+                builder.keep_logs(true);
+            }
+
+            if !self.force {
+                let mut manifest_path = doc.build_dir().to_owned();
+                manifest_path.push(output_name);
+                manifest_path.push(".tectonic-manifest.json");
+                builder.build_manifest_path(manifest_path);
+            }
+
+            if let Some(p) = self.report.as_ref() {
+                // If we're building multiple outputs, give each one its own
+                // report, distinguished by the document and output names.
+                if qualify_reports || output_names.len() > 1 {
+                    let mut fname = p.file_stem().unwrap_or_default().to_owned();
+
+                    if qualify_reports {
+                        fname.push(format!("-{}", doc.name));
+                    }
+
+                    fname.push(format!("-{output_name}"));
+
+                    if let Some(ext) = p.extension() {
+                        fname.push(".");
+                        fname.push(ext);
+                    }
+
+                    builder.build_report_path(p.with_file_name(fname));
+                } else {
+                    builder.build_report_path(p);
+                }
+            }
+
             crate::compile::run_and_report(builder, status)?;
 
             if self.open {
@@ -111,6 +202,6 @@ impl TectonicCommand for BuildCommand {
             }
         }
 
-        Ok(0)
+        Ok(())
     }
 }
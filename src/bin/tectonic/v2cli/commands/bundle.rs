@@ -1,18 +1,31 @@
 use clap::{Parser, Subcommand};
+use std::{collections::BTreeSet, fs::File, io::BufWriter, path::PathBuf, time::Duration};
 use tectonic::{
     config::PersistentConfig,
+    ctry,
     docmodel::{DocumentExt, DocumentSetupOptions},
     errors::Result,
-    tt_note,
+    tt_error, tt_note,
+};
+use tectonic_bridge_core::{SecuritySettings, SecurityStance};
+use tectonic_bundles::{
+    cache::{Cache, CachingBundle},
+    diff::diff_bundles,
+    export::{
+        export_subset_to_dir, export_subset_to_itar, export_subset_to_zip, export_to_dir,
+        export_to_itar, export_to_zip,
+    },
+    itar::IndexedTarBackend,
+    Bundle,
 };
-use tectonic_bundles::Bundle;
 use tectonic_docmodel::workspace::Workspace;
 use tectonic_status_base::StatusBackend;
+use url::Url;
 
 use crate::v2cli::{CommandCustomizations, TectonicCommand};
 
 fn get_a_bundle(
-    _config: PersistentConfig,
+    config: PersistentConfig,
     only_cached: bool,
     status: &mut dyn StatusBackend,
 ) -> Result<Box<dyn Bundle>> {
@@ -22,7 +35,9 @@ fn get_a_bundle(
         Ok(ws) => {
             let doc = ws.first_document();
             let mut options: DocumentSetupOptions = Default::default();
-            options.only_cached(only_cached);
+            options
+                .only_cached(only_cached)
+                .signature_policy(config.signature_policy()?);
             doc.bundle(&options, status)
         }
 
@@ -34,16 +49,58 @@ fn get_a_bundle(
                     status,
                     "not in a document workspace; using the built-in default bundle"
                 );
-                Ok(Box::new(tectonic_bundles::get_fallback_bundle(
-                    tectonic_engine_xetex::FORMAT_SERIAL,
-                    only_cached,
+                Ok(Box::new(get_fallback_bundle(&config, only_cached, status)?))
+            }
+        }
+    }
+}
+
+/// Like [`get_a_bundle`], but for commands that deal with the local bundle
+/// cache. Returns `None` if the bundle in question isn't cached.
+fn get_a_caching_bundle(
+    config: &PersistentConfig,
+    only_cached: bool,
+    status: &mut dyn StatusBackend,
+) -> Result<Option<CachingBundle<IndexedTarBackend>>> {
+    use tectonic_docmodel::workspace::NoWorkspaceFoundError;
+
+    match Workspace::open_from_environment() {
+        Ok(ws) => {
+            let doc = ws.first_document();
+            let mut options: DocumentSetupOptions = Default::default();
+            options
+                .only_cached(only_cached)
+                .signature_policy(config.signature_policy()?);
+            doc.cached_bundle(&options, status)
+        }
+
+        Err(e) => {
+            if e.downcast_ref::<NoWorkspaceFoundError>().is_none() {
+                Err(e.into())
+            } else {
+                tt_note!(
                     status,
-                )?))
+                    "not in a document workspace; using the built-in default bundle"
+                );
+                Ok(Some(get_fallback_bundle(config, only_cached, status)?))
             }
         }
     }
 }
 
+/// Open the built-in default bundle, checking its signature according to the
+/// user’s configuration.
+fn get_fallback_bundle(
+    config: &PersistentConfig,
+    only_cached: bool,
+    status: &mut dyn StatusBackend,
+) -> Result<CachingBundle<IndexedTarBackend>> {
+    let url = tectonic_bundles::get_fallback_bundle_url(tectonic_engine_xetex::FORMAT_SERIAL);
+    let mut cache = Cache::get_user_default()?;
+    cache.set_signature_policy(config.signature_policy()?);
+    Ok(cache.open(&url, only_cached, status)?)
+}
+
 /// `bundle`: Commands relating to Tectonic bundles
 #[derive(Debug, Eq, PartialEq, Parser)]
 pub struct BundleCommand {
@@ -57,23 +114,53 @@ enum BundleCommands {
     /// Dump the contents of a file in the bundle
     Cat(BundleCatCommand),
 
+    #[command(name = "diff")]
+    /// Compare the contents of two bundles
+    Diff(BundleDiffCommand),
+
+    #[command(name = "export")]
+    /// Export the bundle as a standalone ZIP file or directory
+    Export(BundleExportCommand),
+
+    #[command(name = "gc")]
+    /// Delete cached bundle data that haven't been used recently
+    Gc(BundleGcCommand),
+
+    #[command(name = "prefetch")]
+    /// Download bundle files into the local cache for offline use
+    Prefetch(BundlePrefetchCommand),
+
     #[command(name = "search")]
     /// Filter the list of filenames contained in the bundle
     Search(BundleSearchCommand),
+
+    #[command(name = "verify")]
+    /// Check the integrity of the locally cached bundle files
+    Verify(BundleVerifyCommand),
 }
 
 impl TectonicCommand for BundleCommand {
     fn customize(&self, cc: &mut CommandCustomizations) {
         match &self.command {
             BundleCommands::Cat(c) => c.customize(cc),
+            BundleCommands::Diff(c) => c.customize(cc),
+            BundleCommands::Export(c) => c.customize(cc),
+            BundleCommands::Gc(c) => c.customize(cc),
+            BundleCommands::Prefetch(c) => c.customize(cc),
             BundleCommands::Search(c) => c.customize(cc),
+            BundleCommands::Verify(c) => c.customize(cc),
         }
     }
 
     fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
         match self.command {
             BundleCommands::Cat(c) => c.execute(config, status),
+            BundleCommands::Diff(c) => c.execute(config, status),
+            BundleCommands::Export(c) => c.execute(config, status),
+            BundleCommands::Gc(c) => c.execute(config, status),
+            BundleCommands::Prefetch(c) => c.execute(config, status),
             BundleCommands::Search(c) => c.execute(config, status),
+            BundleCommands::Verify(c) => c.execute(config, status),
         }
     }
 }
@@ -103,6 +190,315 @@ impl BundleCatCommand {
     }
 }
 
+#[derive(Debug, Eq, PartialEq, Parser)]
+struct BundleDiffCommand {
+    /// Use only resource files cached locally
+    #[arg(short = 'C', long)]
+    only_cached: bool,
+
+    #[arg(help = "The URL or path of the old bundle")]
+    old: String,
+
+    #[arg(help = "The URL or path of the new bundle")]
+    new: String,
+}
+
+impl BundleDiffCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        let mut old = open_bundle_at(&config, &self.old, self.only_cached, status)?;
+        let mut new = open_bundle_at(&config, &self.new, self.only_cached, status)?;
+        let diff = diff_bundles(&mut old, &mut new, status)?;
+
+        // Print the results in the same style as `git diff --name-status`.
+        let mut lines: Vec<_> = diff
+            .added
+            .iter()
+            .map(|n| (n, 'A'))
+            .chain(diff.removed.iter().map(|n| (n, 'D')))
+            .chain(diff.changed.iter().map(|n| (n, 'M')))
+            .chain(diff.unknown.iter().map(|n| (n, '?')))
+            .collect();
+        lines.sort();
+
+        for (name, code) in lines {
+            println!("{code}\t{name}");
+        }
+
+        if diff.unknown.is_empty() {
+            tt_note!(
+                status,
+                "{} file(s) added, {} removed, {} changed",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            );
+        } else {
+            tt_note!(
+                status,
+                "{} file(s) added, {} removed, {} changed, {} unknown",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len(),
+                diff.unknown.len()
+            );
+        }
+        Ok(0)
+    }
+}
+
+/// Open a bundle given a URL or a filesystem path, as for the `bundle` setting
+/// in `Tectonic.toml`. Relative paths are resolved relative to the current
+/// directory.
+fn open_bundle_at(
+    config: &PersistentConfig,
+    loc: &str,
+    only_cached: bool,
+    status: &mut dyn StatusBackend,
+) -> Result<Box<dyn Bundle>> {
+    match Url::parse(loc) {
+        Ok(url) if url.scheme() != "file" => {
+            config.make_cached_url_provider(loc, only_cached, None, status)
+        }
+
+        Ok(_) => config.make_file_url_provider(loc, only_cached, status),
+
+        Err(_) => config.make_local_file_provider(PathBuf::from(loc), status),
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Parser)]
+struct BundleExportCommand {
+    /// Use only resource files cached locally
+    #[arg(short = 'C', long)]
+    only_cached: bool,
+
+    /// Only export the files needed to build the current document
+    #[arg(long)]
+    minimal: bool,
+
+    /// Document is untrusted -- disable all known-insecure features
+    #[arg(long, requires = "minimal")]
+    untrusted: bool,
+
+    /// Export the bundle as a ZIP file at this path
+    #[arg(long, conflicts_with_all = ["dir", "itar"], required_unless_present_any = ["dir", "itar"])]
+    zip: Option<PathBuf>,
+
+    /// Export the bundle as a directory at this path
+    #[arg(long, conflicts_with = "itar")]
+    dir: Option<PathBuf>,
+
+    /// Export the bundle as an indexed tar file at this path
+    #[arg(long)]
+    itar: Option<PathBuf>,
+}
+
+impl BundleExportCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        let (mut bundle, names) = if self.minimal {
+            let (bundle, names) = self.build_for_minimal_bundle(&config, status)?;
+            (bundle, Some(names))
+        } else {
+            (get_a_bundle(config, self.only_cached, status)?, None)
+        };
+
+        let (digest, dest) = if let Some(path) = self.zip {
+            let f = ctry!(
+                File::create(&path);
+                "couldn't open `{}` for writing", path.display()
+            );
+            let f = BufWriter::new(f);
+
+            let digest = match names {
+                Some(ref n) => export_subset_to_zip(&mut bundle, n, f, status)?,
+                None => export_to_zip(&mut bundle, f, status)?,
+            };
+            (digest, path)
+        } else if let Some(path) = self.itar {
+            let mut index_path = path.clone().into_os_string();
+            index_path.push(".index.gz");
+            let index_path = PathBuf::from(index_path);
+
+            let f = ctry!(
+                File::create(&path);
+                "couldn't open `{}` for writing", path.display()
+            );
+            let f = BufWriter::new(f);
+            let index = ctry!(
+                File::create(&index_path);
+                "couldn't open `{}` for writing", index_path.display()
+            );
+            let index = BufWriter::new(index);
+
+            let digest = match names {
+                Some(ref n) => export_subset_to_itar(&mut bundle, n, f, index, status)?,
+                None => export_to_itar(&mut bundle, f, index, status)?,
+            };
+            (digest, path)
+        } else {
+            // Clap ensures that one of the three options is present.
+            let path = self.dir.unwrap();
+
+            let digest = match names {
+                Some(ref n) => export_subset_to_dir(&mut bundle, n, &path, status)?,
+                None => export_to_dir(&mut bundle, &path, status)?,
+            };
+            (digest, path)
+        };
+
+        tt_note!(
+            status,
+            "exported bundle with digest {} to `{}`",
+            digest,
+            dest.display()
+        );
+        Ok(0)
+    }
+
+    /// Build every output of the current document, without saving the
+    /// results, and return its bundle along with the names of the bundle
+    /// files that were read.
+    fn build_for_minimal_bundle(
+        &self,
+        config: &PersistentConfig,
+        status: &mut dyn StatusBackend,
+    ) -> Result<(Box<dyn Bundle>, Vec<String>)> {
+        let ws = Workspace::open_from_environment()?;
+        let doc = ws.first_document();
+
+        let stance = if self.untrusted {
+            SecurityStance::DisableInsecures
+        } else {
+            SecurityStance::MaybeAllowInsecures
+        };
+
+        let mut setup_options =
+            DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
+        setup_options
+            .only_cached(self.only_cached)
+            .signature_policy(config.signature_policy()?);
+
+        // We use a fresh format cache so that the format files are
+        // regenerated, and the bundle files needed to do so are included.
+        let format_dir = ctry!(
+            tempfile::tempdir();
+            "can't create temporary directory for format files"
+        );
+
+        let output_names: Vec<&str> = doc.output_names().collect();
+        let builders = doc.setup_sessions(&output_names, &setup_options, status)?;
+        let mut names = BTreeSet::new();
+
+        for mut builder in builders {
+            builder
+                .format_cache_path(format_dir.path())
+                .do_not_write_output_files();
+            let mut sess = crate::compile::run_and_report(builder, status)?;
+            names.extend(sess.bundle_files_read(status));
+        }
+
+        let bundle = doc.bundle(&setup_options, status)?;
+        Ok((bundle, names.into_iter().collect()))
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Parser)]
+struct BundleGcCommand {
+    /// Keep data for bundles used within this many days
+    #[arg(long, default_value_t = 30)]
+    max_age: u64,
+}
+
+impl BundleGcCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, _config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        let cache = Cache::get_user_default()?;
+        let max_age = Duration::from_secs(self.max_age * 86400);
+        let report = cache.gc(max_age, status)?;
+
+        tt_note!(
+            status,
+            "removed data for {} bundle version(s) and {} cached file(s), freeing {:.1} MiB",
+            report.n_digests,
+            report.n_files,
+            report.n_bytes as f64 / 1048576.
+        );
+        Ok(0)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Parser)]
+struct BundlePrefetchCommand {
+    /// Download every file in the bundle, not just the ones the document needs
+    #[arg(long)]
+    all: bool,
+
+    /// Document is untrusted -- disable all known-insecure features
+    #[arg(long)]
+    untrusted: bool,
+}
+
+impl BundlePrefetchCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        if self.all {
+            let mut bundle = match get_a_caching_bundle(&config, false, status)? {
+                Some(b) => b,
+                None => {
+                    tt_note!(status, "the bundle is not cached; nothing to do");
+                    return Ok(0);
+                }
+            };
+
+            let n = bundle.prefetch_all(status)?;
+            tt_note!(status, "downloaded {} file(s) into the cache", n);
+            return Ok(0);
+        }
+
+        // Otherwise, the simplest and most reliable way to find out what the
+        // document needs is to build it, without saving any outputs.
+
+        let ws = Workspace::open_from_environment()?;
+        let doc = ws.first_document();
+
+        let stance = if self.untrusted {
+            SecurityStance::DisableInsecures
+        } else {
+            SecurityStance::MaybeAllowInsecures
+        };
+
+        let mut setup_options =
+            DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
+        setup_options.signature_policy(config.signature_policy()?);
+        let output_names: Vec<&str> = doc.output_names().collect();
+        let builders = doc.setup_sessions(&output_names, &setup_options, status)?;
+
+        for mut builder in builders {
+            builder
+                .format_cache_path(config.format_cache_path()?)
+                .do_not_write_output_files();
+            crate::compile::run_and_report(builder, status)?;
+        }
+
+        tt_note!(status, "all files needed by the document are now cached");
+        Ok(0)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Parser)]
 struct BundleSearchCommand {
     /// Use only resource files cached locally
@@ -138,3 +534,50 @@ impl BundleSearchCommand {
         Ok(0)
     }
 }
+
+#[derive(Debug, Eq, PartialEq, Parser)]
+struct BundleVerifyCommand {
+    /// Remove any bad files from the cache so that they will be downloaded again
+    #[arg(long)]
+    repair: bool,
+}
+
+impl BundleVerifyCommand {
+    fn customize(&self, cc: &mut CommandCustomizations) {
+        cc.always_stderr = true;
+    }
+
+    fn execute(self, config: PersistentConfig, status: &mut dyn StatusBackend) -> Result<i32> {
+        let mut bundle = match get_a_caching_bundle(&config, true, status)? {
+            Some(b) => b,
+            None => {
+                tt_note!(status, "the bundle is not cached; nothing to do");
+                return Ok(0);
+            }
+        };
+
+        let report = bundle.verify(self.repair, status)?;
+
+        if report.is_ok() {
+            tt_note!(status, "all {} cached file(s) are OK", report.n_checked);
+            Ok(0)
+        } else if self.repair {
+            tt_note!(
+                status,
+                "removed {} missing and {} corrupt file(s) from the cache; they will be downloaded again when needed",
+                report.missing.len(),
+                report.corrupt.len()
+            );
+            Ok(0)
+        } else {
+            tt_error!(
+                status,
+                "{} of {} cached file(s) are missing and {} are corrupt; rerun with `--repair` to fix",
+                report.missing.len(),
+                report.n_checked,
+                report.corrupt.len()
+            );
+            Ok(1)
+        }
+    }
+}
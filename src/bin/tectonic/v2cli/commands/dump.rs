@@ -61,7 +61,9 @@ impl TectonicCommand for DumpCommand {
 
         let mut setup_options =
             DocumentSetupOptions::new_with_security(SecuritySettings::new(stance));
-        setup_options.only_cached(self.only_cached);
+        setup_options
+            .only_cached(self.only_cached)
+            .signature_policy(config.signature_policy()?);
 
         // If output profile is unspecified, just grab one at (pseudo-)random.
         let output_name = self
@@ -16,7 +16,13 @@ use std::{
     sync::atomic::{AtomicBool, Ordering},
 };
 use tectonic_bundles::{
-    cache::Cache, dir::DirBundle, itar::IndexedTarBackend, zip::ZipBundle, Bundle,
+    cache::Cache,
+    dir::DirBundle,
+    itar::{IndexedTarBackend, LocalIndexedTarBackend},
+    signature::SignaturePolicy,
+    tar::{has_tar_extension, TarBundle},
+    zip::ZipBundle,
+    Bundle,
 };
 use tectonic_io_base::app_dirs;
 use url::Url;
@@ -66,6 +72,15 @@ pub fn maybe_return_test_bundle(web_bundle: Option<String>) -> Result<Box<dyn Bu
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct PersistentConfig {
     default_bundles: Vec<BundleInfo>,
+
+    /// Hex-encoded Ed25519 public keys of trusted bundle publishers. If any
+    /// are given, bundles obtained through the cache must be signed with one
+    /// of them.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    trusted_bundle_keys: Vec<String>,
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
@@ -151,10 +166,60 @@ impl PersistentConfig {
             Cache::get_user_default()?
         };
 
+        cache.set_signature_policy(self.signature_policy()?);
+
+        // Indexed tar files on the local filesystem get the same caching
+        // treatment as ones on the web.
+        if Url::parse(url).is_ok_and(|u| u.scheme() == "file") {
+            let bundle = cache.open::<LocalIndexedTarBackend>(url, only_cached, status)?;
+            return Ok(Box::new(bundle) as _);
+        }
+
         let bundle = cache.open::<IndexedTarBackend>(url, only_cached, status)?;
         Ok(Box::new(bundle) as _)
     }
 
+    /// Get the policy for checking the signatures of bundles obtained through
+    /// the cache, based on the configured trusted keys.
+    pub fn signature_policy(&self) -> Result<SignaturePolicy> {
+        let mut policy = SignaturePolicy::default();
+
+        for key in &self.trusted_bundle_keys {
+            policy.add_trusted_key(key)?;
+        }
+
+        Ok(policy)
+    }
+
+    /// Open a bundle given as a `file:` URL.
+    ///
+    /// Indexed tar files, as recognized by
+    /// [`LocalIndexedTarBackend::can_open`], are opened through the cache like
+    /// web bundles, so that their files and digests are checked and cached in
+    /// the same way. Anything else is opened directly with
+    /// [`Self::make_local_file_provider`].
+    pub fn make_file_url_provider(
+        &self,
+        url: &str,
+        only_cached: bool,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Box<dyn Bundle>> {
+        use std::io;
+
+        let file_path = Url::parse(url)
+            .ok()
+            .and_then(|u| u.to_file_path().ok())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "failed to parse local path")
+            })?;
+
+        if LocalIndexedTarBackend::can_open(&file_path) {
+            self.make_cached_url_provider(url, only_cached, None, status)
+        } else {
+            self.make_local_file_provider(file_path, status)
+        }
+    }
+
     pub fn make_local_file_provider(
         &self,
         file_path: PathBuf,
@@ -162,6 +227,8 @@ impl PersistentConfig {
     ) -> Result<Box<dyn Bundle>> {
         let bundle: Box<dyn Bundle> = if file_path.is_dir() {
             Box::new(DirBundle::new(file_path))
+        } else if has_tar_extension(&file_path) {
+            Box::new(TarBundle::open(file_path)?)
         } else {
             Box::new(ZipBundle::open(file_path)?)
         };
@@ -193,11 +260,7 @@ impl PersistentConfig {
         let url = Url::parse(&self.default_bundles[0].url)
             .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "failed to parse url"))?;
         if url.scheme() == "file" {
-            // load the local zip file.
-            let file_path = url.to_file_path().map_err(|_| {
-                io::Error::new(io::ErrorKind::InvalidInput, "failed to parse local path")
-            })?;
-            return self.make_local_file_provider(file_path, status);
+            return self.make_file_url_provider(&self.default_bundles[0].url, only_cached, status);
         }
         let bundle =
             self.make_cached_url_provider(&self.default_bundles[0].url, only_cached, None, status)?;
@@ -219,6 +282,7 @@ impl Default for PersistentConfig {
 
         PersistentConfig {
             default_bundles: vec![BundleInfo { url }],
+            trusted_bundle_keys: Vec::new(),
         }
     }
 }
@@ -7,14 +7,17 @@
 //! `tectonic_docmodel` crate with the actual document-processing capabilities
 //! provided by the processing engines.
 
-use std::{
-    fmt::Write as FmtWrite,
-    fs, io,
-    path::{Path, PathBuf},
-};
+use std::{cell::RefCell, fmt::Write as FmtWrite, fs, io, rc::Rc};
 use tectonic_bridge_core::SecuritySettings;
 use tectonic_bundles::{
-    cache::Cache, dir::DirBundle, itar::IndexedTarBackend, zip::ZipBundle, Bundle,
+    cache::{Cache, CachingBundle},
+    dir::DirBundle,
+    itar::{IndexedTarBackend, LocalIndexedTarBackend},
+    overlay::OverlayBundle,
+    signature::SignaturePolicy,
+    tar::{has_tar_extension, TarBundle},
+    zip::ZipBundle,
+    Bundle,
 };
 use tectonic_docmodel::{
     document::{BuildTargetType, Document, InputFile},
@@ -25,7 +28,7 @@ use url::Url;
 
 use crate::{
     config, ctry,
-    driver::{OutputFormat, PassSetting, ProcessingSessionBuilder},
+    driver::{OutputFormat, PassSetting, ProcessingSessionBuilder, RerunPolicy},
     errors::{ErrorKind, Result},
     status::StatusBackend,
     tt_note,
@@ -44,6 +47,9 @@ pub struct DocumentSetupOptions {
 
     /// Ensure a deterministic build environment.
     deterministic_mode: bool,
+
+    /// The keys trusted to sign bundles obtained through the cache.
+    signature_policy: SignaturePolicy,
 }
 
 impl DocumentSetupOptions {
@@ -54,6 +60,7 @@ impl DocumentSetupOptions {
             only_cached: false,
             deterministic_mode: false,
             security,
+            signature_policy: SignaturePolicy::default(),
         }
     }
 
@@ -72,19 +79,49 @@ impl DocumentSetupOptions {
         self.deterministic_mode = s;
         self
     }
+
+    /// Specify which keys are trusted to sign bundles obtained through the
+    /// cache.
+    ///
+    /// If the document’s backing bundle is not network-based, this setting will
+    /// have no effect.
+    pub fn signature_policy(&mut self, p: SignaturePolicy) -> &mut Self {
+        self.signature_policy = p;
+        self
+    }
+
+    /// Get the user’s default cache, set up according to these options.
+    fn user_cache(&self) -> Result<Cache> {
+        let mut cache = Cache::get_user_default()?;
+        cache.set_signature_policy(self.signature_policy.clone());
+        Ok(cache)
+    }
 }
 
 pub trait DocumentExt {
     /// Get the bundle used by this document.
     ///
     /// This parses [`Document::bundle_loc`] and turns it into the appropriate
-    /// bundle backend.
+    /// bundle backend. If the document has any
+    /// [`Document::overlay_bundle_locs`], they are opened too and combined
+    /// with it into an [`OverlayBundle`].
     fn bundle(
         &self,
         setup_options: &DocumentSetupOptions,
         status: &mut dyn StatusBackend,
     ) -> Result<Box<dyn Bundle>>;
 
+    /// Get the bundle used by this document, if it is backed by the local
+    /// cache.
+    ///
+    /// This returns `None` if the document’s bundle is a local file or
+    /// directory, which don't need caching.
+    fn cached_bundle(
+        &self,
+        setup_options: &DocumentSetupOptions,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<CachingBundle<IndexedTarBackend>>>;
+
     /// Set up a [`ProcessingSessionBuilder`] for one of the outputs.
     ///
     /// The *output_profile* argument gives the name of the document’s output profile to
@@ -95,6 +132,20 @@ pub trait DocumentExt {
         setup_options: &DocumentSetupOptions,
         status: &mut dyn StatusBackend,
     ) -> Result<ProcessingSessionBuilder>;
+
+    /// Set up [`ProcessingSessionBuilder`]s for several of the outputs at once.
+    ///
+    /// This is equivalent to calling [`Self::setup_session`] for each of the
+    /// named output profiles, except that the document’s bundle is only opened
+    /// once and is shared among all of the sessions. Since format files are
+    /// cached by bundle, the first session to need a format will generate it
+    /// and the rest will reuse it.
+    fn setup_sessions(
+        &self,
+        output_profiles: &[&str],
+        setup_options: &DocumentSetupOptions,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Vec<ProcessingSessionBuilder>>;
 }
 
 impl DocumentExt for Document {
@@ -103,33 +154,62 @@ impl DocumentExt for Document {
         setup_options: &DocumentSetupOptions,
         status: &mut dyn StatusBackend,
     ) -> Result<Box<dyn Bundle>> {
-        fn bundle_from_path(p: PathBuf) -> Result<Box<dyn Bundle>> {
-            if p.is_dir() {
-                Ok(Box::new(DirBundle::new(p)))
-            } else {
-                Ok(Box::new(ZipBundle::open(p)?))
-            }
+        let bundle = if let Ok(test_bundle) = config::maybe_return_test_bundle(None) {
+            test_bundle
+        } else if let Some(bundle) = self.cached_bundle(setup_options, status)? {
+            Box::new(bundle)
+        } else {
+            local_bundle(self, &self.bundle_loc, setup_options, status)?
+        };
+
+        if self.overlay_bundle_locs.is_empty() {
+            return Ok(bundle);
+        }
+
+        let mut layers = Vec::with_capacity(self.overlay_bundle_locs.len() + 1);
+
+        for loc in &self.overlay_bundle_locs {
+            let layer: Box<dyn Bundle> = match Url::parse(loc) {
+                Ok(url) if url.scheme() != "file" => {
+                    let mut cache = setup_options.user_cache()?;
+                    Box::new(cache.open::<IndexedTarBackend>(
+                        loc,
+                        setup_options.only_cached,
+                        status,
+                    )?)
+                }
+
+                _ => local_bundle(self, loc, setup_options, status)?,
+            };
+
+            layers.push(layer);
         }
 
-        if let Ok(test_bundle) = config::maybe_return_test_bundle(None) {
-            Ok(test_bundle)
-        } else if let Ok(url) = Url::parse(&self.bundle_loc) {
-            if url.scheme() != "file" {
-                let mut cache = Cache::get_user_default()?;
+        layers.push(bundle);
+        Ok(Box::new(OverlayBundle::new(layers)))
+    }
+
+    fn cached_bundle(
+        &self,
+        setup_options: &DocumentSetupOptions,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<CachingBundle<IndexedTarBackend>>> {
+        if config::is_test_bundle_wanted(None) {
+            return Ok(None);
+        }
+
+        match Url::parse(&self.bundle_loc) {
+            Ok(url) if url.scheme() != "file" => {
+                let mut cache = setup_options.user_cache()?;
                 let bundle = cache.open::<IndexedTarBackend>(
                     &self.bundle_loc,
                     setup_options.only_cached,
                     status,
                 )?;
-                Ok(Box::new(bundle))
-            } else {
-                let file_path = url.to_file_path().map_err(|_| {
-                    io::Error::new(io::ErrorKind::InvalidInput, "failed to parse local path")
-                })?;
-                bundle_from_path(file_path)
+                Ok(Some(bundle))
             }
-        } else {
-            bundle_from_path(Path::new(&self.bundle_loc).to_owned())
+
+            _ => Ok(None),
         }
     }
 
@@ -139,73 +219,291 @@ impl DocumentExt for Document {
         setup_options: &DocumentSetupOptions,
         status: &mut dyn StatusBackend,
     ) -> Result<ProcessingSessionBuilder> {
-        let profile = self.outputs.get(output_profile).ok_or_else(|| {
-            ErrorKind::Msg(format!(
-                "unrecognized output profile name \"{output_profile}\""
-            ))
+        if setup_options.only_cached {
+            tt_note!(status, "using only cached resource files");
+        }
+
+        let bundle = self.bundle(setup_options, status)?;
+        setup_session_with_bundle(self, output_profile, setup_options, bundle)
+    }
+
+    fn setup_sessions(
+        &self,
+        output_profiles: &[&str],
+        setup_options: &DocumentSetupOptions,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Vec<ProcessingSessionBuilder>> {
+        if setup_options.only_cached {
+            tt_note!(status, "using only cached resource files");
+        }
+
+        let bundle = Rc::new(RefCell::new(self.bundle(setup_options, status)?));
+
+        output_profiles
+            .iter()
+            .map(|name| {
+                setup_session_with_bundle(self, name, setup_options, Box::new(bundle.clone()))
+            })
+            .collect()
+    }
+}
+
+/// Open a bundle stored on the local filesystem, given either a `file:` URL or
+/// a path. Relative paths are resolved relative to the document’s source
+/// directory. Indexed tar files given as `file:` URLs are opened through the
+/// cache, like web bundles.
+fn local_bundle(
+    doc: &Document,
+    loc: &str,
+    setup_options: &DocumentSetupOptions,
+    status: &mut dyn StatusBackend,
+) -> Result<Box<dyn Bundle>> {
+    let path = if let Ok(url) = Url::parse(loc) {
+        let path = url.to_file_path().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "failed to parse local path")
         })?;
 
-        let output_format = match profile.target_type {
-            BuildTargetType::Html => OutputFormat::Html,
-            BuildTargetType::Pdf => OutputFormat::Pdf,
+        if LocalIndexedTarBackend::can_open(&path) {
+            let mut cache = setup_options.user_cache()?;
+            return Ok(Box::new(cache.open::<LocalIndexedTarBackend>(
+                loc,
+                setup_options.only_cached,
+                status,
+            )?));
+        }
+
+        path
+    } else {
+        doc.src_dir().join(loc)
+    };
+
+    if path.is_dir() {
+        Ok(Box::new(DirBundle::new(path)))
+    } else if has_tar_extension(&path) {
+        Ok(Box::new(ctry!(
+            TarBundle::open(&path);
+            "couldn't open bundle `{}`", path.display()
+        )))
+    } else {
+        Ok(Box::new(ctry!(
+            ZipBundle::open(&path);
+            "couldn't open bundle `{}`", path.display()
+        )))
+    }
+}
+
+/// The guts of [`DocumentExt::setup_session`], given an already-opened bundle.
+fn setup_session_with_bundle(
+    doc: &Document,
+    output_profile: &str,
+    setup_options: &DocumentSetupOptions,
+    bundle: Box<dyn Bundle>,
+) -> Result<ProcessingSessionBuilder> {
+    let profile = doc.outputs.get(output_profile).ok_or_else(|| {
+        ErrorKind::Msg(format!(
+            "unrecognized output profile name \"{output_profile}\""
+        ))
+    })?;
+
+    let output_format = match profile.target_type {
+        BuildTargetType::Aux => OutputFormat::Aux,
+        BuildTargetType::Format => OutputFormat::Format,
+        BuildTargetType::Html => OutputFormat::Html,
+        BuildTargetType::Pdf => OutputFormat::Pdf,
+        BuildTargetType::Xdv => OutputFormat::Xdv,
+    };
+
+    let mut input_buffer = String::new();
+
+    // The document metadata are made available to the TeX code through the
+    // `\tectonicmeta{key}` macro. Nested keys are joined with dots. The macro
+    // is always defined, so that documents can use it whether or not there
+    // are any metadata. The values are defined with `\escapechar` disabled,
+    // so that the `\string` commands inserted by `escape_tex_text` produce
+    // their characters literally.
+    writeln!(
+        input_buffer,
+        "\\def\\tectonicmeta#1{{\\csname tectonicmeta@#1\\endcsname}}"
+    )?;
+
+    if let Some(metadata) = &doc.metadata {
+        let mut entries = Vec::new();
+        flatten_metadata(metadata, String::new(), &mut entries);
+        writeln!(input_buffer, "\\begingroup\\escapechar=-1")?;
+
+        for (key, value) in entries {
+            writeln!(
+                input_buffer,
+                "\\expandafter\\xdef\\csname tectonicmeta@{}\\endcsname{{{}}}",
+                escape_tex_text(&key),
+                escape_tex_text(&value)
+            )?;
+        }
+
+        writeln!(input_buffer, "\\endgroup")?;
+    }
+
+    for (name, value) in &profile.defines {
+        writeln!(input_buffer, "\\def\\{}{{{}}}", name, value)?;
+    }
+
+    for input in &profile.inputs {
+        match input {
+            InputFile::Inline(s) => {
+                writeln!(input_buffer, "{}", s)?;
+            }
+            InputFile::File(f) => {
+                writeln!(input_buffer, "\\input{{{}}}", f)?;
+            }
         };
+    }
 
-        let mut input_buffer = String::new();
+    let mut tex_dir = doc.src_dir().to_owned();
+    tex_dir.push("src");
 
-        for input in &profile.inputs {
-            match input {
-                InputFile::Inline(s) => {
-                    writeln!(input_buffer, "{}", s)?;
-                }
-                InputFile::File(f) => {
-                    writeln!(input_buffer, "\\input{{{}}}", f)?;
-                }
-            };
+    let deterministic_mode = setup_options.deterministic_mode || profile.deterministic_mode;
+
+    let mut sess_builder =
+        ProcessingSessionBuilder::new_with_security(setup_options.security.clone());
+
+    sess_builder
+        .output_format(output_format)
+        .format_name(&profile.tex_format)
+        .build_date_from_env(deterministic_mode)
+        .unstables(UnstableOptions {
+            continue_on_errors: profile.continue_on_errors,
+            paper_size: profile.paper_size.clone(),
+            png_dpi: profile.png_dpi,
+            min_crossrefs: profile.min_crossrefs,
+            index_style: profile.index_style.clone(),
+            extra_search_paths: profile
+                .search_paths
+                .iter()
+                .map(|p| tex_dir.join(p))
+                .collect(),
+            svg_embed_fonts: profile.svg_embed_fonts,
+            deterministic_mode,
+            ..Default::default()
+        })
+        .pass(PassSetting::Default)
+        .primary_input_buffer(input_buffer.as_bytes())
+        .tex_input_name(output_profile)
+        .synctex(profile.synctex)
+        .keep_intermediates(profile.keep_intermediates)
+        .keep_logs(profile.keep_logs)
+        .pdf_compression(profile.pdf_compression);
+
+    if let Some(n) = profile.reruns {
+        sess_builder.reruns(n);
+    }
+
+    let mut rerun_policy = RerunPolicy::default();
+
+    if let Some(n) = profile.max_reruns {
+        rerun_policy.max_reruns(n);
+    }
+
+    for name in &profile.rerun_ignored_files {
+        rerun_policy.ignore_file(name);
+    }
+
+    rerun_policy.error_on_nonconvergence(profile.error_on_nonconvergence);
+    sess_builder.rerun_policy(rerun_policy);
+
+    if let Some(metadata) = &doc.metadata {
+        let value = ctry!(
+            serde_json::to_value(metadata);
+            "couldn't convert the document metadata for use in HTML templates"
+        );
+        sess_builder.html_template_variable("metadata", value);
+    }
+
+    for name in &profile.hidden_files {
+        sess_builder.hide(tex_dir.join(name));
+    }
+
+    if profile.shell_escape {
+        // For now, this is the only option we allow.
+        if let Some(cwd) = &profile.shell_escape_cwd {
+            sess_builder.shell_escape_with_work_dir(cwd);
+        } else {
+            sess_builder.shell_escape_with_temp_dir();
         }
+    }
 
-        let mut sess_builder =
-            ProcessingSessionBuilder::new_with_security(setup_options.security.clone());
+    sess_builder.bundle(bundle);
+    sess_builder.filesystem_root(&tex_dir);
 
-        sess_builder
-            .output_format(output_format)
-            .format_name(&profile.tex_format)
-            .build_date_from_env(setup_options.deterministic_mode)
-            .unstables(UnstableOptions {
-                deterministic_mode: setup_options.deterministic_mode,
-                ..Default::default()
-            })
-            .pass(PassSetting::Default)
-            .primary_input_buffer(input_buffer.as_bytes())
-            .tex_input_name(output_profile);
-
-        if profile.shell_escape {
-            // For now, this is the only option we allow.
-            if let Some(cwd) = &profile.shell_escape_cwd {
-                sess_builder.shell_escape_with_work_dir(cwd);
-            } else {
-                sess_builder.shell_escape_with_temp_dir();
+    let mut output_dir = doc.build_dir().to_owned();
+    output_dir.push(output_profile);
+    ctry!(
+        fs::create_dir_all(&output_dir);
+        "couldn\'t create output directory `{}`", output_dir.display()
+    );
+    sess_builder.output_dir(&output_dir);
+
+    if doc.reuse_intermediates {
+        sess_builder.intermediates_cache_dir(output_dir.join(".tectonic-intermediates"));
+    }
+
+    Ok(sess_builder)
+}
+
+/// Flatten the document metadata into a list of key-value pairs, joining the
+/// keys of nested tables and the indices of arrays to their parents' keys with
+/// dots.
+fn flatten_metadata(value: &toml::Value, key: String, entries: &mut Vec<(String, String)>) {
+    let join = |k: &dyn std::fmt::Display| {
+        if key.is_empty() {
+            k.to_string()
+        } else {
+            format!("{key}.{k}")
+        }
+    };
+
+    match value {
+        toml::Value::Table(t) => {
+            for (k, v) in t {
+                flatten_metadata(v, join(k), entries);
             }
         }
 
-        if setup_options.only_cached {
-            tt_note!(status, "using only cached resource files");
+        toml::Value::Array(a) => {
+            for (i, v) in a.iter().enumerate() {
+                flatten_metadata(v, join(&i), entries);
+            }
         }
-        sess_builder.bundle(self.bundle(setup_options, status)?);
 
-        let mut tex_dir = self.src_dir().to_owned();
-        tex_dir.push("src");
-        sess_builder.filesystem_root(&tex_dir);
+        toml::Value::String(s) => entries.push((key, s.clone())),
 
-        let mut output_dir = self.build_dir().to_owned();
-        output_dir.push(output_profile);
-        ctry!(
-            fs::create_dir_all(&output_dir);
-            "couldn\'t create output directory `{}`", output_dir.display()
-        );
-        sess_builder.output_dir(&output_dir);
+        other => entries.push((key, other.to_string())),
+    }
+}
 
-        Ok(sess_builder)
+/// Escape text so that TeX reads it literally, as by `\detokenize`, when
+/// `\escapechar` is disabled.
+///
+/// Characters with special meanings, such as `%` and unbalanced braces, are
+/// written as `\string` applied to a control symbol, which yields the
+/// character itself with category code 12 (other). Line breaks become spaces,
+/// so that the result fits on one line.
+fn escape_tex_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' | '$' | '&' | '#' | '^' | '_' | '%' | '~' => {
+                escaped.push_str("\\string\\");
+                escaped.push(c);
+            }
+
+            '\n' | '\r' => escaped.push(' '),
+
+            c => escaped.push(c),
+        }
     }
+
+    escaped
 }
 
 /// Extension methods for [`WorkspaceCreator`].
@@ -241,3 +539,50 @@ impl WorkspaceCreatorExt for WorkspaceCreator {
         Ok(self.create(bundle_loc)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_flattening() {
+        let metadata: toml::Value = toml::from_str(
+            r#"
+            title = "A Title"
+            version = 3
+            authors = ["A", "B"]
+
+            [build]
+            id = "abc"
+            "#,
+        )
+        .unwrap();
+
+        let mut entries = Vec::new();
+        flatten_metadata(&metadata, String::new(), &mut entries);
+        entries.sort();
+
+        let expected = [
+            ("authors.0", "A"),
+            ("authors.1", "B"),
+            ("build.id", "abc"),
+            ("title", "A Title"),
+            ("version", "3"),
+        ];
+        let expected: Vec<(String, String)> = expected
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn tex_escaping() {
+        assert_eq!(escape_tex_text("A Title"), "A Title");
+        assert_eq!(
+            escape_tex_text("50% {of} #1\\x"),
+            "50\\string\\% \\string\\{of\\string\\} \\string\\#1\\string\\\\x"
+        );
+        assert_eq!(escape_tex_text("a\nb"), "a b");
+    }
+}
@@ -17,8 +17,15 @@
 
 use byte_unit::{Byte, UnitType};
 use quick_xml::{events::Event, NsReader};
+#[cfg(feature = "serialization")]
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+#[cfg(feature = "serialization")]
+use std::collections::BTreeMap;
 use std::{
     collections::{HashMap, HashSet},
+    fmt::{self, Arguments},
     fs::File,
     io::{Cursor, Read, Write},
     path::{Path, PathBuf},
@@ -26,13 +33,14 @@ use std::{
     rc::Rc,
     result::Result as StdResult,
     str::FromStr,
-    time::{Duration, SystemTime},
+    time::{Duration, Instant, SystemTime},
 };
 use tectonic_bridge_core::{CoreBridgeLauncher, DriverHooks, SecuritySettings, SystemRequestError};
 use tectonic_bundles::Bundle;
-use tectonic_engine_spx2html::AssetSpecification;
+use tectonic_engine_spx2html::{AssetSpecification, TemplateValue};
+use tectonic_errors::Error as NewError;
 use tectonic_io_base::{
-    digest::DigestData,
+    digest::{self, Digest, DigestData},
     filesystem::{FilesystemIo, FilesystemPrimaryInputIo},
     stdstreams::{BufferedPrimaryIo, GenuineStdoutIo},
     InputHandle, IoProvider, OpenResult, OutputHandle,
@@ -42,21 +50,24 @@ use crate::{
     ctry, errmsg,
     errors::{ChainErrCompatExt, ErrorKind, Result},
     io::{
-        format_cache::FormatCache,
+        format_cache::{prefetch_format_sources, FormatCache},
         memory::{MemoryFileCollection, MemoryIo},
         InputOrigin,
     },
-    status::StatusBackend,
+    status::{MessageKind, StatusBackend},
     tt_error, tt_note, tt_warning,
     unstable_opts::UnstableOptions,
-    BibtexEngine, Spx2HtmlEngine, TexEngine, TexOutcome, XdvipdfmxEngine,
+    BibtexEngine, MakeindexEngine, MakeindexOutcome, Spx2HtmlEngine, TexEngine, TexOutcome,
+    Xdv2PngEngine, Xdv2SvgEngine, Xdv2TextEngine, XdvipdfmxEngine,
 };
 
 /// Different patterns with which files may have been accessed by the
 /// underlying engines. Once a file is marked as ReadThenWritten or
 /// WrittenThenRead, its pattern does not evolve further.
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum AccessPattern {
+pub enum AccessPattern {
     /// This file is only ever read.
     Read,
 
@@ -115,6 +126,252 @@ impl FileSummary {
     }
 }
 
+/// Where a file touched during a build came from, as recorded in a
+/// [`BuildReport`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileOrigin {
+    /// The file lives on the filesystem.
+    Filesystem,
+
+    /// The file was never used as an input.
+    NotInput,
+
+    /// The file came from somewhere else, such as the bundle or the engines'
+    /// own outputs.
+    Other,
+}
+
+impl From<InputOrigin> for FileOrigin {
+    fn from(o: InputOrigin) -> Self {
+        match o {
+            InputOrigin::Filesystem => FileOrigin::Filesystem,
+            InputOrigin::NotInput => FileOrigin::NotInput,
+            InputOrigin::Other => FileOrigin::Other,
+        }
+    }
+}
+
+/// A machine-readable summary of a processing session.
+///
+/// Obtain one with [`ProcessingSession::build_report`], or have the session
+/// save one in JSON format with
+/// [`ProcessingSessionBuilder::build_report_path`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, Default)]
+pub struct BuildReport {
+    /// The name of the primary input file, as TeX knows it.
+    pub primary_input: String,
+
+    /// Whether the session completed successfully.
+    pub succeeded: bool,
+
+    /// The total wall-clock duration of the session, in seconds.
+    pub duration_secs: f64,
+
+    /// The engine passes that were run, in order.
+    pub passes: Vec<PassReport>,
+
+    /// Every file touched by the engines, sorted by name.
+    pub files: Vec<FileReport>,
+
+    /// The warnings that were issued during processing.
+    pub warnings: Vec<String>,
+
+    /// The errors that were issued during processing.
+    pub errors: Vec<String>,
+}
+
+/// Information about one engine pass in a [`BuildReport`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug, Default)]
+pub struct PassReport {
+    /// The engine that was run, such as `tex` or `bibtex`.
+    pub engine: String,
+
+    /// The main input of the pass, if it is not implied by the engine.
+    pub input: Option<String>,
+
+    /// Why this pass was run, if it was a rerun.
+    pub rerun_reason: Option<String>,
+
+    /// The wall-clock duration of the pass, in seconds.
+    pub duration_secs: f64,
+}
+
+/// Information about one file in a [`BuildReport`].
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[derive(Clone, Debug)]
+pub struct FileReport {
+    /// The name of the file, as the engines know it.
+    pub name: String,
+
+    /// How the file was accessed.
+    pub access_pattern: AccessPattern,
+
+    /// Where the file came from, if it was read.
+    pub origin: FileOrigin,
+
+    /// The hex digest of the file's contents when it was first read in the
+    /// final pass of the engine that read it, if known.
+    pub read_digest: Option<String>,
+
+    /// The hex digest of the file's contents when it was last written, if it
+    /// was written.
+    pub write_digest: Option<String>,
+
+    /// Whether the file was saved to disk at the end of the session.
+    pub written_to_disk: bool,
+}
+
+/// A status backend that passes everything through to another one, while
+/// remembering warnings and errors for the build report.
+struct RecordingStatusBackend<'a> {
+    inner: &'a mut dyn StatusBackend,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl<'a> RecordingStatusBackend<'a> {
+    fn new(inner: &'a mut dyn StatusBackend) -> Self {
+        RecordingStatusBackend {
+            inner,
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl StatusBackend for RecordingStatusBackend<'_> {
+    fn report(&mut self, kind: MessageKind, args: Arguments, err: Option<&NewError>) {
+        let dest = match kind {
+            MessageKind::Note => None,
+            MessageKind::Warning => Some(&mut self.warnings),
+            MessageKind::Error => Some(&mut self.errors),
+        };
+
+        if let Some(dest) = dest {
+            match err {
+                Some(e) => dest.push(format!("{args}: {e}")),
+                None => dest.push(args.to_string()),
+            }
+        }
+
+        self.inner.report(kind, args, err)
+    }
+
+    fn note_highlighted(&mut self, before: &str, highlighted: &str, after: &str) {
+        self.inner.note_highlighted(before, highlighted, after)
+    }
+
+    fn dump_error_logs(&mut self, output: &[u8]) {
+        self.inner.dump_error_logs(output)
+    }
+}
+
+/// The information saved after a successful build so that a subsequent
+/// build can tell whether it would be a no-op.
+///
+/// Digests are stored as hex strings.
+#[cfg(feature = "serialization")]
+#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct BuildManifest {
+    /// A digest of the session settings that can affect the outputs.
+    settings: String,
+    bundle: String,
+    format: String,
+    primary_input: String,
+
+    /// The digests of the files that the engines looked for in the
+    /// filesystem, keyed by name. A file that wasn't found there has the
+    /// digest of an empty file.
+    inputs: BTreeMap<String, String>,
+
+    /// The names of the files written to the output directory.
+    outputs: Vec<String>,
+}
+
+/// Load cached intermediate files from *dir* into the memory layer,
+/// recursing into subdirectories. *prefix* is the TeX-style path of *dir*
+/// relative to the root of the cache.
+fn seed_intermediates(
+    mem: &mut MemoryIo,
+    dir: &Path,
+    prefix: &str,
+    seeded: &mut HashSet<String>,
+) -> StdResult<(), NewError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let fname = match entry.file_name().into_string() {
+            Ok(s) => s,
+            Err(_) => continue,
+        };
+        let name = format!("{prefix}{fname}");
+
+        if entry.file_type()?.is_dir() {
+            seed_intermediates(mem, &entry.path(), &format!("{name}/"), seeded)?;
+        } else if SEEDABLE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+            mem.create_entry(&name, std::fs::read(entry.path())?);
+            seeded.insert(name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Load the record of the files that BibTeX read when it generated the
+/// bibliography in the intermediates cache *dir*. If the record is missing or
+/// unreadable, an empty map is returned, so that the bibliography is
+/// regenerated.
+fn load_bibtex_input_digests(dir: &Path) -> HashMap<String, DigestData> {
+    let text = match std::fs::read_to_string(dir.join(BIBTEX_INPUTS_RECORD)) {
+        Ok(t) => t,
+        Err(_) => return HashMap::new(),
+    };
+
+    let mut digests = HashMap::new();
+
+    for line in text.lines() {
+        let parsed = line
+            .split_once(' ')
+            .and_then(|(digest, name)| Some((name.to_owned(), digest.parse().ok()?)));
+
+        match parsed {
+            Some((name, digest)) => {
+                digests.insert(name, digest);
+            }
+            None => return HashMap::new(),
+        }
+    }
+
+    digests
+}
+
+/// Compute the digest of the remaining contents of an input handle.
+fn digest_of_input(mut ih: InputHandle) -> Result<DigestData> {
+    let mut dc = digest::create();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = ih.read(&mut buf)?;
+
+        if n == 0 {
+            break;
+        }
+
+        dc.update(&buf[..n]);
+    }
+
+    Ok(DigestData::from(dc))
+}
+
 /// The different types of output files that tectonic knows how to produce.
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub enum OutputFormat {
@@ -127,6 +384,12 @@ pub enum OutputFormat {
     /// A '.pdf' file.
     #[default]
     Pdf,
+    /// One '.svg' file per page.
+    Svg,
+    /// One '.png' file per page.
+    Png,
+    /// One '.txt' file per page.
+    Text,
     /// A '.fmt' file, for initializing the TeX engine.
     Format,
 }
@@ -140,6 +403,9 @@ impl FromStr for OutputFormat {
             "html" => Ok(OutputFormat::Html),
             "xdv" => Ok(OutputFormat::Xdv),
             "pdf" => Ok(OutputFormat::Pdf),
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "txt" => Ok(OutputFormat::Text),
             "fmt" => Ok(OutputFormat::Format),
             _ => Err("unsupported or unknown format"),
         }
@@ -805,20 +1071,28 @@ pub struct ProcessingSessionBuilder {
     format_name: Option<String>,
     format_cache_path: Option<PathBuf>,
     output_format: OutputFormat,
+    extra_output_formats: Vec<OutputFormat>,
     makefile_output_path: Option<PathBuf>,
+    build_report_path: Option<PathBuf>,
+    build_manifest_path: Option<PathBuf>,
+    intermediates_cache_dir: Option<PathBuf>,
     hidden_input_paths: HashSet<PathBuf>,
     pass: PassSetting,
     reruns: Option<usize>,
+    rerun_policy: RerunPolicy,
     print_stdout: bool,
     bundle: Option<Box<dyn Bundle>>,
     keep_intermediates: bool,
     keep_logs: bool,
     synctex: bool,
+    pdf_do_not_compress: bool,
     build_date: Option<SystemTime>,
+    build_date_is_current_time: bool,
     unstables: UnstableOptions,
     shell_escape_mode: ShellEscapeMode,
     html_assets_spec_path: Option<String>,
     html_precomputed_assets: Option<AssetSpecification>,
+    html_template_variables: Vec<(String, TemplateValue)>,
     html_do_not_emit_files: bool,
     html_do_not_emit_assets: bool,
 }
@@ -917,12 +1191,66 @@ impl ProcessingSessionBuilder {
         self
     }
 
+    /// Request an additional type of output from the same run of the TeX
+    /// engine.
+    ///
+    /// For instance, requesting [`OutputFormat::Xdv`] in addition to
+    /// [`OutputFormat::Pdf`] keeps the XDV file that is converted into the
+    /// PDF, and requesting [`OutputFormat::Aux`] keeps the `.aux` file. HTML
+    /// output needs the TeX engine to be run in a different mode, so it can't
+    /// be combined with XDV output or with PDF, SVG, PNG, or text output,
+    /// which are all derived from XDV; a document that needs both must be
+    /// processed by two sessions. Format files can't be combined with
+    /// anything. [`Self::create`] will fail if incompatible outputs are
+    /// requested.
+    pub fn extra_output_format(&mut self, f: OutputFormat) -> &mut Self {
+        if f != self.output_format && !self.extra_output_formats.contains(&f) {
+            self.extra_output_formats.push(f);
+        }
+        self
+    }
+
     /// If set, a makefile will be written out at the given path.
     pub fn makefile_output_path<P: AsRef<Path>>(&mut self, p: P) -> &mut Self {
         self.makefile_output_path = Some(p.as_ref().to_owned());
         self
     }
 
+    /// If set, a [`BuildReport`] will be written out in JSON format at the
+    /// given path when the session finishes, whether or not it succeeds.
+    pub fn build_report_path<P: AsRef<Path>>(&mut self, p: P) -> &mut Self {
+        self.build_report_path = Some(p.as_ref().to_owned());
+        self
+    }
+
+    /// If set, enable incremental builds using a manifest file at the given
+    /// path.
+    ///
+    /// After a successful build, the session records the digests of its
+    /// inputs, the bundle, and the format file in the manifest. If a later
+    /// session finds that none of these have changed, that its settings are
+    /// the same, and that all of the previous outputs still exist, it skips
+    /// processing entirely. Sessions that use shell-escape or external tools
+    /// such as `biber` can't reliably track their inputs, so they never
+    /// record a manifest.
+    pub fn build_manifest_path<P: AsRef<Path>>(&mut self, p: P) -> &mut Self {
+        self.build_manifest_path = Some(p.as_ref().to_owned());
+        self
+    }
+
+    /// If set, carry intermediate files such as `.aux`, `.toc`, and `.bbl`
+    /// over from one session to the next using the given directory.
+    ///
+    /// When the session starts, any such files in the directory are loaded
+    /// into the in-memory I/O layer, as if a previous TeX pass had just
+    /// written them. After a successful session, the directory is updated
+    /// with the latest versions. A document whose cross-references haven't
+    /// changed can then be built with a single TeX pass.
+    pub fn intermediates_cache_dir<P: AsRef<Path>>(&mut self, p: P) -> &mut Self {
+        self.intermediates_cache_dir = Some(p.as_ref().to_owned());
+        self
+    }
+
     /// Which kind of pass should the `ProcessingSession` run? Defaults to `PassSetting::Default`
     /// (duh).
     pub fn pass(&mut self, p: PassSetting) -> &mut Self {
@@ -939,6 +1267,15 @@ impl ProcessingSessionBuilder {
         self
     }
 
+    /// Set the policy used to decide whether the TeX engine needs to be rerun
+    /// when the number of reruns is auto-detected.
+    ///
+    /// See [`RerunPolicy`] for the defaults.
+    pub fn rerun_policy(&mut self, policy: RerunPolicy) -> &mut Self {
+        self.rerun_policy = policy;
+        self
+    }
+
     /// If set to `true`, stdout from the TeX engine will be forwarded to actual stdout. (By
     /// default, it will be suppressed.)
     pub fn print_stdout(&mut self, p: bool) -> &mut Self {
@@ -966,7 +1303,7 @@ impl ProcessingSessionBuilder {
         self
     }
 
-    /// If set to `true`, '.log' and '.blg' files will be written out to the filesystem.
+    /// If set to `true`, '.log', '.blg', and '.ilg' files will be written out to the filesystem.
     pub fn keep_logs(&mut self, k: bool) -> &mut Self {
         self.keep_logs = k;
         self
@@ -978,10 +1315,19 @@ impl ProcessingSessionBuilder {
         self
     }
 
+    /// Set whether the streams in PDF output should be compressed. This is
+    /// enabled by default; disabling it makes the PDF larger, but easier to
+    /// inspect.
+    pub fn pdf_compression(&mut self, enable: bool) -> &mut Self {
+        self.pdf_do_not_compress = !enable;
+        self
+    }
+
     /// Sets the date and time of the processing session.
     /// See `TexEngine::build_date` for mor information.
     pub fn build_date(&mut self, date: SystemTime) -> &mut Self {
         self.build_date = Some(date);
+        self.build_date_is_current_time = false;
         self
     }
 
@@ -991,20 +1337,23 @@ impl ProcessingSessionBuilder {
     /// Otherwise, we use the current system time.
     pub fn build_date_from_env(&mut self, force_deterministic: bool) -> &mut Self {
         let build_date_str = std::env::var("SOURCE_DATE_EPOCH").ok();
-        let build_date = match (force_deterministic, build_date_str) {
+        let (build_date, is_current_time) = match (force_deterministic, build_date_str) {
             (_, Some(s)) => {
                 let epoch = s
                     .parse::<u64>()
                     .expect("invalid SOURCE_DATE_EPOCH (not a number)");
 
-                SystemTime::UNIX_EPOCH
+                let date = SystemTime::UNIX_EPOCH
                     .checked_add(Duration::from_secs(epoch))
-                    .expect("time overflow")
+                    .expect("time overflow");
+                (date, false)
             }
-            (true, None) => SystemTime::UNIX_EPOCH,
-            (false, None) => SystemTime::now(),
+            (true, None) => (SystemTime::UNIX_EPOCH, false),
+            (false, None) => (SystemTime::now(), true),
         };
-        self.build_date(build_date)
+        self.build_date(build_date);
+        self.build_date_is_current_time = is_current_time;
+        self
     }
 
     /// Loads unstable options into the processing session
@@ -1086,6 +1435,20 @@ impl ProcessingSessionBuilder {
         self
     }
 
+    /// Make a variable available to the templates used to create HTML
+    /// output.
+    ///
+    /// Variables set by the document itself take precedence over ones set
+    /// here.
+    pub fn html_template_variable<S: ToString>(
+        &mut self,
+        name: S,
+        value: TemplateValue,
+    ) -> &mut Self {
+        self.html_template_variables.push((name.to_string(), value));
+        self
+    }
+
     /// Set whether templated outputs should be created during HTML processing.
     ///
     /// This mode can be useful if you want to analyze what *would* be created
@@ -1109,6 +1472,45 @@ impl ProcessingSessionBuilder {
 
     /// Creates a `ProcessingSession`.
     pub fn create(self, status: &mut dyn StatusBackend) -> Result<ProcessingSession> {
+        // Make sure that all of the requested outputs can come out of the
+        // same pipeline.
+
+        let all_formats = || std::iter::once(&self.output_format).chain(&self.extra_output_formats);
+        let wants_html = all_formats().any(|f| *f == OutputFormat::Html);
+
+        if !self.extra_output_formats.is_empty() {
+            if all_formats().any(|f| *f == OutputFormat::Format) {
+                return Err(errmsg!(
+                    "format files can't be generated along with other outputs"
+                ));
+            }
+
+            // Everything other than HTML is plain XDV or derived from it,
+            // while HTML needs the TeX engine to emit SPX.
+            let xdv_based = [
+                (OutputFormat::Xdv, "XDV"),
+                (OutputFormat::Pdf, "PDF"),
+                (OutputFormat::Svg, "SVG"),
+                (OutputFormat::Png, "PNG"),
+                (OutputFormat::Text, "text"),
+            ];
+
+            for (xf, desc) in &xdv_based {
+                if wants_html && all_formats().any(|f| f == xf) {
+                    return Err(errmsg!(
+                        "HTML and {} outputs can't be generated by the same session",
+                        desc
+                    ));
+                }
+            }
+        }
+
+        if self.rerun_policy.max_reruns == 0 {
+            return Err(errmsg!(
+                "the rerun policy must allow at least one TeX rerun"
+            ));
+        }
+
         // First, work on the "bridge state", which gathers the subset of our
         // state that has to be held in a mutable reference while running the
         // C/C++ engines:
@@ -1171,6 +1573,13 @@ impl ProcessingSessionBuilder {
         // move this out of self to get around borrow checker issues
         let hidden_input_paths = self.hidden_input_paths;
 
+        #[cfg(feature = "serialization")]
+        let sorted_hidden_input_paths = {
+            let mut paths: Vec<_> = hidden_input_paths.iter().cloned().collect();
+            paths.sort();
+            paths
+        };
+
         let extra_search_paths = if self.security.allow_extra_search_paths() {
             self.unstables
                 .extra_search_paths
@@ -1186,7 +1595,17 @@ impl ProcessingSessionBuilder {
 
         let filesystem = FilesystemIo::new(&filesystem_root, false, true, hidden_input_paths);
 
-        let mem = MemoryIo::new(true);
+        let mut mem = MemoryIo::new(true);
+        let mut seeded_files = HashSet::new();
+        let mut bibtex_input_digests = HashMap::new();
+
+        if let Some(ref dir) = self.intermediates_cache_dir {
+            if let Err(e) = seed_intermediates(&mut mem, dir, "", &mut seeded_files) {
+                tt_warning!(status, "couldn't load cached intermediate files from `{}`", dir.display(); e);
+            }
+
+            bibtex_input_digests = load_bibtex_input_digests(dir);
+        }
 
         let bs = BridgeState {
             primary_input: pio,
@@ -1215,13 +1634,10 @@ impl ProcessingSessionBuilder {
         let mut aux_path = PathBuf::from(tex_input_name.clone());
         aux_path.set_extension("aux");
         let mut xdv_path = aux_path.clone();
-        xdv_path.set_extension(if self.output_format == OutputFormat::Html {
-            "spx"
-        } else {
-            "xdv"
-        });
+        xdv_path.set_extension(if wants_html { "spx" } else { "xdv" });
         let mut pdf_path = aux_path.clone();
         pdf_path.set_extension("pdf");
+        let image_stem = aux_path.with_extension("");
 
         let shell_escape_mode = if !self.security.allow_shell_escape() {
             ShellEscapeMode::Disabled
@@ -1246,34 +1662,233 @@ impl ProcessingSessionBuilder {
             bs,
             pass: self.pass,
             primary_input_path,
-            primary_input_tex_path: tex_input_name,
+            primary_input_tex_path: tex_input_name.clone(),
             format_name: self.format_name.unwrap(),
             tex_aux_path: aux_path.display().to_string(),
             tex_xdv_path: xdv_path.display().to_string(),
             tex_pdf_path: pdf_path.display().to_string(),
+            tex_image_stem: image_stem.display().to_string(),
             output_format: self.output_format,
+            extra_output_formats: self.extra_output_formats,
             makefile_output_path: self.makefile_output_path,
+            build_report_path: self.build_report_path,
+            build_manifest_path: self.build_manifest_path,
+            intermediates_cache_dir: self.intermediates_cache_dir,
+            seeded_files,
+            bibtex_input_digests,
             output_path,
             tex_rerun_specification: self.reruns,
+            rerun_policy: self.rerun_policy,
             keep_intermediates: self.keep_intermediates,
             keep_logs: self.keep_logs,
             synctex_enabled: self.synctex,
+            pdf_compression: !self.pdf_do_not_compress,
             build_date: self.build_date.unwrap_or(SystemTime::UNIX_EPOCH),
+            #[cfg(feature = "serialization")]
+            build_date_is_current_time: self.build_date_is_current_time,
+            #[cfg(feature = "serialization")]
+            hidden_input_paths: sorted_hidden_input_paths,
             unstables: self.unstables,
             shell_escape_mode,
             html_assets_spec_path: self.html_assets_spec_path,
             html_precomputed_assets: self.html_precomputed_assets,
+            html_template_variables: self.html_template_variables,
             html_emit_files: !self.html_do_not_emit_files,
             html_emit_assets: !self.html_do_not_emit_assets,
+            index_digests: HashMap::new(),
+            index_log_files: HashSet::new(),
+            warned_about_xindy: false,
+            used_untracked_tool: false,
+            report: BuildReport {
+                primary_input: tex_input_name.clone(),
+                ..BuildReport::default()
+            },
         })
     }
 }
 
+/// The glossaries that the `glossaries` package wants sorted.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct GlossariesRequirement {
+    /// The name of the style file written out by the package.
+    style: String,
+
+    /// Whether keys should be sorted with "letter ordering".
+    letter_ordering: bool,
+
+    /// The log, output, and input file extensions of each glossary.
+    glossaries: Vec<(String, String, String)>,
+}
+
+/// Extract the contents of a sequence of brace-delimited TeX arguments, such
+/// as `{a}{b}{c}`, ignoring anything after the last complete one. Escaped
+/// braces, such as `\{`, don't delimit anything.
+fn tex_group_args(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut chars = text.trim_start().chars();
+
+    while chars.next() == Some('{') {
+        let mut depth = 0;
+        let mut arg = String::new();
+        let mut closed = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    arg.push(c);
+
+                    if let Some(escaped) = chars.next() {
+                        arg.push(escaped);
+                    }
+
+                    continue;
+                }
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    closed = true;
+                    break;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+
+            arg.push(c);
+        }
+
+        if !closed {
+            break;
+        }
+
+        args.push(arg);
+    }
+
+    args
+}
+
+/// Parse the requirements that the `glossaries` package records in the main
+/// `.aux` file, which `makeglossaries` consults in the same way. Returns
+/// `None` if the package doesn't ask for any glossaries to be sorted.
+fn parse_glossaries_requirement(aux: &str) -> Option<GlossariesRequirement> {
+    let mut style = None;
+    let mut letter_ordering = false;
+    let mut glossaries = Vec::new();
+
+    for line in aux.lines() {
+        if let Some(rest) = line.strip_prefix("\\@istfilename") {
+            style = tex_group_args(rest).into_iter().next();
+        } else if let Some(rest) = line.strip_prefix("\\@glsorder") {
+            letter_ordering = tex_group_args(rest).first().map(|s| s.as_str()) == Some("letter");
+        } else if let Some(rest) = line.strip_prefix("\\@newglossary") {
+            // Arguments are: label, log extension, output extension, input extension.
+            if let [_, log_ext, out_ext, in_ext] = &tex_group_args(rest)[..] {
+                glossaries.push((log_ext.clone(), out_ext.clone(), in_ext.clone()));
+            }
+        }
+    }
+
+    if glossaries.is_empty() {
+        return None;
+    }
+
+    Some(GlossariesRequirement {
+        style: style?,
+        letter_ordering,
+        glossaries,
+    })
+}
+
+/// Settings controlling how many times, and why, the TeX engine is rerun.
+///
+/// The defaults match the historical behavior: up to six TeX reruns, no files
+/// ignored, and a warning (not an error) if the document still hasn't
+/// converged after the final pass. These settings only matter if the pass is
+/// [`PassSetting::Default`] and a fixed number of reruns hasn't been requested
+/// with [`ProcessingSessionBuilder::reruns`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RerunPolicy {
+    max_reruns: usize,
+    ignored_files: Vec<String>,
+    error_on_nonconvergence: bool,
+}
+
+impl Default for RerunPolicy {
+    fn default() -> Self {
+        RerunPolicy {
+            max_reruns: DEFAULT_MAX_TEX_RERUNS,
+            ignored_files: Vec::new(),
+            error_on_nonconvergence: false,
+        }
+    }
+}
+
+impl RerunPolicy {
+    /// Set the maximum number of times that TeX will be rerun after its
+    /// first pass.
+    ///
+    /// This must be at least one: a policy allowing zero reruns is rejected
+    /// when the session is created. To prevent reruns altogether, use
+    /// [`ProcessingSessionBuilder::reruns`] instead.
+    pub fn max_reruns(&mut self, n: usize) -> &mut Self {
+        self.max_reruns = n;
+        self
+    }
+
+    /// Don't rerun TeX just because the named file changed.
+    ///
+    /// This is useful for files whose contents churn on every pass without
+    /// affecting the output, such as a table of contents that embeds a
+    /// timestamp. If *name* starts with `*`, the rest of it is matched
+    /// against the end of each file name, so that `*.toc` ignores all
+    /// `.toc` files.
+    pub fn ignore_file<S: ToString>(&mut self, name: S) -> &mut Self {
+        self.ignored_files.push(name.to_string());
+        self
+    }
+
+    /// If set to `true`, it is an error for the document to still need a
+    /// rerun after the maximum number of passes. Otherwise, a warning is
+    /// issued and processing continues with the results of the last pass.
+    pub fn error_on_nonconvergence(&mut self, value: bool) -> &mut Self {
+        self.error_on_nonconvergence = value;
+        self
+    }
+
+    fn is_ignored(&self, name: &str) -> bool {
+        self.ignored_files
+            .iter()
+            .any(|pat| match pat.strip_prefix('*') {
+                Some(suffix) => name.ends_with(suffix),
+                None => name == pat,
+            })
+    }
+}
+
 #[derive(Debug, Clone)]
 enum RerunReason {
     Biber,
     Bibtex,
-    FileChange(String),
+    /// The names of all of the files that changed, sorted.
+    FileChange(Vec<String>),
+}
+
+impl fmt::Display for RerunReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RerunReason::Biber => f.write_str("biber was run"),
+            RerunReason::Bibtex => f.write_str("bibtex was run"),
+            RerunReason::FileChange(names) => {
+                for (i, name) in names.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(if i == names.len() - 1 { " and " } else { ", " })?;
+                    }
+
+                    write!(f, "\"{name}\"")?;
+                }
+
+                f.write_str(" changed")
+            }
+        }
+    }
 }
 
 /// The ProcessingSession struct runs the whole show when we're actually
@@ -1305,10 +1920,34 @@ pub struct ProcessingSession {
     tex_xdv_path: String,
     tex_pdf_path: String,
 
+    /// The base name of the per-page output files (SVG, PNG, and text),
+    /// without the file extension or any page number suffix.
+    tex_image_stem: String,
+
     /// If we're writing out Makefile rules, this is where they go. The TeX
     /// engine doesn't know about this path at all.
     makefile_output_path: Option<PathBuf>,
 
+    /// If we're writing out a build report, this is where it goes.
+    build_report_path: Option<PathBuf>,
+
+    /// If we're doing incremental builds, this is where the manifest of the
+    /// previous build lives.
+    build_manifest_path: Option<PathBuf>,
+
+    /// If we're carrying intermediate files over between sessions, this is
+    /// where they're kept.
+    intermediates_cache_dir: Option<PathBuf>,
+
+    /// The names of the files that were loaded from the intermediates cache
+    /// when the session was created.
+    seeded_files: HashSet<String>,
+
+    /// The digests of the files, other than `.aux` files, that BibTeX read
+    /// when it last generated the bibliography. These are loaded from the
+    /// intermediates cache and updated whenever BibTeX runs.
+    bibtex_input_digests: HashMap<String, DigestData>,
+
     /// This is the path that the processed file will be saved at. It defaults
     /// to the path of `primary_input_path` or `.` if STDIN is used. If set to
     /// None, the output files will not be saved to disk — in which case, the
@@ -1318,14 +1957,27 @@ pub struct ProcessingSession {
 
     pass: PassSetting,
     output_format: OutputFormat,
+    extra_output_formats: Vec<OutputFormat>,
     tex_rerun_specification: Option<usize>,
+    rerun_policy: RerunPolicy,
     keep_intermediates: bool,
     keep_logs: bool,
     synctex_enabled: bool,
+    pdf_compression: bool,
 
     /// See `TexEngine::with_date` and `XdvipdfmxEngine::with_date`.
     build_date: SystemTime,
 
+    /// Whether the build date is just the current time, in which case it
+    /// shouldn't affect whether a build is considered up-to-date.
+    #[cfg(feature = "serialization")]
+    build_date_is_current_time: bool,
+
+    /// The paths hidden from the filesystem I/O layers, sorted, for the build
+    /// manifest.
+    #[cfg(feature = "serialization")]
+    hidden_input_paths: Vec<PathBuf>,
+
     unstables: UnstableOptions,
 
     /// How to handle shell-escape. The `Defaulted` option will never
@@ -1334,11 +1986,39 @@ pub struct ProcessingSession {
 
     html_assets_spec_path: Option<String>,
     html_precomputed_assets: Option<AssetSpecification>,
+    html_template_variables: Vec<(String, TemplateValue)>,
     html_emit_files: bool,
     html_emit_assets: bool,
+
+    /// The digests of the index engine inputs (e.g., `.idx` files) as of the
+    /// last time that we ran the engine on them, so that we only regenerate
+    /// indices and glossaries that might have changed.
+    index_digests: HashMap<String, DigestData>,
+
+    /// The transcript files written by the index engine, which are treated
+    /// like other log files.
+    index_log_files: HashSet<String>,
+
+    warned_about_xindy: bool,
+
+    /// Set if we ran a tool, such as biber, whose file accesses we can't
+    /// track. In that case we can't safely do incremental builds.
+    used_untracked_tool: bool,
+
+    /// The parts of the build report that we accumulate as we go. The file
+    /// information is filled in by [`Self::build_report`].
+    report: BuildReport,
 }
 
-const DEFAULT_MAX_TEX_PASSES: usize = 6;
+const DEFAULT_MAX_TEX_RERUNS: usize = 6;
+
+/// Intermediate files that are worth carrying over between sessions, since
+/// they save TeX passes.
+const SEEDABLE_EXTENSIONS: &[&str] = &[".aux", ".bbl", ".toc", ".lof", ".lot"];
+
+/// The name of the file in the intermediates cache that records the digests
+/// of the files that BibTeX read, one per line, in the form `<digest> <name>`.
+const BIBTEX_INPUTS_RECORD: &str = ".bibtex-inputs";
 const ALWAYS_INTERMEDIATE_EXTENSIONS: &[&str] = &[
     ".snm", ".toc", // generated by Beamer
 ];
@@ -1348,9 +2028,7 @@ impl ProcessingSession {
     /// was a file that the engine read and then rewrote, and the rewritten
     /// version is different than the version that it read in.
     fn is_rerun_needed(&self, status: &mut dyn StatusBackend) -> Option<RerunReason> {
-        // TODO: we should probably wire up diagnostics since I expect this
-        // stuff could get finicky and we're going to want to be able to
-        // figure out why rerun detection is breaking.
+        let mut changed = Vec::new();
 
         for (name, info) in &self.bs.events {
             if info.access_pattern == AccessPattern::ReadThenWritten {
@@ -1369,69 +2047,390 @@ impl ProcessingSession {
                 };
 
                 if file_changed {
-                    return Some(RerunReason::FileChange(name.clone()));
+                    if self.rerun_policy.is_ignored(name) {
+                        tt_note!(
+                            status,
+                            "ignoring change to \"{}\" per the rerun policy",
+                            name
+                        );
+                    } else {
+                        changed.push(name.clone());
+                    }
                 }
             }
         }
 
-        None
+        if changed.is_empty() {
+            None
+        } else {
+            changed.sort();
+            Some(RerunReason::FileChange(changed))
+        }
     }
 
-    #[allow(dead_code)]
-    fn _dump_access_info(&self, status: &mut dyn StatusBackend) {
-        for (name, info) in &self.bs.events {
-            if info.access_pattern != AccessPattern::Read {
-                let r = match info.read_digest {
-                    Some(ref d) => d.to_string(),
-                    None => "-".into(),
-                };
-                let w = match info.write_digest {
-                    Some(ref d) => d.to_string(),
-                    None => "-".into(),
-                };
-                tt_note!(
-                    status,
-                    "ACCESS: {} {:?} {:?} {:?}",
-                    name,
-                    info.access_pattern,
-                    r,
-                    w
-                );
-            }
-        }
+    /// Get a report describing what happened during this session.
+    ///
+    /// This is most useful after [`Self::run`] has been called, whether or not
+    /// it succeeded.
+    pub fn build_report(&self) -> BuildReport {
+        let mut report = self.report.clone();
+
+        report.files = self
+            .bs
+            .events
+            .iter()
+            .filter(|(name, _)| !name.is_empty())
+            .map(|(name, info)| FileReport {
+                name: name.clone(),
+                access_pattern: info.access_pattern,
+                origin: info.input_origin.into(),
+                read_digest: info.read_digest.map(|d| d.to_string()),
+                write_digest: info.write_digest.map(|d| d.to_string()),
+                written_to_disk: info.got_written_to_disk,
+            })
+            .collect();
+
+        report.files.sort_by(|a, b| a.name.cmp(&b.name));
+        report
     }
 
-    /// Runs the session, generating the desired outputs.
+    /// Get the names of the files that this session read from its bundle.
     ///
-    /// What this does depends on which [`PassSetting`] you asked for. The most common choice is
-    /// `PassSetting::Default`, in which case this method does the following:
+    /// This is based on the record of file accesses that the session keeps,
+    /// so it is most useful after [`Self::run`] has been called. Together with
+    /// [`tectonic_bundles::export`], it can be used to create a minimal
+    /// bundle containing only the files needed to build a particular
+    /// document. Note that if the session loaded a cached format file, the
+    /// files needed to generate the format won't be included.
     ///
-    /// - if a `.fmt` file does not yet exist, generate one and cache it
-    /// - run the TeX engine once
-    /// - run BibTeX, if it seems to be required
-    /// - repeat the last two steps as often as needed
-    /// - write the output files to disk, including a Makefile if it was requested.
-    pub fn run(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
-        // Pre-invocation setup that requires cleanup even if the processing errors out.
+    /// The session doesn't directly track which provider each file came
+    /// from, so we check whether the bundle contains each file that was read
+    /// before it was written. This might include files that the bundle
+    /// provides but that were actually taken from the filesystem, which is
+    /// harmless. The returned names are sorted.
+    pub fn bundle_files_read(&mut self, status: &mut dyn StatusBackend) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .bs
+            .events
+            .iter()
+            .filter(|(name, info)| {
+                !name.is_empty()
+                    && info.input_origin != InputOrigin::NotInput
+                    && matches!(
+                        info.access_pattern,
+                        AccessPattern::Read | AccessPattern::ReadThenWritten
+                    )
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
 
-        let (shell_escape_work, clean_up_shell_escape) = match self.shell_escape_mode {
-            ShellEscapeMode::Disabled => (None, false),
+        names.retain(|name| {
+            matches!(
+                self.bs.bundle.input_open_name(name, status),
+                OpenResult::Ok(_)
+            )
+        });
+        names.sort();
+        names
+    }
 
-            ShellEscapeMode::ExternallyManagedDir(ref p) => (
-                Some(FilesystemIo::new(p, false, false, HashSet::new())),
-                false,
-            ),
+    #[cfg(feature = "serialization")]
+    fn write_build_report(&self, path: &Path) -> Result<()> {
+        let f = ctry!(File::create(path); "couldn't create build report file `{}`", path.display());
+        ctry!(
+            serde_json::to_writer_pretty(f, &self.build_report());
+            "couldn't write build report file `{}`", path.display()
+        );
+        Ok(())
+    }
 
-            ShellEscapeMode::TempDir => {
-                let tempdir = ctry!(tempfile::Builder::new().tempdir(); "can't create temporary directory for shell-escape work");
-                (
-                    Some(FilesystemIo::new(
-                        &tempdir.into_path(),
-                        false,
-                        false,
-                        HashSet::new(),
-                    )),
-                    true,
+    #[cfg(not(feature = "serialization"))]
+    fn write_build_report(&self, _path: &Path) -> Result<()> {
+        Err(errmsg!(
+            "build reports require Tectonic to be built with the \"serialization\" feature"
+        ))
+    }
+
+    /// Compute the parts of the build manifest that don't depend on the
+    /// details of a particular build. Returns `None` if the format file
+    /// doesn't exist yet.
+    #[cfg(feature = "serialization")]
+    fn base_build_manifest(
+        &mut self,
+        status: &mut dyn StatusBackend,
+    ) -> Result<Option<BuildManifest>> {
+        // Each setting is hashed along with its name. The build date is
+        // omitted if it's just the current time, since it then changes every
+        // time.
+        let mut dc = digest::create();
+        let mut add = |name: &str, value: &dyn fmt::Debug| {
+            dc.update(format!("{name} = {value:?}\n").as_bytes());
+        };
+
+        add("primary_input", &self.primary_input_tex_path);
+        add("format", &self.format_name);
+        add("output_path", &self.output_path);
+        add("hidden_input_paths", &self.hidden_input_paths);
+        add("pass", &self.pass);
+        add("output_format", &self.output_format);
+        add("extra_output_formats", &self.extra_output_formats);
+        add("reruns", &self.tex_rerun_specification);
+        add("rerun_policy", &self.rerun_policy);
+        add("keep_intermediates", &self.keep_intermediates);
+        add("keep_logs", &self.keep_logs);
+        add("synctex", &self.synctex_enabled);
+        add("pdf_compression", &self.pdf_compression);
+        add("unstables", &self.unstables);
+        add("html_assets_spec_path", &self.html_assets_spec_path);
+        add("html_template_variables", &self.html_template_variables);
+        add("html_emit_files", &self.html_emit_files);
+        add("html_emit_assets", &self.html_emit_assets);
+
+        if !self.build_date_is_current_time {
+            add("build_date", &self.build_date);
+        }
+
+        // The format and primary input are read directly from their
+        // providers, rather than through the bridge state, so that these
+        // reads aren't recorded as I/O events of the build.
+        let format = match self
+            .bs
+            .format_cache
+            .input_open_format(&self.format_name, status)
+        {
+            OpenResult::Ok(ih) => digest_of_input(ih)?,
+            OpenResult::NotAvailable => return Ok(None),
+            OpenResult::Err(e) => return Err(e.into()),
+        };
+
+        let primary_input = match self.bs.primary_input.input_open_primary(status) {
+            OpenResult::Ok(ih) => digest_of_input(ih)?,
+            OpenResult::NotAvailable => return Ok(None),
+            OpenResult::Err(e) => return Err(e.into()),
+        };
+
+        Ok(Some(BuildManifest {
+            settings: DigestData::from(dc).to_string(),
+            bundle: self.bs.bundle.get_digest(status)?.to_string(),
+            format: format.to_string(),
+            primary_input: primary_input.to_string(),
+            inputs: BTreeMap::new(),
+            outputs: Vec::new(),
+        }))
+    }
+
+    /// Get the digest of a file as it would currently be found in the
+    /// filesystem layers of the I/O stack.
+    #[cfg(feature = "serialization")]
+    fn filesystem_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Result<DigestData> {
+        let providers =
+            std::iter::once(&mut self.bs.filesystem).chain(self.bs.extra_search_paths.iter_mut());
+
+        for io in providers {
+            match io.input_open_name(name, status) {
+                OpenResult::Ok(ih) => return digest_of_input(ih),
+                OpenResult::NotAvailable => {}
+                OpenResult::Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(DigestData::of_nothing())
+    }
+
+    /// Check whether the outputs of the previous build, as described by its
+    /// manifest, are still up-to-date. If they're not, the manifest is
+    /// removed so that a failed build can't leave a stale one behind.
+    #[cfg(feature = "serialization")]
+    fn check_build_manifest(
+        &mut self,
+        path: &Path,
+        status: &mut dyn StatusBackend,
+    ) -> Result<bool> {
+        let previous: BuildManifest = match File::open(path) {
+            Ok(f) => match serde_json::from_reader(f) {
+                Ok(m) => m,
+                Err(e) => {
+                    tt_warning!(status, "ignoring unreadable build manifest `{}`", path.display(); e.into());
+                    return Ok(false);
+                }
+            },
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+
+        ctry!(std::fs::remove_file(path); "couldn't remove build manifest `{}`", path.display());
+
+        let mut current = match self.base_build_manifest(status)? {
+            Some(m) => m,
+            None => return Ok(false),
+        };
+
+        for name in previous.inputs.keys() {
+            let digest = self.filesystem_digest(name, status)?;
+            current.inputs.insert(name.clone(), digest.to_string());
+        }
+
+        current.outputs = previous.outputs.clone();
+
+        if current != previous {
+            return Ok(false);
+        }
+
+        let root = match self.output_path {
+            Some(ref p) => p,
+            None => return Ok(false),
+        };
+
+        if !previous
+            .outputs
+            .iter()
+            .all(|name| root.join(name).is_file())
+        {
+            return Ok(false);
+        }
+
+        // Everything matches, so put the manifest back for next time.
+        let f = ctry!(File::create(path); "couldn't create build manifest `{}`", path.display());
+        ctry!(
+            serde_json::to_writer_pretty(f, &previous);
+            "couldn't write build manifest `{}`", path.display()
+        );
+        Ok(true)
+    }
+
+    #[cfg(not(feature = "serialization"))]
+    fn check_build_manifest(
+        &mut self,
+        _path: &Path,
+        _status: &mut dyn StatusBackend,
+    ) -> Result<bool> {
+        Ok(false)
+    }
+
+    /// Write out a manifest describing the build that just completed.
+    #[cfg(feature = "serialization")]
+    fn write_build_manifest(&mut self, path: &Path, status: &mut dyn StatusBackend) -> Result<()> {
+        if self.shell_escape_mode != ShellEscapeMode::Disabled || self.used_untracked_tool {
+            tt_note!(
+                status,
+                "not recording a build manifest since not all inputs could be tracked"
+            );
+            return Ok(());
+        }
+
+        let mut manifest = match self.base_build_manifest(status)? {
+            Some(m) => m,
+            None => return Ok(()),
+        };
+
+        for (name, info) in &self.bs.events {
+            if name.is_empty() || info.access_pattern != AccessPattern::Read {
+                continue;
+            }
+
+            // Files from the bundle are covered by its digest, but if a file
+            // of the same name shows up in the filesystem, it would take
+            // precedence, so record that it wasn't there.
+            let digest = match (info.input_origin, info.read_digest) {
+                (InputOrigin::Filesystem, Some(d)) => d,
+                (InputOrigin::Filesystem, None) => {
+                    tt_note!(
+                        status,
+                        "not recording a build manifest since \"{}\" couldn't be tracked",
+                        name
+                    );
+                    return Ok(());
+                }
+                _ => DigestData::of_nothing(),
+            };
+
+            manifest.inputs.insert(name.clone(), digest.to_string());
+        }
+
+        manifest.outputs = self
+            .bs
+            .events
+            .iter()
+            .filter(|(_, info)| info.got_written_to_disk)
+            .map(|(name, _)| name.clone())
+            .collect();
+        manifest.outputs.sort();
+
+        let f = ctry!(File::create(path); "couldn't create build manifest `{}`", path.display());
+        ctry!(
+            serde_json::to_writer_pretty(f, &manifest);
+            "couldn't write build manifest `{}`", path.display()
+        );
+        Ok(())
+    }
+
+    #[cfg(not(feature = "serialization"))]
+    fn write_build_manifest(&mut self, _path: &Path, status: &mut dyn StatusBackend) -> Result<()> {
+        tt_warning!(
+            status,
+            "incremental builds require Tectonic to be built with the \"serialization\" feature"
+        );
+        Ok(())
+    }
+
+    /// Note that an engine pass has completed, for the build report.
+    fn record_pass(
+        &mut self,
+        engine: &str,
+        input: Option<&str>,
+        rerun_reason: Option<&str>,
+        start: Instant,
+    ) {
+        self.report.passes.push(PassReport {
+            engine: engine.to_owned(),
+            input: input.map(|s| s.to_owned()),
+            rerun_reason: rerun_reason.map(|s| s.to_owned()),
+            duration_secs: start.elapsed().as_secs_f64(),
+        });
+    }
+
+    /// Runs the session, generating the desired outputs.
+    ///
+    /// What this does depends on which [`PassSetting`] you asked for. The most common choice is
+    /// `PassSetting::Default`, in which case this method does the following:
+    ///
+    /// - if a `.fmt` file does not yet exist, generate one and cache it
+    /// - run the TeX engine once
+    /// - run the index engine, if any `.idx` files or glossaries have changed
+    /// - run BibTeX, if it seems to be required
+    /// - repeat the last two steps as often as needed
+    /// - write the output files to disk, including a Makefile if it was requested.
+    pub fn run(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
+        // Keep track of warnings and errors for the build report.
+
+        let start = Instant::now();
+        let mut recorder = RecordingStatusBackend::new(status);
+        let status = &mut recorder;
+
+        // Pre-invocation setup that requires cleanup even if the processing errors out.
+
+        let (shell_escape_work, clean_up_shell_escape) = match self.shell_escape_mode {
+            ShellEscapeMode::Disabled => (None, false),
+
+            ShellEscapeMode::ExternallyManagedDir(ref p) => (
+                Some(FilesystemIo::new(p, false, false, HashSet::new())),
+                false,
+            ),
+
+            ShellEscapeMode::TempDir => {
+                let tempdir = ctry!(tempfile::Builder::new().tempdir(); "can't create temporary directory for shell-escape work");
+                (
+                    Some(FilesystemIo::new(
+                        &tempdir.into_path(),
+                        false,
+                        false,
+                        HashSet::new(),
+                    )),
+                    true,
                 )
             }
 
@@ -1455,14 +2454,45 @@ impl ProcessingSession {
             }
         }
 
+        // Finish the build report.
+
+        self.report.warnings = recorder.warnings;
+        self.report.errors = recorder.errors;
+
+        if let Err(ref e) = result {
+            self.report.errors.push(e.to_string());
+        }
+
+        self.report.succeeded = result.is_ok();
+        self.report.duration_secs = start.elapsed().as_secs_f64();
+
+        let report_result = match self.build_report_path {
+            Some(ref p) => self.write_build_report(p),
+            None => Ok(()),
+        };
+
         // Propagate the actual result.
-        result
+        result.and(report_result)
     }
 
     /// The bulk of the `run` implementation. We need to wrap it to manage the
     /// lifecycle of resources like the shell-escape temporary directory, if
     /// needed.
     fn run_inner(&mut self, status: &mut dyn StatusBackend) -> Result<()> {
+        // If we're doing incremental builds, maybe there's nothing to do.
+
+        let manifest_path = self.build_manifest_path.clone();
+
+        if let Some(ref p) = manifest_path {
+            if self.check_build_manifest(p, status)? {
+                tt_note!(
+                    status,
+                    "nothing has changed since the last build; skipping it"
+                );
+                return Ok(());
+            }
+        }
+
         // Do we need to generate the format file?
 
         let generate_format = if self.output_format == OutputFormat::Format {
@@ -1480,6 +2510,24 @@ impl ProcessingSession {
 
         if generate_format {
             tt_note!(status, "generating format \"{}\"", self.format_name);
+
+            // If we know which bundle files were needed the last time that we
+            // generated this format, get them all at once. This saves a lot of
+            // time when the bundle files have to be downloaded. Otherwise, get
+            // the files that the format's sources refer to in a few batches.
+            let deps = self.bs.format_cache.read_dependencies(&self.format_name);
+            let stem = self.format_name.split('.').next().unwrap_or_default();
+
+            let result = if deps.is_empty() {
+                prefetch_format_sources(&mut *self.bs.bundle, stem, status)
+            } else {
+                self.bs.bundle.prefetch(&deps, status)
+            };
+
+            if let Err(e) = result {
+                tt_warning!(status, "failed to prefetch the files needed for the format"; e);
+            }
+
             self.make_format_pass(status)?;
         }
 
@@ -1569,6 +2617,20 @@ impl ProcessingSession {
             ctry!(writeln!(mf_dest, ""); "couldn't write to Makefile-rules file");
         }
 
+        // Save intermediates for the next session to start from.
+
+        if let Some(ref dir) = self.intermediates_cache_dir {
+            if let Err(e) = self.save_intermediates(dir) {
+                tt_warning!(status, "couldn't save intermediate files to `{}`", dir.display(); e);
+            }
+        }
+
+        // Record what we did for the next incremental build.
+
+        if let Some(ref p) = manifest_path {
+            self.write_build_manifest(p, status)?;
+        }
+
         // All done.
 
         Ok(())
@@ -1597,9 +2659,21 @@ impl ProcessingSession {
             }
 
             let sname = name;
-            let summ = self.bs.events.get_mut(name).unwrap();
 
-            if !only_logs && (self.output_format == OutputFormat::Aux) {
+            let requested = self.is_requested_output(name);
+
+            // Files loaded from the intermediates cache that the engines
+            // never touched have no summary, and aren't outputs of this
+            // session.
+            let summ = match self.bs.events.get_mut(name) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            if !only_logs
+                && self.output_format == OutputFormat::Aux
+                && self.extra_output_formats.is_empty()
+            {
                 // In this mode we're only writing the .aux file. I initially
                 // wanted to be clever-ish and output all auxiliary-type
                 // files, but doing so ended up causing non-obvious problems
@@ -1609,6 +2683,7 @@ impl ProcessingSession {
                     continue;
                 }
             } else if !self.keep_intermediates
+                && !requested
                 && (summ.access_pattern != AccessPattern::Written
                     || ALWAYS_INTERMEDIATE_EXTENSIONS
                         .iter()
@@ -1618,7 +2693,9 @@ impl ProcessingSession {
                 continue;
             }
 
-            let is_logfile = sname.ends_with(".log") || sname.ends_with(".blg");
+            let is_logfile = sname.ends_with(".log")
+                || sname.ends_with(".blg")
+                || self.index_log_files.contains(sname);
 
             if is_logfile && !self.keep_logs {
                 continue;
@@ -1682,12 +2759,18 @@ impl ProcessingSession {
             Some(RerunReason::Bibtex)
         } else {
             warnings = self.tex_pass(None, status)?;
+            self.makeindex_pass(status)?;
+            self.glossaries_pass(status)?;
             let maybe_biber = self.check_biber_requirement()?;
 
             if let Some(biber) = maybe_biber {
-                self.bs.external_tool_pass(&biber, status)?;
+                let start = Instant::now();
+                let result = self.bs.external_tool_pass(&biber, status);
+                self.record_pass(&biber.argv[0], None, None, start);
+                self.used_untracked_tool = true;
+                result?;
                 Some(RerunReason::Biber)
-            } else if self.is_bibtex_needed() {
+            } else if self.is_bibtex_needed() && !self.is_seeded_bibliography_current(status) {
                 self.bibtex_pass(status)?;
                 Some(RerunReason::Bibtex)
             } else {
@@ -1699,7 +2782,7 @@ impl ProcessingSession {
 
         let (pass_count, reruns_fixed) = match self.tex_rerun_specification {
             Some(n) => (n, true),
-            None => (DEFAULT_MAX_TEX_PASSES, false),
+            None => (self.rerun_policy.max_reruns, false),
         };
 
         for i in 0..pass_count {
@@ -1707,9 +2790,7 @@ impl ProcessingSession {
                 "I was told to".to_owned()
             } else {
                 match rerun_result {
-                    Some(RerunReason::Biber) => "biber was run".to_owned(),
-                    Some(RerunReason::Bibtex) => "bibtex was run".to_owned(),
-                    Some(RerunReason::FileChange(ref s)) => format!("\"{s}\" changed"),
+                    Some(ref reason) => reason.to_string(),
                     None => break,
                 }
             };
@@ -1725,15 +2806,28 @@ impl ProcessingSession {
             }
 
             warnings = self.tex_pass(Some(&rerun_explanation), status)?;
+            self.makeindex_pass(status)?;
+            self.glossaries_pass(status)?;
 
             if !reruns_fixed {
                 rerun_result = self.is_rerun_needed(status);
 
-                if rerun_result.is_some() && i == DEFAULT_MAX_TEX_PASSES - 1 {
+                if let (Some(reason), true) = (&rerun_result, i == pass_count - 1) {
+                    let max_reruns = self.rerun_policy.max_reruns;
+
+                    if self.rerun_policy.error_on_nonconvergence {
+                        return Err(errmsg!(
+                            "the document did not converge after {} TeX reruns ({})",
+                            max_reruns,
+                            reason
+                        ));
+                    }
+
                     tt_warning!(
                         status,
-                        "TeX rerun seems needed, but stopping at {} passes",
-                        DEFAULT_MAX_TEX_PASSES
+                        "TeX rerun seems needed ({}), but stopping after {} reruns",
+                        reason,
+                        max_reruns
                     );
                     break;
                 }
@@ -1745,17 +2839,56 @@ impl ProcessingSession {
             tt_warning!(status, "{}", warnings);
         }
 
-        // And finally, xdvipdfmx or spx2html. Maybe.
+        // And finally, the converters for whichever outputs were requested.
+        // Maybe.
 
-        if let OutputFormat::Pdf = self.output_format {
+        let mut converted = false;
+
+        if self.wants_output(OutputFormat::Pdf) {
             self.xdvipdfmx_pass(status)?;
-        } else if let OutputFormat::Html = self.output_format {
+            converted = true;
+        }
+
+        if self.wants_output(OutputFormat::Svg) {
+            self.xdv2svg_pass(status)?;
+            converted = true;
+        }
+
+        if self.wants_output(OutputFormat::Png) {
+            self.xdv2png_pass(status)?;
+            converted = true;
+        }
+
+        if self.wants_output(OutputFormat::Text) {
+            self.xdv2text_pass(status)?;
+            converted = true;
+        }
+
+        if self.wants_output(OutputFormat::Html) {
             self.spx2html_pass(status)?;
+            converted = true;
+        }
+
+        if converted && !self.wants_output(OutputFormat::Xdv) {
+            self.bs.mem.files.borrow_mut().remove(&self.tex_xdv_path);
         }
 
         Ok(0)
     }
 
+    /// Check whether the session was asked to produce the given type of
+    /// output, either as its main output or as an extra one.
+    fn wants_output(&self, f: OutputFormat) -> bool {
+        self.output_format == f || self.extra_output_formats.contains(&f)
+    }
+
+    /// Check whether the named file was explicitly requested as an output,
+    /// even though it might otherwise be considered an intermediate file.
+    fn is_requested_output(&self, name: &str) -> bool {
+        (self.wants_output(OutputFormat::Aux) && name.ends_with(".aux"))
+            || (self.wants_output(OutputFormat::Xdv) && name == self.tex_xdv_path)
+    }
+
     fn is_bibtex_needed(&self) -> bool {
         const BIBDATA: &[u8] = b"\\bibdata";
 
@@ -1772,6 +2905,124 @@ impl ProcessingSession {
             .unwrap_or(false)
     }
 
+    /// Check whether a bibliography loaded from the intermediates cache can be
+    /// used as-is. This is the case if the TeX pass wrote out exactly the
+    /// same `.aux` files that were loaded from the cache, and the `.bib` and
+    /// `.bst` files that BibTeX read to generate the bibliography haven't
+    /// changed, since then BibTeX would just regenerate the same `.bbl` file.
+    fn is_seeded_bibliography_current(&mut self, status: &mut dyn StatusBackend) -> bool {
+        let mut bbl_path = PathBuf::from(&self.tex_aux_path);
+        bbl_path.set_extension("bbl");
+
+        if !self.seeded_files.contains(&self.tex_aux_path)
+            || !self.seeded_files.contains(&*bbl_path.to_string_lossy())
+            || self.bibtex_input_digests.is_empty()
+        {
+            return false;
+        }
+
+        let aux_current = self
+            .bs
+            .events
+            .iter()
+            .filter(|(name, _)| name.ends_with(".aux"))
+            .all(|(name, info)| {
+                self.seeded_files.contains(name)
+                    && info.read_digest.is_some()
+                    && info.read_digest == info.write_digest
+            });
+
+        if !aux_current {
+            return false;
+        }
+
+        let recorded: Vec<_> = self
+            .bibtex_input_digests
+            .iter()
+            .map(|(name, digest)| (name.clone(), *digest))
+            .collect();
+
+        recorded
+            .iter()
+            .all(|(name, digest)| self.current_input_digest(name, status) == Some(*digest))
+    }
+
+    /// Get the digest of a file as the engines would currently find it in
+    /// the filesystem or the bundle. The file is opened directly, rather than
+    /// through the bridge state, so that no I/O event is recorded. Returns
+    /// `None` if the file can't be found or read.
+    fn current_input_digest(
+        &mut self,
+        name: &str,
+        status: &mut dyn StatusBackend,
+    ) -> Option<DigestData> {
+        let providers = std::iter::once(&mut self.bs.filesystem as &mut dyn IoProvider)
+            .chain(
+                self.bs
+                    .extra_search_paths
+                    .iter_mut()
+                    .map(|p| p as &mut dyn IoProvider),
+            )
+            .chain(std::iter::once(self.bs.bundle.as_ioprovider_mut()));
+
+        for io in providers {
+            match io.input_open_name(name, status) {
+                OpenResult::Ok(ih) => return digest_of_input(ih).ok(),
+                OpenResult::NotAvailable => {}
+                OpenResult::Err(_) => return None,
+            }
+        }
+
+        None
+    }
+
+    /// Replace the contents of the intermediates cache with the latest
+    /// versions of the files that it tracks, along with the record of the
+    /// files that BibTeX read.
+    fn save_intermediates(&self, dir: &Path) -> StdResult<(), NewError> {
+        match std::fs::remove_dir_all(dir) {
+            Ok(_) => {}
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        std::fs::create_dir_all(dir)?;
+
+        for (name, file) in &*self.bs.mem.files.borrow() {
+            if !SEEDABLE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+                continue;
+            }
+
+            // TeX names can be arbitrary, but we only save files that stay
+            // within the cache directory.
+            let rel = Path::new(name);
+
+            if rel
+                .components()
+                .any(|c| !matches!(c, std::path::Component::Normal(_)))
+            {
+                continue;
+            }
+
+            let path = dir.join(rel);
+
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::write(path, &file.data)?;
+        }
+
+        let mut record: Vec<_> = self
+            .bibtex_input_digests
+            .iter()
+            .map(|(name, digest)| format!("{digest} {name}\n"))
+            .collect();
+        record.sort();
+        std::fs::write(dir.join(BIBTEX_INPUTS_RECORD), record.concat())?;
+        Ok(())
+    }
+
     /// Use the TeX engine to generate a format file.
     #[allow(clippy::manual_split_once)] // requires Rust 1.52 (note that we don't actually define our MSRV)
     fn make_format_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
@@ -1786,6 +3037,8 @@ impl ProcessingSession {
             .into()
         });
         let stem = r?;
+        let start = Instant::now();
+        let previous_events: HashSet<String> = self.bs.events.keys().cloned().collect();
 
         let result = {
             self.bs
@@ -1801,6 +3054,14 @@ impl ProcessingSession {
             r
         };
 
+        // `stem` borrows from `self`, so we can't use `self.record_pass()` here.
+        self.report.passes.push(PassReport {
+            engine: "tex".to_owned(),
+            input: Some(self.format_name.clone()),
+            rerun_reason: None,
+            duration_secs: start.elapsed().as_secs_f64(),
+        });
+
         match result {
             Ok(TexOutcome::Spotless) => {}
             Ok(TexOutcome::Warnings) => {
@@ -1834,6 +3095,30 @@ impl ProcessingSession {
             ctry!(self.bs.format_cache.write_format(stem, &file.data, status); "cannot write format file {}", sname);
         }
 
+        // Remember which files were read, so that they can be prefetched the
+        // next time that this format needs to be generated.
+
+        let mut deps: Vec<_> = self
+            .bs
+            .events
+            .iter()
+            .filter(|(name, info)| {
+                !name.is_empty()
+                    && !previous_events.contains(*name)
+                    && info.input_origin == InputOrigin::Other
+                    && matches!(
+                        info.access_pattern,
+                        AccessPattern::Read | AccessPattern::ReadThenWritten
+                    )
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        deps.sort();
+
+        if let Err(e) = self.bs.format_cache.write_dependencies(stem, &deps) {
+            tt_warning!(status, "couldn't save the list of files needed for the format"; e);
+        }
+
         // All done. Clear the memory layer since this was a special preparatory step.
         self.bs.mem.files.borrow_mut().clear();
 
@@ -1846,6 +3131,9 @@ impl ProcessingSession {
         rerun_explanation: Option<&str>,
         status: &mut dyn StatusBackend,
     ) -> Result<Option<&'static str>> {
+        let start = Instant::now();
+        let semantic_pagination = self.wants_output(OutputFormat::Html);
+
         let result = {
             if let Some(s) = rerun_explanation {
                 status.note_highlighted("Rerunning ", "TeX", &format!(" because {s} ..."));
@@ -1872,7 +3160,7 @@ impl ProcessingSession {
                 .halt_on_error_mode(!self.unstables.continue_on_errors)
                 .initex_mode(self.output_format == OutputFormat::Format)
                 .synctex(self.synctex_enabled)
-                .semantic_pagination(self.output_format == OutputFormat::Html)
+                .semantic_pagination(semantic_pagination)
                 .shell_escape(self.shell_escape_mode != ShellEscapeMode::Disabled)
                 .build_date(self.build_date)
                 .process(
@@ -1882,6 +3170,8 @@ impl ProcessingSession {
                 )
         };
 
+        self.record_pass("tex", None, rerun_explanation, start);
+
         let warnings = match result {
             Ok(TexOutcome::Spotless) => None,
             Ok(TexOutcome::Warnings) =>
@@ -1911,6 +3201,8 @@ impl ProcessingSession {
         status: &mut dyn StatusBackend,
         aux_file: &String,
     ) -> Result<i32> {
+        let start = Instant::now();
+
         let result = {
             status.note_highlighted("Running ", "BibTeX", &format!(" on {aux_file} ..."));
             let mut launcher =
@@ -1919,6 +3211,8 @@ impl ProcessingSession {
             engine.process(&mut launcher, aux_file, &self.unstables)
         };
 
+        self.record_pass("bibtex", Some(aux_file), None, start);
+
         match result {
             Ok(TexOutcome::Spotless) => {}
             Ok(TexOutcome::Warnings) => {
@@ -1956,10 +3250,171 @@ impl ProcessingSession {
             let _r = self.bibtex_pass_for_one_aux_file(status, &f)?;
         }
 
+        // Remember what BibTeX read, so that a later session can tell whether
+        // a bibliography carried over from this one is stale.
+        self.bibtex_input_digests = self
+            .bs
+            .events
+            .iter()
+            .filter(|(name, _)| name.ends_with(".bib") || name.ends_with(".bst"))
+            .filter_map(|(name, info)| info.read_digest.map(|d| (name.clone(), d)))
+            .collect();
+
+        Ok(0)
+    }
+
+    /// Get the digest of an engine output file, if it was written during this
+    /// session and has changed since the last time that we ran the index
+    /// engine on it.
+    fn changed_index_input(&self, name: &str) -> Option<DigestData> {
+        let digest = self.bs.events.get(name)?.write_digest?;
+
+        if self.index_digests.get(name) == Some(&digest) {
+            None
+        } else {
+            Some(digest)
+        }
+    }
+
+    /// Run the index engine on one input file. If the output file differs
+    /// from what TeX read in, the usual rerun detection will notice.
+    fn index_pass_for_one_file(
+        &mut self,
+        status: &mut dyn StatusBackend,
+        mut engine: MakeindexEngine,
+        input: String,
+        output: &str,
+        log: &str,
+        digest: DigestData,
+    ) -> Result<i32> {
+        let start = Instant::now();
+
+        let result = {
+            status.note_highlighted("Running ", "makeindex", &format!(" on {input} ..."));
+            engine.process(&mut self.bs, status, &input, output, log)
+        };
+
+        self.record_pass("makeindex", Some(&input), None, start);
+
+        match result.chain_err(|| ErrorKind::EngineError("makeindex"))? {
+            MakeindexOutcome::Spotless => {}
+            MakeindexOutcome::Warnings => {
+                tt_note!(
+                    status,
+                    "warnings were issued by makeindex; use --keep-logs for details."
+                );
+            }
+            MakeindexOutcome::Errors => {
+                tt_warning!(
+                    status,
+                    "errors were issued by makeindex, but were ignored; \
+                     use --keep-logs for details."
+                );
+            }
+        }
+
+        self.index_log_files.insert(log.to_owned());
+        self.index_digests.insert(input, digest);
+        Ok(0)
+    }
+
+    /// Run the index engine on every `.idx` file that has changed since the
+    /// last time that we processed it.
+    fn makeindex_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let mut idx_files: Vec<(String, DigestData)> = self
+            .bs
+            .events
+            .keys()
+            .filter(|name| name.ends_with(".idx"))
+            .filter_map(|name| {
+                self.changed_index_input(name)
+                    .map(|digest| (name.clone(), digest))
+            })
+            .collect();
+
+        idx_files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (idx_file, digest) in idx_files {
+            let stem = &idx_file[..idx_file.len() - 4];
+            let ind_file = format!("{stem}.ind");
+            let ilg_file = format!("{stem}.ilg");
+            let mut engine = MakeindexEngine::default();
+
+            if let Some(ref s) = self.unstables.index_style {
+                engine.style(s);
+            }
+
+            self.index_pass_for_one_file(status, engine, idx_file, &ind_file, &ilg_file, digest)?;
+        }
+
+        Ok(0)
+    }
+
+    /// Sort the glossaries of the `glossaries` package, if it's in use. This
+    /// does the job of the `makeglossaries` script, using the index engine
+    /// in-process rather than running external programs.
+    fn glossaries_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let req = match self.check_glossaries_requirement() {
+            Some(r) => r,
+            None => return Ok(0),
+        };
+
+        if req.style.ends_with(".xdy") {
+            if !self.warned_about_xindy {
+                tt_warning!(
+                    status,
+                    "the document asks for its glossaries to be sorted with xindy, which \
+                     is not supported; use the glossaries package's `makeindex` option instead"
+                );
+                self.warned_about_xindy = true;
+            }
+
+            return Ok(0);
+        }
+
+        let stem = self
+            .tex_aux_path
+            .strip_suffix(".aux")
+            .unwrap_or(&self.tex_aux_path)
+            .to_owned();
+
+        for (log_ext, out_ext, in_ext) in &req.glossaries {
+            let in_file = format!("{stem}.{in_ext}");
+
+            let digest = match self.changed_index_input(&in_file) {
+                Some(d) => d,
+                None => continue,
+            };
+
+            let mut engine = MakeindexEngine::default();
+            engine
+                .style(&req.style)
+                .letter_ordering(req.letter_ordering);
+
+            self.index_pass_for_one_file(
+                status,
+                engine,
+                in_file,
+                &format!("{stem}.{out_ext}"),
+                &format!("{stem}.{log_ext}"),
+                digest,
+            )?;
+        }
+
         Ok(0)
     }
 
+    /// Check whether the `glossaries` package has asked for glossaries to be
+    /// sorted.
+    fn check_glossaries_requirement(&self) -> Option<GlossariesRequirement> {
+        let mem_files = &*self.bs.mem.files.borrow();
+        let aux = mem_files.get(&self.tex_aux_path)?;
+        parse_glossaries_requirement(&String::from_utf8_lossy(&aux.data))
+    }
+
     fn xdvipdfmx_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let start = Instant::now();
+
         {
             status.note_highlighted("Running ", "xdvipdfmx", " ...");
 
@@ -1967,20 +3422,90 @@ impl ProcessingSession {
                 CoreBridgeLauncher::new_with_security(&mut self.bs, status, self.security.clone());
             let mut engine = XdvipdfmxEngine::default();
 
-            engine.build_date(self.build_date);
+            engine
+                .build_date(self.build_date)
+                .enable_compression(self.pdf_compression);
 
             if let Some(ref ps) = self.unstables.paper_size {
                 engine.paper_spec(ps.clone());
             }
 
-            engine.process(&mut launcher, &self.tex_xdv_path, &self.tex_pdf_path)?;
+            let result = engine.process(&mut launcher, &self.tex_xdv_path, &self.tex_pdf_path);
+            self.record_pass("xdvipdfmx", None, None, start);
+            result?;
+        }
+
+        Ok(0)
+    }
+
+    fn xdv2svg_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let start = Instant::now();
+
+        {
+            let mut engine = Xdv2SvgEngine::default();
+            engine.embed_fonts(self.unstables.svg_embed_fonts);
+
+            status.note_highlighted("Running ", "xdv2svg", " ...");
+            let result = engine.process(
+                &mut self.bs,
+                status,
+                &self.tex_xdv_path,
+                &self.tex_image_stem,
+            );
+            self.record_pass("xdv2svg", None, None, start);
+            result?;
+        }
+
+        Ok(0)
+    }
+
+    fn xdv2png_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let start = Instant::now();
+
+        {
+            let mut engine = Xdv2PngEngine::default();
+
+            if let Some(dpi) = self.unstables.png_dpi {
+                engine.dpi(dpi);
+            }
+
+            status.note_highlighted("Running ", "xdv2png", " ...");
+            let result = engine.process(
+                &mut self.bs,
+                status,
+                &self.tex_xdv_path,
+                &self.tex_image_stem,
+            );
+            self.record_pass("xdv2png", None, None, start);
+            result?;
+        }
+
+        Ok(0)
+    }
+
+    fn xdv2text_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let start = Instant::now();
+
+        {
+            let mut engine = Xdv2TextEngine::default();
+
+            status.note_highlighted("Running ", "xdv2text", " ...");
+            let result = engine.process(
+                &mut self.bs,
+                status,
+                &self.tex_xdv_path,
+                &self.tex_image_stem,
+            );
+            self.record_pass("xdv2text", None, None, start);
+            result?;
         }
 
-        self.bs.mem.files.borrow_mut().remove(&self.tex_xdv_path);
         Ok(0)
     }
 
     fn spx2html_pass(&mut self, status: &mut dyn StatusBackend) -> Result<i32> {
+        let start = Instant::now();
+
         {
             let mut engine = Spx2HtmlEngine::default();
 
@@ -2000,11 +3525,16 @@ impl ProcessingSession {
                 engine.precomputed_assets(a.clone());
             }
 
+            for (name, value) in &self.html_template_variables {
+                engine.template_variable(name.clone(), value.clone());
+            }
+
             status.note_highlighted("Running ", "spx2html", " ...");
-            engine.process_to_filesystem(&mut self.bs, status, &self.tex_xdv_path)?;
+            let result = engine.process_to_filesystem(&mut self.bs, status, &self.tex_xdv_path);
+            self.record_pass("spx2html", None, None, start);
+            result?;
         }
 
-        self.bs.mem.files.borrow_mut().remove(&self.tex_xdv_path);
         Ok(0)
     }
 
@@ -2212,3 +3742,133 @@ impl ProcessingSession {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rerun_policy_defaults() {
+        let policy = RerunPolicy::default();
+        assert_eq!(policy.max_reruns, DEFAULT_MAX_TEX_RERUNS);
+        assert!(!policy.error_on_nonconvergence);
+        assert!(!policy.is_ignored("doc.aux"));
+    }
+
+    #[test]
+    fn rerun_policy_ignored_files() {
+        let mut policy = RerunPolicy::default();
+        policy.ignore_file("doc.out").ignore_file("*.toc");
+
+        assert!(policy.is_ignored("doc.out"));
+        assert!(policy.is_ignored("doc.toc"));
+        assert!(policy.is_ignored("chapter/other.toc"));
+        assert!(!policy.is_ignored("other.out"));
+        assert!(!policy.is_ignored("doc.aux"));
+        assert!(!policy.is_ignored("doc.toc.bak"));
+    }
+
+    #[test]
+    fn rerun_policy_rejects_zero_reruns() {
+        let mut policy = RerunPolicy::default();
+        policy.max_reruns(0);
+
+        let mut builder = ProcessingSessionBuilder::default();
+        builder.rerun_policy(policy);
+        let mut status = crate::status::NoopStatusBackend::default();
+        assert!(builder.create(&mut status).is_err());
+    }
+
+    #[test]
+    fn bibtex_input_record() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_bibtex_input_digests(dir.path()).is_empty());
+
+        let digest = DigestData::of_nothing();
+        let record = format!("{digest} refs.bib\n{digest} my style.bst\n");
+        std::fs::write(dir.path().join(BIBTEX_INPUTS_RECORD), record).unwrap();
+        let digests = load_bibtex_input_digests(dir.path());
+        assert_eq!(digests.len(), 2);
+        assert_eq!(digests.get("my style.bst"), Some(&digest));
+
+        std::fs::write(dir.path().join(BIBTEX_INPUTS_RECORD), "garbage\n").unwrap();
+        assert!(load_bibtex_input_digests(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn rerun_reason_display() {
+        let reason = RerunReason::FileChange(vec!["a.aux".into()]);
+        assert_eq!(reason.to_string(), "\"a.aux\" changed");
+
+        let reason = RerunReason::FileChange(vec!["a.aux".into(), "a.toc".into(), "b.aux".into()]);
+        assert_eq!(
+            reason.to_string(),
+            "\"a.aux\", \"a.toc\" and \"b.aux\" changed"
+        );
+    }
+
+    #[test]
+    fn tex_group_args_nesting() {
+        assert_eq!(tex_group_args("{a}{b}{c}"), vec!["a", "b", "c"]);
+        assert_eq!(tex_group_args("  {a} trailing {b}"), vec!["a"]);
+        assert_eq!(tex_group_args("{a{b}c}{{d}}"), vec!["a{b}c", "{d}"]);
+        assert_eq!(tex_group_args("{}{x}"), vec!["", "x"]);
+    }
+
+    #[test]
+    fn tex_group_args_escapes() {
+        assert_eq!(tex_group_args(r"{a\}b}{c}"), vec![r"a\}b", "c"]);
+        assert_eq!(tex_group_args(r"{\{}{\\}"), vec![r"\{", r"\\"]);
+    }
+
+    #[test]
+    fn tex_group_args_missing() {
+        assert!(tex_group_args("").is_empty());
+        assert!(tex_group_args("no braces").is_empty());
+        assert_eq!(tex_group_args("{a}{unterminated"), vec!["a"]);
+        assert_eq!(tex_group_args("{a}{b{c}"), vec!["a"]);
+    }
+
+    #[test]
+    fn glossaries_requirement() {
+        let aux = "\\relax\n\
+                   \\@newglossary{main}{glg}{gls}{glo}\n\
+                   \\@newglossary{acronym}{alg}{acr}{acn}\n\
+                   \\@istfilename{doc.ist}\n\
+                   \\@glsorder{letter}\n";
+
+        assert_eq!(
+            parse_glossaries_requirement(aux),
+            Some(GlossariesRequirement {
+                style: "doc.ist".into(),
+                letter_ordering: true,
+                glossaries: vec![
+                    ("glg".into(), "gls".into(), "glo".into()),
+                    ("alg".into(), "acr".into(), "acn".into()),
+                ],
+            })
+        );
+
+        let req = parse_glossaries_requirement(&aux.replace("{letter}", "{word}")).unwrap();
+        assert!(!req.letter_ordering);
+
+        // Glossaries with missing arguments are skipped.
+        let req = parse_glossaries_requirement(&aux.replace("{acn}", "")).unwrap();
+        assert_eq!(req.glossaries.len(), 1);
+    }
+
+    #[test]
+    fn glossaries_requirement_missing() {
+        assert_eq!(parse_glossaries_requirement(""), None);
+        assert_eq!(
+            parse_glossaries_requirement("\\@istfilename{doc.ist}\n"),
+            None
+        );
+
+        // Without a style file, makeglossaries can't do anything either.
+        assert_eq!(
+            parse_glossaries_requirement("\\@newglossary{main}{glg}{gls}{glo}\n"),
+            None
+        );
+    }
+}
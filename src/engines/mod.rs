@@ -4,16 +4,22 @@
 //! Access to Tectonic’s processing backends.
 //!
 //! These backends subsume the functionality of programs such as `bibtex`,
-//! `xetex`, and `xdvipdfmx`. This module is historical — the API for each of
+//! `makeindex`, `xetex`, and `xdvipdfmx`. This module is historical — the API for each of
 //! these is defined in crates with names like `tectonic_engine_xetex`.
 
 // Public sub-modules and reexports.
 
 pub mod bibtex;
+pub mod makeindex;
 pub mod spx2html;
 pub mod tex;
+pub mod xdv2png;
+pub mod xdv2svg;
+pub mod xdv2text;
 pub mod xdvipdfmx;
 
 pub use self::{
-    bibtex::BibtexEngine, spx2html::Spx2HtmlEngine, tex::TexEngine, xdvipdfmx::XdvipdfmxEngine,
+    bibtex::BibtexEngine, makeindex::MakeindexEngine, spx2html::Spx2HtmlEngine, tex::TexEngine,
+    xdv2png::Xdv2PngEngine, xdv2svg::Xdv2SvgEngine, xdv2text::Xdv2TextEngine,
+    xdvipdfmx::XdvipdfmxEngine,
 };
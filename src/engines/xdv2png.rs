@@ -0,0 +1,4 @@
+// Copyright 2026 the Tectonic Project
+// Licensed under the MIT License.
+
+pub use tectonic_engine_xdv2png::Xdv2PngEngine;
@@ -6,9 +6,12 @@
 //! Code for locally caching compiled format files.
 
 use std::{
-    io::{BufReader, Write},
+    collections::HashSet,
+    fs,
+    io::{BufReader, Read, Write},
     path::PathBuf,
 };
+use tectonic_bundles::Bundle;
 use tectonic_errors::{anyhow::bail, Result};
 
 use super::{InputHandle, InputOrigin, IoProvider, OpenResult};
@@ -40,18 +43,8 @@ impl FormatCache {
 
     /// Get an on-disk path name for a given format file. This function simply
     /// produces a path that may or may not exist.
-    #[allow(clippy::manual_split_once)] // requires Rust 1.52 (note that we don't actually define our MSRV)
     fn path_for_format(&mut self, name: &str) -> Result<PathBuf> {
-        // Remove all extensions from the format name. PathBuf.file_stem() doesn't
-        // do what we want since it only strips one extension, so here we go:
-
-        let stem = match name.split('.').next() {
-            Some(s) => s,
-            None => {
-                bail!("incomprehensible format file name \"{}\"", name);
-            }
-        };
-
+        let stem = format_stem(name)?;
         let mut p = self.formats_base.clone();
         p.push(format!(
             "{}-{}-{}.fmt",
@@ -61,6 +54,187 @@ impl FormatCache {
         ));
         Ok(p)
     }
+
+    /// Get an on-disk path name for the list of bundle files needed to
+    /// generate a given format. Unlike the format files themselves, these
+    /// lists don't depend on the bundle digest, so that they remain useful
+    /// when the bundle is updated.
+    fn path_for_dependencies(&mut self, name: &str) -> Result<PathBuf> {
+        let stem = format_stem(name)?;
+        let mut p = self.formats_base.clone();
+        p.push(format!("{}-{}.deps", stem, crate::FORMAT_SERIAL));
+        Ok(p)
+    }
+
+    /// Get the names of the bundle files that were needed the last time that
+    /// the named format was generated.
+    ///
+    /// These can be prefetched from the bundle in bulk before generating the
+    /// format again. If no list has been saved, the return value is empty.
+    pub fn read_dependencies(&mut self, name: &str) -> Vec<String> {
+        self.path_for_dependencies(name)
+            .and_then(|p| Ok(fs::read_to_string(p)?))
+            .map(|text| text.lines().map(|l| l.to_owned()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Save the names of the bundle files that were needed to generate the
+    /// named format.
+    pub fn write_dependencies(&mut self, name: &str, names: &[String]) -> Result<()> {
+        let final_path = self.path_for_dependencies(name)?;
+        let mut temp_dest = tempfile::Builder::new()
+            .prefix("deps_")
+            .rand_bytes(6)
+            .tempfile_in(&self.formats_base)?;
+
+        for n in names {
+            writeln!(temp_dest, "{n}")?;
+        }
+
+        temp_dest.persist(final_path)?;
+        Ok(())
+    }
+}
+
+/// The maximum number of rounds of references followed by
+/// [`prefetch_format_sources`].
+const MAX_PREFETCH_ROUNDS: usize = 8;
+
+/// Prefetch the bundle files that are likely to be needed to generate a
+/// format, when there's no list saved from a previous generation.
+///
+/// Starting from the format's source file, we look for references to other
+/// files, such as `\input` commands, and prefetch all of the newly found files
+/// in one batch before scanning them in turn. This can't find every file that
+/// the format needs, since TeX can compute file names on the fly, but it lets a
+/// cold build get most of them in a few bulk requests, rather than one by one.
+pub fn prefetch_format_sources(
+    bundle: &mut dyn Bundle,
+    stem: &str,
+    status: &mut dyn StatusBackend,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    let mut round = vec![format!("tectonic-format-{stem}.tex")];
+
+    for _ in 0..MAX_PREFETCH_ROUNDS {
+        round.retain(|name| seen.insert(name.clone()));
+
+        if round.is_empty() {
+            break;
+        }
+
+        bundle.prefetch(&round, status)?;
+        let mut next = Vec::new();
+
+        for name in &round {
+            if let OpenResult::Ok(mut ih) = bundle.input_open_name(name, status) {
+                let mut data = Vec::new();
+
+                if ih.read_to_end(&mut data).is_ok() {
+                    next.extend(scan_tex_references(&String::from_utf8_lossy(&data)));
+                }
+            }
+        }
+
+        round = next;
+    }
+
+    Ok(())
+}
+
+/// Find the names of the files that some TeX source code refers to, as far as
+/// can be told without running TeX. Names without an extension are returned
+/// both as-is and with `.tex` added, since `\input` tries both.
+fn scan_tex_references(text: &str) -> Vec<String> {
+    const FILE_COMMANDS: &[(&str, &str)] = &[
+        ("input", ""),
+        ("@input", ""),
+        ("@@input", ""),
+        ("InputIfFileExists", ""),
+        ("documentclass", ".cls"),
+        ("LoadClass", ".cls"),
+        ("usepackage", ".sty"),
+        ("RequirePackage", ".sty"),
+    ];
+
+    let mut names = Vec::new();
+
+    for line in text.lines() {
+        // Drop comments, which start at a `%` that isn't escaped.
+        let line = match line
+            .char_indices()
+            .find(|&(i, c)| c == '%' && !line[..i].ends_with('\\'))
+        {
+            Some((i, _)) => &line[..i],
+            None => line,
+        };
+
+        for (pos, _) in line.match_indices('\\') {
+            let rest = &line[pos + 1..];
+            let cmd_len = rest
+                .find(|c: char| !(c.is_ascii_alphabetic() || c == '@'))
+                .unwrap_or(rest.len());
+            let cmd = &rest[..cmd_len];
+
+            let ext = match FILE_COMMANDS.iter().find(|(c, _)| *c == cmd) {
+                Some((_, ext)) => *ext,
+                None => continue,
+            };
+
+            let mut arg = rest[cmd_len..].trim_start();
+
+            // Skip package options.
+            if arg.starts_with('[') {
+                arg = match arg.find(']') {
+                    Some(i) => arg[i + 1..].trim_start(),
+                    None => continue,
+                };
+            }
+
+            let args: Vec<&str> = if let Some(braced) = arg.strip_prefix('{') {
+                match braced.find('}') {
+                    Some(i) => braced[..i].split(',').map(str::trim).collect(),
+                    None => continue,
+                }
+            } else {
+                let end = arg
+                    .find(|c: char| c.is_whitespace() || "\\{}".contains(c))
+                    .unwrap_or(arg.len());
+                vec![&arg[..end]]
+            };
+
+            for name in args {
+                // Skip empty names and ones computed by macros.
+                if name.is_empty() || name.contains(['\\', '#']) {
+                    continue;
+                }
+
+                if !ext.is_empty() {
+                    names.push(format!("{name}{ext}"));
+                } else if name.contains('.') {
+                    names.push(name.to_owned());
+                } else {
+                    names.push(name.to_owned());
+                    names.push(format!("{name}.tex"));
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Remove all extensions from a format name.
+#[allow(clippy::manual_split_once)] // requires Rust 1.52 (note that we don't actually define our MSRV)
+fn format_stem(name: &str) -> Result<&str> {
+    // PathBuf.file_stem() doesn't do what we want since it only strips one
+    // extension, so here we go:
+    match name.split('.').next() {
+        Some(s) => Ok(s),
+        None => {
+            bail!("incomprehensible format file name \"{}\"", name);
+        }
+    }
 }
 
 impl IoProvider for FormatCache {
@@ -103,3 +277,34 @@ impl IoProvider for FormatCache {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tex_references() {
+        let names = scan_tex_references(
+            "\\input fonttext.ltx\\relax\n\
+             \\input{hyphen.cfg} % \\input ignored.tex\n\
+             50\\% \\@@input plain\n\
+             \\InputIfFileExists{tectonic.cfg}{}{}\n\
+             \\RequirePackage[foo]{ amsmath ,amssymb}\\usepackage{\\jobname}\n\
+             \\LoadClass{article}\\input\\x \\inputlineno\n",
+        );
+
+        assert_eq!(
+            names,
+            vec![
+                "fonttext.ltx",
+                "hyphen.cfg",
+                "plain",
+                "plain.tex",
+                "tectonic.cfg",
+                "amsmath.sty",
+                "amssymb.sty",
+                "article.cls",
+            ]
+        );
+    }
+}
@@ -94,8 +94,12 @@ pub mod unstable_opts;
 pub mod test_util;
 
 pub use crate::engines::bibtex::BibtexEngine;
+pub use crate::engines::makeindex::{MakeindexEngine, MakeindexOutcome};
 pub use crate::engines::spx2html::Spx2HtmlEngine;
 pub use crate::engines::tex::{TexEngine, TexOutcome};
+pub use crate::engines::xdv2png::Xdv2PngEngine;
+pub use crate::engines::xdv2svg::Xdv2SvgEngine;
+pub use crate::engines::xdv2text::Xdv2TextEngine;
 pub use crate::engines::xdvipdfmx::XdvipdfmxEngine;
 pub use crate::errors::{Error, ErrorKind, Result};
 
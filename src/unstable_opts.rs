@@ -16,15 +16,19 @@ const HELPMSG: &str = r#"Available unstable options:
 
     -Z help                     List all unstable options
     -Z continue-on-errors       Keep compiling even when severe errors occur
+    -Z index-style=<file>       Use the makeindex style file <file> when generating indices
     -Z min-crossrefs=<num>      Equivalent to bibtex's -min-crossrefs flag - "include after <num>
                                     crossrefs" [default: 2]
     -Z paper-size=<spec>        Change the initial paper size [default: letter]
+    -Z png-dpi=<num>            Resolution of PNG output, in dots per inch [default: 100]
     -Z search-path=<path>       Also look in <path> for files (unless --untrusted has been specified),
                                     like TEXINPUTS. Can be specified multiple times.
     -Z shell-escape             Enable \write18 (unless --untrusted has been specified)
     -Z shell-escape-cwd=<path>  Working directory to use for \write18. Use $(pwd) for same behaviour as
                                     most other engines (e.g. for relative paths in \inputminted).
                                     Implies -Z shell-escape
+    -Z svg-embed-fonts          Embed font files into SVG output instead of writing them out
+                                    separately and referencing them
     -Z deterministic-mode       Force a deterministic build environment. Note that setting
                                     `SOURCE_DATE_EPOCH` is usually sufficient for reproducible builds,
                                     and this option makes some extra functionality trade-offs.
@@ -37,11 +41,14 @@ const HELPMSG: &str = r#"Available unstable options:
 pub enum UnstableArg {
     ContinueOnErrors,
     Help,
+    IndexStyle(String),
     MinCrossrefs(u32),
     PaperSize(String),
+    PngDpi(u32),
     SearchPath(PathBuf),
     ShellEscapeEnabled,
     ShellEscapeCwd(String),
+    SvgEmbedFonts,
     DeterministicModeEnabled,
 }
 
@@ -83,6 +90,8 @@ impl FromStr for UnstableArg {
 
             "continue-on-errors" => Ok(UnstableArg::ContinueOnErrors),
 
+            "index-style" => require_value("file").map(|s| UnstableArg::IndexStyle(s.to_string())),
+
             "min-crossrefs" => require_value("num")
                 .and_then(|s| {
                     FromStr::from_str(s).map_err(|e| format!("-Z min-crossrefs: {e}").into())
@@ -91,6 +100,10 @@ impl FromStr for UnstableArg {
 
             "paper-size" => require_value("spec").map(|s| UnstableArg::PaperSize(s.to_string())),
 
+            "png-dpi" => require_value("num")
+                .and_then(|s| FromStr::from_str(s).map_err(|e| format!("-Z png-dpi: {e}").into()))
+                .map(UnstableArg::PngDpi),
+
             "search-path" => require_value("path").map(|s| UnstableArg::SearchPath(s.into())),
 
             "shell-escape" => require_no_value(value, UnstableArg::ShellEscapeEnabled),
@@ -99,6 +112,8 @@ impl FromStr for UnstableArg {
                 require_value("path").map(|s| UnstableArg::ShellEscapeCwd(s.to_string()))
             }
 
+            "svg-embed-fonts" => require_no_value(value, UnstableArg::SvgEmbedFonts),
+
             "deterministic-mode" => require_no_value(value, UnstableArg::DeterministicModeEnabled),
 
             _ => Err(format!("Unknown unstable option '{arg}'").into()),
@@ -110,10 +125,13 @@ impl FromStr for UnstableArg {
 pub struct UnstableOptions {
     pub continue_on_errors: bool,
     pub paper_size: Option<String>,
+    pub png_dpi: Option<u32>,
     pub shell_escape: bool,
     pub min_crossrefs: Option<u32>,
+    pub index_style: Option<String>,
     pub extra_search_paths: Vec<PathBuf>,
     pub shell_escape_cwd: Option<String>,
+    pub svg_embed_fonts: bool,
 
     /// Ensure a deterministic build environment.
     ///
@@ -141,13 +159,16 @@ impl UnstableOptions {
                 Help => print_unstable_help_and_exit(),
                 ContinueOnErrors => opts.continue_on_errors = true,
                 MinCrossrefs(num) => opts.min_crossrefs = Some(num),
+                IndexStyle(s) => opts.index_style = Some(s),
                 PaperSize(size) => opts.paper_size = Some(size),
+                PngDpi(dpi) => opts.png_dpi = Some(dpi),
                 ShellEscapeEnabled => opts.shell_escape = true,
                 SearchPath(p) => opts.extra_search_paths.push(p),
                 ShellEscapeCwd(p) => {
                     opts.shell_escape_cwd = Some(p);
                     opts.shell_escape = true;
                 }
+                SvgEmbedFonts => opts.svg_embed_fonts = true,
                 DeterministicModeEnabled => opts.deterministic_mode = true,
             }
         }
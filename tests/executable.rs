@@ -485,6 +485,43 @@ fn bibtex_multiple_aux_files() {
     success_or_panic(&output);
 }
 
+/// The glossary registered in the `.aux` file is sorted with the style that
+/// it names, and the document is rerun to typeset it.
+#[test]
+fn glossaries() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["glossaries.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[
+            &fmt_arg,
+            "--outfmt=txt",
+            "--keep-intermediates",
+            "glossaries.tex",
+        ],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "glossaries.gls");
+
+    let chatter = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(chatter.contains("Rerunning"));
+    assert!(chatter.contains("\"glossaries.gls\""));
+
+    let text = fs::read_to_string(tempdir.path().join("glossaries.txt")).unwrap();
+    let aardvark = text
+        .find("aardvark")
+        .expect("glossary entries should be typeset");
+    let zebra = text
+        .find("zebra")
+        .expect("glossary entries should be typeset");
+    assert!(aardvark < zebra);
+}
+
 #[test]
 fn help_flag() {
     let output = run_tectonic(&PathBuf::from("."), &["-h"]);
@@ -512,6 +549,33 @@ fn keep_logs_on_error() {
     assert!(log.contains(r"job aborted, no legal \end found"));
 }
 
+/// The index written by the document is sorted by the built-in makeindex
+/// engine, and the document is rerun to typeset it.
+#[test]
+fn makeindex() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["makeindex.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[
+            &fmt_arg,
+            "--outfmt=txt",
+            "--keep-intermediates",
+            "makeindex.tex",
+        ],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "makeindex.ind");
+
+    let text = fs::read_to_string(tempdir.path().join("makeindex.txt")).unwrap();
+    let aardvark = text
+        .find("aardvark")
+        .expect("index entries should be typeset");
+    let zebra = text.find("zebra").expect("index entries should be typeset");
+    assert!(aardvark < zebra);
+}
+
 #[test]
 fn no_color_option() {
     // No input files here, but output files are created.
@@ -593,6 +657,152 @@ fn outdir_option_is_file() {
     success_or_panic(&output);
 }
 
+#[test]
+fn outfmt_incompatible() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=pdf,html", "subdirectory/content/1.tex"],
+    );
+    error_or_panic(&output);
+
+    // HTML comes from an SPX file, which is no substitute for XDV.
+    let output = run_tectonic(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=html,xdv", "subdirectory/content/1.tex"],
+    );
+    error_or_panic(&output);
+    assert!(!tempdir.path().join("subdirectory/content/1.spx").exists());
+}
+
+#[test]
+fn outfmt_multiple() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=pdf,xdv", "subdirectory/content/1.tex"],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "subdirectory/content/1.pdf");
+    check_file(&tempdir, "subdirectory/content/1.xdv");
+}
+
+#[test]
+fn outfmt_png() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[
+            &fmt_arg,
+            "--outfmt=png",
+            "-Zpng-dpi=20",
+            "subdirectory/content/1.tex",
+        ],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "subdirectory/content/1.png");
+
+    // Plain TeX's TFM-based text should have been drawn.
+    let png = fs::read(tempdir.path().join("subdirectory/content/1.png")).unwrap();
+    assert!(png_has_ink(&png));
+}
+
+/// Check whether a PNG file produced by the PNG output has any non-white
+/// pixels. That output is always unfiltered 8-bit RGB, so we only need to
+/// inflate the image data.
+fn png_has_ink(png: &[u8]) -> bool {
+    let be_u32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize;
+    let mut pos = 8;
+    let mut width = 0;
+    let mut compressed = Vec::new();
+
+    while pos + 8 <= png.len() {
+        let len = be_u32(&png[pos..]);
+        let data = &png[pos + 8..pos + 8 + len];
+
+        match &png[pos + 4..pos + 8] {
+            b"IHDR" => width = be_u32(data),
+            b"IDAT" => compressed.extend_from_slice(data),
+            _ => {}
+        }
+
+        pos += 12 + len;
+    }
+
+    let mut rows = Vec::new();
+    flate2::read::ZlibDecoder::new(&compressed[..])
+        .read_to_end(&mut rows)
+        .unwrap();
+
+    // Each row starts with its filter type, which we skip.
+    rows.chunks(1 + 3 * width)
+        .any(|row| row[1..].iter().any(|&b| b != 0xFF))
+}
+
+#[test]
+fn outfmt_svg() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=svg", "subdirectory/content/1.tex"],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "subdirectory/content/1.svg");
+    assert!(!tempdir.path().join("subdirectory/content/1.xdv").exists());
+
+    // Plain TeX uses TFM fonts, whose glyphs are drawn as paths.
+    let svg = fs::read_to_string(tempdir.path().join("subdirectory/content/1.svg")).unwrap();
+    assert!(svg.contains("<path id="));
+    assert!(svg.contains("<use xlink:href="));
+}
+
+#[test]
+fn outfmt_svg_native_font() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&[]);
+
+    // "fi" is set as a ligature glyph, which needs a character of its own.
+    let output = run_tectonic_with_stdin(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=svg", "-"],
+        "\\font\\x=\"[lmroman12-regular]\" \\x Hello figures\\bye\n",
+    );
+    success_or_panic(&output);
+
+    let svg = fs::read_to_string(tempdir.path().join("texput.svg")).unwrap();
+    assert!(svg.contains("<text class="));
+    assert!(svg.contains(">Hello</text>"));
+    assert!(svg.contains("src: url(\"texput-font0.otf\") format(\"opentype\")"));
+
+    // The font subset is an output in its own right, not a copy of the input.
+    check_file(&tempdir, "texput-font0.otf");
+    assert!(!tempdir.path().join("lmroman12-regular.otf").exists());
+}
+
+#[test]
+fn outfmt_txt() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[&fmt_arg, "--outfmt=txt", "subdirectory/content/1.tex"],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "subdirectory/content/1.txt");
+
+    let text = fs::read_to_string(tempdir.path().join("subdirectory/content/1.txt")).unwrap();
+    assert!(text.contains("This"));
+}
+
 #[test] // GitHub #31
 fn relative_include() {
     let fmt_arg = get_plain_format_arg();
@@ -609,6 +819,27 @@ fn relative_include() {
     check_file(&tempdir, "subdirectory/relative_include.pdf");
 }
 
+#[test]
+fn report_option() {
+    let fmt_arg = get_plain_format_arg();
+    let tempdir = setup_and_copy_files(&["subdirectory/content/1.tex"]);
+
+    let output = run_tectonic(
+        tempdir.path(),
+        &[
+            &fmt_arg,
+            "subdirectory/content/1.tex",
+            "--report=report.json",
+        ],
+    );
+    success_or_panic(&output);
+    check_file(&tempdir, "report.json");
+
+    let report = fs::read_to_string(tempdir.path().join("report.json")).unwrap();
+    assert!(report.contains("\"succeeded\": true"));
+    assert!(report.contains("\"engine\": \"xdvipdfmx\""));
+}
+
 // Regression #36
 #[test]
 fn space_in_filename() {
@@ -767,6 +998,25 @@ fn v2_bundle_overrides() {
     success_or_panic(&output);
 }
 
+#[cfg(feature = "serialization")]
+#[test]
+fn v2_bundle_export_minimal() {
+    let (_tempdir, temppath) = setup_v2();
+    let output = run_tectonic(
+        &temppath,
+        &["-X", "bundle", "export", "--minimal", "--dir", "minimal"],
+    );
+    success_or_panic(&output);
+
+    // Format generation needs these, even though the document doesn't
+    // reference them directly.
+    let minimal = temppath.join("minimal");
+    assert!(minimal.join("tectonic-format-plain.tex").exists());
+    assert!(minimal.join("hyphen.tex").exists());
+    assert!(minimal.join("SHA256SUM").exists());
+    assert!(!minimal.join("redbox.png").exists());
+}
+
 #[cfg(feature = "serialization")]
 #[test]
 fn v2_build_basic() {
@@ -775,6 +1025,41 @@ fn v2_build_basic() {
     success_or_panic(&output);
 }
 
+#[cfg(feature = "serialization")]
+#[test]
+fn v2_build_incremental() {
+    fn skipped(output: &Output) -> bool {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        stdout.contains("nothing has changed") || stderr.contains("nothing has changed")
+    }
+
+    let (_tempdir, temppath) = setup_v2();
+    let output = run_tectonic(&temppath, &["-X", "build"]);
+    success_or_panic(&output);
+    assert!(!skipped(&output));
+
+    let output = run_tectonic(&temppath, &["-X", "build"]);
+    success_or_panic(&output);
+    assert!(skipped(&output));
+
+    let output = run_tectonic(&temppath, &["-X", "build", "--force"]);
+    success_or_panic(&output);
+    assert!(!skipped(&output));
+
+    {
+        let mut path = temppath.clone();
+        path.push("src");
+        path.push("index.tex");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "Hello, changed world!").unwrap();
+    }
+
+    let output = run_tectonic(&temppath, &["-X", "build"]);
+    success_or_panic(&output);
+    assert!(!skipped(&output));
+}
+
 #[test]
 #[cfg(all(feature = "serialization", not(windows)))] // `echo` may not be available
 fn v2_build_open() {
@@ -864,6 +1149,47 @@ fn v2_build_multiple_outputs() {
     success_or_panic(&output);
 }
 
+#[cfg(feature = "serialization")]
+#[test]
+fn v2_build_workspace() {
+    let (tempdir, temppath) = setup_v2();
+    let root = tempdir.path();
+
+    // Make a second document from a copy of the first one.
+
+    let other = root.join("other");
+    fs::create_dir_all(other.join("src")).unwrap();
+
+    for name in ["_preamble.tex", "index.tex", "_postamble.tex"] {
+        fs::copy(
+            temppath.join("src").join(name),
+            other.join("src").join(name),
+        )
+        .unwrap();
+    }
+
+    let toml = fs::read_to_string(temppath.join("Tectonic.toml")).unwrap();
+    fs::write(
+        other.join("Tectonic.toml"),
+        toml.replace("name = \"doc\"", "name = \"other\""),
+    )
+    .unwrap();
+
+    fs::write(
+        root.join("Tectonic.toml"),
+        "[workspace]\nmembers = [\"doc\", \"other\"]\n",
+    )
+    .unwrap();
+
+    let output = run_tectonic(root, &["-X", "build", "--document", "nonexistent"]);
+    error_or_panic(&output);
+
+    let output = run_tectonic(root, &["-X", "build"]);
+    success_or_panic(&output);
+    check_file(&tempdir, "doc/build/default/default.pdf");
+    check_file(&tempdir, "other/build/default/default.pdf");
+}
+
 #[test]
 #[cfg(feature = "serialization")]
 fn v2_dump_basic() {
@@ -884,6 +1210,54 @@ fn v2_dump_basic() {
     assert!(saw_it);
 }
 
+#[test]
+#[cfg(feature = "serialization")]
+fn v2_dump_metadata() {
+    let (_tempdir, temppath) = setup_v2();
+
+    {
+        let mut path = temppath.clone();
+        path.push("src");
+        path.push("index.tex");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(
+            br"\immediate\write-1{META[\tectonicmeta{title}][\tectonicmeta{notes.0}]}Hello",
+        )
+        .unwrap();
+    }
+
+    // `\tectonicmeta` can be used even if there are no metadata.
+    let output = run_tectonic(&temppath, &["-X", "dump", "default.log"]);
+    success_or_panic(&output);
+
+    // Values with TeX special characters are inserted literally.
+    {
+        let mut toml_path = temppath.clone();
+        toml_path.push("Tectonic.toml");
+        let mut file = OpenOptions::new().append(true).open(toml_path).unwrap();
+        file.write_all(
+            br#"
+[doc.metadata]
+title = '50% of {x #1 \relax ~$'
+notes = ["""a
+
+b}"""]
+"#,
+        )
+        .unwrap();
+    }
+
+    let output = run_tectonic(&temppath, &["-X", "dump", "default.log"]);
+    success_or_panic(&output);
+
+    let t = std::str::from_utf8(&output.stdout[..]).unwrap();
+    assert!(
+        t.contains(r"META[50% of {x #1 \relax ~$][a b}]"),
+        "unexpected log:\n{}",
+        t
+    );
+}
+
 #[test]
 #[cfg(feature = "serialization")]
 fn v2_dump_suffix() {